@@ -11,6 +11,11 @@ pub enum ErrorCode {
     ValidationFailed,
     InvalidMode,
     RequestTooLarge,
+    NoMatchingProfile,
+    DuplicateRequestId,
+    ApprovalQueueFull,
+    MixedApprovalPlan,
+    ChallengeFailed,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
@@ -18,11 +23,20 @@ pub enum ErrorCode {
 pub enum ActionErrorCode {
     PolicyDenied,
     ConfirmationRequired,
+    ApprovalRequired,
+    ApprovalFailed,
+    GrantFailed,
     ExecFailed,
     ExecTimedOut,
     ReadFailed,
     WriteFailed,
     InvalidModeString,
+    ServiceFailed,
+    PackageFailed,
+    CgroupFailed,
+    FirmwareFailed,
+    ObserveFailed,
+    LogQueryFailed,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
@@ -30,6 +44,7 @@ pub enum ActionErrorCode {
 pub enum Mode {
     PlanOnly,
     Execute,
+    Explain,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
@@ -47,6 +62,17 @@ pub struct ActionPlan {
 #[serde(deny_unknown_fields)]
 pub struct Confirmation {
     pub token: String,
+    // the challenge nonce from a previous `confirmation_required` response for this exact plan.
+    pub nonce: Option<String>,
+}
+
+// issued with `confirmation_required`; valid once, for the same plan from the same peer uid.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ConfirmationChallenge {
+    pub nonce: String,
+    pub plan_hash: String,
+    pub expires_at_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
@@ -60,11 +86,77 @@ pub enum Action {
     RemovePackages(RemovePackagesAction),
     UpdateSystem(UpdateSystemAction),
     Observe(ObserveAction),
+    LogQuery(LogQueryAction),
     CgroupApply(CgroupApplyAction),
+    CgroupInspect(CgroupInspectAction),
     FirmwareOp(FirmwareOpAction),
+    Approval(ApprovalAction),
+    SessionGrant(SessionGrantAction),
     Ping,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalOp {
+    List,
+    Show,
+    Approve,
+    Reject,
+}
+
+// operates the daemon's queue of plans parked for a second person; `request_id` names the parked plan.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ApprovalAction {
+    pub op: ApprovalOp,
+    pub request_id: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionGrantOp {
+    Grant,
+    List,
+    Revoke,
+}
+
+// manages time-bounded grants for the plan's `session_id`; `scope` and `ttl_sec` apply to `grant`,
+// `grant_id` to `revoke`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SessionGrantAction {
+    pub op: SessionGrantOp,
+    pub scope: Option<GrantScope>,
+    pub ttl_sec: Option<u64>,
+    pub grant_id: Option<String>,
+    pub note: Option<String>,
+}
+
+// what a grant lets through without confirmation; it never relaxes `approve` or `deny` decisions.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GrantScope {
+    Exec(ExecGrant),
+    ReadFile(PathGrant),
+    WriteFile(PathGrant),
+}
+
+// `program` matches the resolved executable path (globs allowed). `args` holds one glob per
+// argument, and a final `**` matches any remaining arguments; any arguments match when omitted.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ExecGrant {
+    pub program: String,
+    pub args: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PathGrant {
+    pub root: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum FirmwareOp {
@@ -90,6 +182,42 @@ pub struct CgroupApplyAction {
     pub unit: Option<String>,
     pub cpu_weight: Option<u64>,
     pub mem_max_bytes: Option<u64>,
+    pub cpu_max: Option<CpuMax>,
+    pub mem_high_bytes: Option<u64>,
+    pub pids_max: Option<u64>,
+    pub io_weight: Option<u64>,
+    pub io_max: Option<Vec<IoMax>>,
+    pub cpuset_cpus: Option<String>,
+    pub reason: String,
+    pub danger: Option<String>,
+    pub recovery: Option<String>,
+}
+
+// a cpu bandwidth limit: `quota_us` of cpu time per `period_us`; a null quota removes the limit.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CpuMax {
+    pub quota_us: Option<u64>,
+    pub period_us: Option<u64>,
+}
+
+// io limits for one block device, named `major:minor`; a null limit means unlimited.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct IoMax {
+    pub device: String,
+    pub rbps: Option<u64>,
+    pub wbps: Option<u64>,
+    pub riops: Option<u64>,
+    pub wiops: Option<u64>,
+}
+
+// reads usage and pressure without changing anything; a pid is inspected in the cgroup it is in now.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CgroupInspectAction {
+    pub pid: Option<u32>,
+    pub unit: Option<String>,
     pub reason: String,
     pub danger: Option<String>,
     pub recovery: Option<String>,
@@ -110,16 +238,107 @@ pub enum ObserveTool {
 #[serde(deny_unknown_fields)]
 pub struct ObserveAction {
     pub tool: ObserveTool,
+    // with tool `other`, the name of a tool the daemon's operator registered.
+    pub name: Option<String>,
     pub args: Vec<String>,
+    // ps and top read /proc natively and take this query instead of args.
+    pub processes: Option<ProcessQuery>,
+    // seconds before the tool is stopped; unset means the daemon's default for the tool,
+    // and the daemon rejects values above its per-tool maximum.
+    pub timeout_sec: Option<u64>,
+    pub reason: String,
+    pub danger: Option<String>,
+    pub recovery: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessField {
+    Pid,
+    Ppid,
+    User,
+    State,
+    Name,
+    Cmdline,
+    RssBytes,
+    CpuTimeMs,
+    StartTimeMs,
+}
+
+// filters are combined with and; `name` matches a substring of the name or the command line.
+// without `sort_by`, ps sorts by pid and top by cpu time, highest first.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessQuery {
+    pub user: Option<String>,
+    pub name: Option<String>,
+    pub state: Option<String>,
+    pub ppid: Option<u32>,
+    pub sort_by: Option<ProcessField>,
+    pub descending: Option<bool>,
+    pub limit: Option<u32>,
+    // the fields to return; pid is always included.
+    pub fields: Option<Vec<ProcessField>>,
+}
+
+// reads the systemd journal with typed filters; the daemon builds the journalctl command line itself.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LogQueryAction {
+    pub unit: Option<String>,
+    pub priority: Option<LogPriorityRange>,
+    // journalctl time specs: `YYYY-MM-DD[ HH:MM[:SS]]`, `now`, `today`, `yesterday`, or `-<n>s|m|h|d`.
+    pub since: Option<String>,
+    pub until: Option<String>,
+    // a regular expression matched against the message; all-lowercase patterns ignore case.
+    pub grep: Option<String>,
+    // the newest matching entries are returned, oldest first; defaults to 100.
+    pub max_entries: Option<u32>,
     pub reason: String,
     pub danger: Option<String>,
     pub recovery: Option<String>,
 }
 
+// syslog levels, most severe first.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum LogPriority {
+    Emerg,
+    Alert,
+    Crit,
+    Err,
+    Warning,
+    Notice,
+    Info,
+    Debug,
+}
+
+// `from` is the most severe level to include and `to` the least, so emerg..err is errors and worse.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LogPriorityRange {
+    pub from: LogPriority,
+    pub to: LogPriority,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateScope {
+    // refresh the package metadata, then upgrade everything.
+    #[default]
+    Full,
+    // refresh the package metadata only.
+    RefreshOnly,
+    // refresh, then apply security updates only.
+    SecurityOnly,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct UpdateSystemAction {
     pub manager: PackageManager,
+    // omitted means `full`.
+    pub scope: Option<UpdateScope>,
     pub reason: String,
     pub danger: Option<String>,
     pub recovery: Option<String>,
@@ -215,15 +434,65 @@ pub fn parse_action_plan(input: &str) -> Result<ActionPlan, serde_json::Error> {
     serde_json::from_str(input)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct ActionPlanResult {
     pub request_id: String,
     pub executed: bool,
     pub results: Vec<ActionResult>,
+    pub explanations: Option<Vec<ActionExplanation>>,
+    pub challenge: Option<ConfirmationChallenge>,
+    // set when the plan was parked for a second person instead of being executed.
+    pub approval: Option<ApprovalStatus>,
+    // session grants that stood in for confirmation, by action index.
+    pub grants_applied: Option<Vec<GrantUse>>,
     pub error: Option<RequestError>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct GrantUse {
+    pub index: usize,
+    pub grant_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SessionGrant {
+    pub grant_id: String,
+    pub session_id: String,
+    pub uid: Option<u32>,
+    pub scope: GrantScope,
+    pub granted_at_ms: u64,
+    pub expires_at_ms: u64,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalState {
+    Pending,
+    Approved,
+    Rejected,
+    Expired,
+}
+
+// one parked plan; `plan` and `result` are only filled in where the caller asked for the details.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ApprovalStatus {
+    pub request_id: String,
+    pub state: ApprovalState,
+    pub requested_by: Option<u32>,
+    pub requested_at_ms: u64,
+    pub expires_at_ms: u64,
+    pub decided_by: Option<u32>,
+    pub decided_at_ms: Option<u64>,
+    pub note: Option<String>,
+    pub plan: Option<ActionPlan>,
+    pub result: Option<Box<ActionPlanResult>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct RequestError {
@@ -236,9 +505,38 @@ pub struct RequestError {
 pub struct ActionError {
     pub code: ActionErrorCode,
     pub message: String,
+    pub policy: Option<PolicyDetail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyDecision {
+    Allow,
+    Confirm,
+    Approve,
+    Deny,
+}
+
+// identifies the policy rule behind a decision so clients can tell why an action was gated.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyDetail {
+    pub rule_id: String,
+    pub source: String,
+    pub decision: PolicyDecision,
+    pub hint: String,
 }
 
+// the policy trace for one action in explain mode; `decision` is the strictest step.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ActionExplanation {
+    pub action: String,
+    pub decision: PolicyDecision,
+    pub trace: Vec<PolicyDetail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ActionResult {
     Exec(ExecResult),
@@ -249,27 +547,133 @@ pub enum ActionResult {
     RemovePackages(RemovePackagesResult),
     UpdateSystem(UpdateSystemResult),
     Observe(ObserveResult),
+    LogQuery(LogQueryResult),
     CgroupApply(CgroupApplyResult),
+    CgroupInspect(CgroupInspectResult),
     FirmwareOp(FirmwareOpResult),
+    Approval(ApprovalResult),
+    SessionGrant(SessionGrantResult),
     Pong(PongResult),
 }
 
+impl ActionResult {
+    pub fn error(&self) -> Option<&ActionError> {
+        match self {
+            ActionResult::Exec(r) => r.error.as_ref(),
+            ActionResult::ReadFile(r) => r.error.as_ref(),
+            ActionResult::WriteFile(r) => r.error.as_ref(),
+            ActionResult::ServiceControl(r) => r.error.as_ref(),
+            ActionResult::InstallPackages(r) => r.error.as_ref(),
+            ActionResult::RemovePackages(r) => r.error.as_ref(),
+            ActionResult::UpdateSystem(r) => r.error.as_ref(),
+            ActionResult::Observe(r) => r.error.as_ref(),
+            ActionResult::LogQuery(r) => r.error.as_ref(),
+            ActionResult::CgroupApply(r) => r.error.as_ref(),
+            ActionResult::CgroupInspect(r) => r.error.as_ref(),
+            ActionResult::FirmwareOp(r) => r.error.as_ref(),
+            ActionResult::Approval(r) => r.error.as_ref(),
+            ActionResult::SessionGrant(r) => r.error.as_ref(),
+            ActionResult::Pong(_) => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct FirmwareOpResult {
     pub ok: bool,
+    // always empty: the native ops run no command, and fwupd_update is not supported.
     pub argv: Vec<String>,
+    pub inventory: Option<Box<FirmwareInventory>>,
+    pub uefi_var: Option<UefiVariable>,
     pub error: Option<ActionError>,
 }
 
+// identity strings from /sys/class/dmi/id; fields the firmware leaves out stay null.
+// serial numbers and uuids are deliberately not read.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FirmwareInventory {
+    pub vendor: Option<String>,
+    pub product: Option<String>,
+    pub product_version: Option<String>,
+    pub bios_vendor: Option<String>,
+    pub bios_version: Option<String>,
+    pub bios_date: Option<String>,
+    pub board_vendor: Option<String>,
+    pub board_name: Option<String>,
+    pub board_version: Option<String>,
+}
+
+// an efivarfs entry: the 4-byte little-endian attribute header, then the variable's data.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct UefiVariable {
+    pub name: String,
+    pub attributes: u32,
+    // EFI_VARIABLE_* flag names set in `attributes`, such as `non_volatile` and `runtime_access`.
+    pub attribute_names: Vec<String>,
+    pub data_base64: String,
+    pub size: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct CgroupApplyResult {
     pub ok: bool,
-    pub argv: Vec<String>,
+    // the delegated cgroup, relative to the cgroupfs root as in /proc/<pid>/cgroup.
+    pub cgroup: String,
+    pub created: bool,
+    pub pid: Option<u32>,
+    // where the pid was before the move; writing it to that cgroup's cgroup.procs moves it back.
+    pub previous_cgroup: Option<String>,
+    pub files: Vec<CgroupFile>,
+    pub error: Option<ActionError>,
+}
+
+// one interface file write; `previous` and `effective` stay null in plan_only mode.
+// for io.max and io.weight they hold only the line for the device that was written.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CgroupFile {
+    pub file: String,
+    pub requested: String,
+    pub previous: Option<String>,
+    pub effective: Option<String>,
+}
+
+// files a controller does not provide are left empty rather than failing the action.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CgroupInspectResult {
+    pub ok: bool,
+    pub cgroup: String,
+    pub memory_current: Option<u64>,
+    // counters from cpu.stat such as usage_usec, nr_throttled and throttled_usec.
+    pub cpu_stat: std::collections::BTreeMap<String, u64>,
+    pub pressure: Vec<CgroupPressure>,
     pub error: Option<ActionError>,
 }
 
+// one `<resource>.pressure` file; `full` is absent for cpu on older kernels.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CgroupPressure {
+    pub resource: String,
+    pub some: Option<PressureStall>,
+    pub full: Option<PressureStall>,
+}
+
+// averages are percentages of wall time stalled over 10, 60 and 300 seconds.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PressureStall {
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+    pub total_usec: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct ObserveResult {
@@ -280,22 +684,118 @@ pub struct ObserveResult {
     pub stdout_truncated: bool,
     pub stderr: String,
     pub stderr_truncated: bool,
+    // one entry per element of `args`, in order.
+    pub arg_classes: Vec<ObserveArgClass>,
+    pub processes: Option<ProcessSnapshot>,
+    // the timeout the tool runs (or would run) under; none for ps and top.
+    pub timeout_sec: Option<u64>,
     pub error: Option<ActionError>,
 }
 
+// how the daemon's argument grammar for the tool classified one argument; a flag's separate
+// value shares the flag's class, and arguments the grammar does not know are denied.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
-pub struct UpdateSystemResult {
+pub struct ObserveArgClass {
+    pub arg: String,
+    pub decision: PolicyDecision,
+    pub rule_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessSnapshot {
+    // processes that matched the filters, before the limit was applied.
+    pub matched: u32,
+    pub processes: Vec<ProcessInfo>,
+}
+
+// fields left out of the query's `fields` are omitted from the json rather than sent as null,
+// so a narrow query stays small.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ppid: Option<u32>,
+    // the account name, or the numeric uid when it has none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    // the single-letter state from /proc/<pid>/stat, such as R, S, D or Z.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // arguments joined with spaces; empty for kernel threads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cmdline: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss_bytes: Option<u64>,
+    // user plus system time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time_ms: Option<u64>,
+    // unix time in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LogQueryResult {
     pub ok: bool,
     pub argv: Vec<String>,
+    pub entries: Vec<LogEntry>,
+    pub error: Option<ActionError>,
+}
+
+// one journal entry; fields the entry does not carry stay null.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LogEntry {
+    // unix time in microseconds.
+    pub timestamp_us: u64,
+    pub unit: Option<String>,
+    pub priority: Option<LogPriority>,
+    pub pid: Option<u32>,
+    pub message: String,
+    // the message was cut short, or the journal left out a message too large to print.
+    pub message_truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateSystemResult {
+    pub ok: bool,
+    pub steps: Vec<UpdateStep>,
     pub error: Option<ActionError>,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateStepState {
+    // plan_only: the step would run.
+    Planned,
+    Succeeded,
+    Failed,
+    // an earlier step failed, so this one did not run.
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateStep {
+    pub argv: Vec<String>,
+    pub state: UpdateStepState,
+    pub exit_code: Option<i32>,
+    pub detail: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct RemovePackagesResult {
     pub ok: bool,
     pub argv: Vec<String>,
+    pub packages: Vec<PackageOutcome>,
     pub error: Option<ActionError>,
 }
 
@@ -304,14 +804,62 @@ pub struct RemovePackagesResult {
 pub struct InstallPackagesResult {
     pub ok: bool,
     pub argv: Vec<String>,
+    pub packages: Vec<PackageOutcome>,
     pub error: Option<ActionError>,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageState {
+    Installed,
+    AlreadyPresent,
+    Removed,
+    NotInstalled,
+    NotFound,
+    Failed,
+}
+
+// one entry per requested package, in request order; empty in plan_only mode.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PackageOutcome {
+    pub name: String,
+    pub state: PackageState,
+    pub detail: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct ServiceControlResult {
     pub ok: bool,
     pub argv: Vec<String>,
+    pub status: Option<ServiceStatus>,
+    pub error: Option<ActionError>,
+}
+
+// the unit's state as systemd reports it; only set for the `status` verb.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceStatus {
+    pub load_state: String,
+    pub active_state: String,
+    pub sub_state: String,
+    pub main_pid: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ApprovalResult {
+    pub ok: bool,
+    pub entries: Vec<ApprovalStatus>,
+    pub error: Option<ActionError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct SessionGrantResult {
+    pub ok: bool,
+    pub grants: Vec<SessionGrant>,
     pub error: Option<ActionError>,
 }
 
@@ -319,17 +867,39 @@ pub struct ServiceControlResult {
 #[serde(deny_unknown_fields)]
 pub struct PongResult {
     pub ok: bool,
+    // the registered tools observe can run with tool `other`, for the requesting peer's policy.
+    #[serde(default)]
+    pub observe_tools: Vec<ObserveToolInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ObserveToolInfo {
+    pub name: String,
+    pub description: Option<String>,
+    // flags the tool accepts after its fixed ones, spelled with `<value>` where one goes.
+    pub allowed_flags: Vec<String>,
+    // used when an action sets no timeout_sec; an action may ask for up to max_timeout_sec.
+    pub timeout_sec: u64,
+    pub max_timeout_sec: u64,
+    pub max_output_bytes: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
 pub struct ExecResult {
     pub ok: bool,
+    pub resolved_path: Option<String>,
     pub exit_code: Option<i32>,
     pub stdout: String,
     pub stdout_truncated: bool,
     pub stderr: String,
     pub stderr_truncated: bool,
+    // set when a timeout killed the process group; the count is the group's size at the time.
+    #[serde(default)]
+    pub killed: bool,
+    #[serde(default)]
+    pub killed_processes: u32,
     pub error: Option<ActionError>,
 }
 
@@ -337,6 +907,7 @@ pub struct ExecResult {
 #[serde(deny_unknown_fields)]
 pub struct ReadFileResult {
     pub ok: bool,
+    pub resolved_path: Option<String>,
     pub content_base64: Option<String>,
     pub truncated: bool,
     pub error: Option<ActionError>,
@@ -346,6 +917,7 @@ pub struct ReadFileResult {
 #[serde(deny_unknown_fields)]
 pub struct WriteFileResult {
     pub ok: bool,
+    pub resolved_path: Option<String>,
     pub artifacts: Vec<String>,
     pub error: Option<ActionError>,
 }
@@ -355,6 +927,28 @@ pub struct ValidationError {
     pub message: String,
 }
 
+// the subset of systemd.time(7) log_query accepts for since and until.
+fn is_journal_time(spec: &str) -> bool {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let shape = |s: &str, pattern: &str| {
+        s.len() == pattern.len() && s.bytes().zip(pattern.bytes()).all(|(c, p)| if p == b'0' { c.is_ascii_digit() } else { c == p })
+    };
+    if matches!(spec, "now" | "today" | "yesterday") {
+        return true;
+    }
+    if let Some(relative) = spec.strip_prefix('-') {
+        return relative
+            .strip_suffix(['s', 'm', 'h', 'd'])
+            .is_some_and(|n| digits(n) && n.len() <= 6);
+    }
+    match spec.split_once(' ') {
+        Some((date, time)) => shape(date, "0000-00-00") && (shape(time, "00:00") || shape(time, "00:00:00")),
+        None => shape(spec, "0000-00-00"),
+    }
+}
+
+// byte lengths are spelled out as `as_bytes().len()` to make the unit of each limit explicit.
+#[allow(clippy::needless_as_bytes)]
 pub fn validate_action_plan(plan: &ActionPlan) -> Result<(), ValidationError> {
     const MAX_READ_FILE_BYTES: u64 = 64 * 1024;
     const MAX_WRITE_FILE_BYTES: usize = 64 * 1024;
@@ -372,11 +966,47 @@ pub fn validate_action_plan(plan: &ActionPlan) -> Result<(), ValidationError> {
     const MAX_MODE_BYTES: usize = 128;
     const MAX_EXEC_TIMEOUT_SEC: u64 = 60;
     const MAX_SYSTEMD_UNIT_BYTES: usize = 256;
+    const MAX_CGROUP_CPU_WEIGHT: u64 = 10000;
+    const MIN_CGROUP_CPU_PERIOD_US: u64 = 1000;
+    const MAX_CGROUP_CPU_PERIOD_US: u64 = 1_000_000;
+    const MAX_CGROUP_CPU_QUOTA_US: u64 = 100_000_000;
+    // the kernel's PID_MAX_LIMIT on 64-bit systems.
+    const MAX_CGROUP_PIDS: u64 = 4_194_304;
+    const MAX_CGROUP_IO_DEVICES: usize = 16;
+    const MAX_CGROUP_CPUSET_BYTES: usize = 256;
     const MAX_PACKAGE_NAME_BYTES: usize = 128;
     const MAX_PACKAGES: usize = 128;
     const MAX_OBSERVE_ARGS: usize = 64;
     const MAX_OBSERVE_ARG_BYTES: usize = 2048;
+    const MAX_OBSERVE_TOOL_NAME_BYTES: usize = 64;
+    const MAX_OBSERVE_TIMEOUT_SEC: u64 = 600;
+    const MAX_PROCESS_LIMIT: u32 = 1000;
+    const MAX_PROCESS_FILTER_BYTES: usize = 256;
+    const MAX_LOG_ENTRIES: u32 = 1000;
+    const MAX_LOG_GREP_BYTES: usize = 512;
     const MAX_UEFI_VAR_NAME_BYTES: usize = 256;
+    const MAX_GRANT_TTL_SEC: u64 = 24 * 60 * 60;
+
+    // cgroup actions name exactly one target; a unit becomes a directory under the delegated cgroup.
+    let cgroup_target = |kind: &str, pid: Option<u32>, unit: Option<&str>| {
+        let message = match (pid, unit) {
+            (None, None) => format!("{kind} requires pid or unit"),
+            (Some(_), Some(_)) => format!("{kind} must not set both pid and unit"),
+            (_, Some(unit)) if unit.trim().is_empty() => format!("{kind}.unit must be non-empty when provided"),
+            (_, Some(unit)) if unit.len() > MAX_SYSTEMD_UNIT_BYTES => format!("{kind}.unit is too long"),
+            (_, Some(unit))
+                if !unit
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@' | ':'))
+                    || unit.starts_with('.')
+                    || unit.starts_with("cgroup.") =>
+            {
+                format!("{kind}.unit must be a plain cgroup name")
+            }
+            _ => return Ok(()),
+        };
+        Err(ValidationError { message })
+    };
 
     if plan.actions.len() > MAX_ACTIONS {
         return Err(ValidationError {
@@ -419,6 +1049,18 @@ pub fn validate_action_plan(plan: &ActionPlan) -> Result<(), ValidationError> {
                 message: "confirmation.token is too long".to_string(),
             });
         }
+        if let Some(nonce) = &conf.nonce {
+            if nonce.trim().is_empty() {
+                return Err(ValidationError {
+                    message: "confirmation.nonce must be non-empty".to_string(),
+                });
+            }
+            if nonce.len() > 128 {
+                return Err(ValidationError {
+                    message: "confirmation.nonce is too long".to_string(),
+                });
+            }
+        }
     }
 
     if plan.version.trim().is_empty() {
@@ -678,6 +1320,16 @@ pub fn validate_action_plan(plan: &ActionPlan) -> Result<(), ValidationError> {
                             message: "install_packages.packages entry is too long".to_string(),
                         });
                     }
+                    if pkg.starts_with('-') {
+                        return Err(ValidationError {
+                            message: "install_packages.packages entries must not start with '-'".to_string(),
+                        });
+                    }
+                    if !is_package_name(pkg) {
+                        return Err(ValidationError {
+                            message: "install_packages.packages entries must be repository package names".to_string(),
+                        });
+                    }
                 }
                 if pkgs.reason.trim().is_empty() {
                     return Err(ValidationError {
@@ -712,6 +1364,16 @@ pub fn validate_action_plan(plan: &ActionPlan) -> Result<(), ValidationError> {
                             message: "remove_packages.packages entry is too long".to_string(),
                         });
                     }
+                    if pkg.starts_with('-') {
+                        return Err(ValidationError {
+                            message: "remove_packages.packages entries must not start with '-'".to_string(),
+                        });
+                    }
+                    if !is_package_name(pkg) {
+                        return Err(ValidationError {
+                            message: "remove_packages.packages entries must be repository package names".to_string(),
+                        });
+                    }
                 }
                 if pkgs.reason.trim().is_empty() {
                     return Err(ValidationError {
@@ -754,6 +1416,75 @@ pub fn validate_action_plan(plan: &ActionPlan) -> Result<(), ValidationError> {
                         });
                     }
                 }
+                match (&obs.tool, &obs.name) {
+                    (ObserveTool::Other, None) => {
+                        return Err(ValidationError {
+                            message: "observe.name must be set for tool other".to_string(),
+                        });
+                    }
+                    (ObserveTool::Other, Some(name)) => {
+                        if name.is_empty() || name.len() > MAX_OBSERVE_TOOL_NAME_BYTES {
+                            return Err(ValidationError {
+                                message: "observe.name must be non-empty and short".to_string(),
+                            });
+                        }
+                    }
+                    (_, Some(_)) => {
+                        return Err(ValidationError {
+                            message: "observe.name is only valid for tool other".to_string(),
+                        });
+                    }
+                    (_, None) => {}
+                }
+                let native = matches!(obs.tool, ObserveTool::Ps | ObserveTool::Top);
+                if native && !obs.args.is_empty() {
+                    return Err(ValidationError {
+                        message: "observe.args must be empty for ps and top; use observe.processes".to_string(),
+                    });
+                }
+                if let Some(timeout_sec) = obs.timeout_sec {
+                    if native {
+                        return Err(ValidationError {
+                            message: "observe.timeout_sec is not valid for ps and top".to_string(),
+                        });
+                    }
+                    if timeout_sec == 0 {
+                        return Err(ValidationError {
+                            message: "observe.timeout_sec must be >= 1".to_string(),
+                        });
+                    }
+                    if timeout_sec > MAX_OBSERVE_TIMEOUT_SEC {
+                        return Err(ValidationError {
+                            message: "observe.timeout_sec is too large".to_string(),
+                        });
+                    }
+                }
+                if let Some(query) = &obs.processes {
+                    if !native {
+                        return Err(ValidationError {
+                            message: "observe.processes is only valid for ps and top".to_string(),
+                        });
+                    }
+                    if query.limit.is_some_and(|limit| limit == 0 || limit > MAX_PROCESS_LIMIT) {
+                        return Err(ValidationError {
+                            message: format!("observe.processes.limit must be between 1 and {MAX_PROCESS_LIMIT}"),
+                        });
+                    }
+                    let filters = [&query.user, &query.name, &query.state];
+                    if filters.iter().any(|f| f.as_ref().is_some_and(|f| f.is_empty() || f.len() > MAX_PROCESS_FILTER_BYTES)) {
+                        return Err(ValidationError {
+                            message: "observe.processes filters must be non-empty and short".to_string(),
+                        });
+                    }
+                    if let Some(fields) = &query.fields {
+                        let unique: std::collections::HashSet<_> = fields.iter().collect();
+                        if fields.is_empty() || unique.len() != fields.len() {
+                            return Err(ValidationError {
+                                message: "observe.processes.fields must be non-empty and list each field once".to_string(),
+                            });
+                        }
+                    }
+                }
                 if obs.reason.trim().is_empty() {
                     return Err(ValidationError {
                         message: "observe.reason must be non-empty".to_string(),
@@ -765,30 +1496,150 @@ pub fn validate_action_plan(plan: &ActionPlan) -> Result<(), ValidationError> {
                     });
                 }
             }
+            Action::LogQuery(q) => {
+                if let Some(unit) = &q.unit {
+                    // journalctl would expand globs, which policy rules keyed by unit could not see.
+                    let plain = unit
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@' | ':' | '\\'));
+                    if unit.is_empty() || unit.len() > MAX_SYSTEMD_UNIT_BYTES || !plain || unit.starts_with('-') {
+                        return Err(ValidationError {
+                            message: "log_query.unit must be a unit name".to_string(),
+                        });
+                    }
+                }
+                if let Some(priority) = &q.priority {
+                    if priority.from > priority.to {
+                        return Err(ValidationError {
+                            message: "log_query.priority.from must be at least as severe as priority.to".to_string(),
+                        });
+                    }
+                }
+                for (field, spec) in [("since", &q.since), ("until", &q.until)] {
+                    if spec.as_deref().is_some_and(|spec| !is_journal_time(spec)) {
+                        return Err(ValidationError {
+                            message: format!(
+                                "log_query.{field} must be YYYY-MM-DD[ HH:MM[:SS]], now, today, yesterday or -<n>s|m|h|d"
+                            ),
+                        });
+                    }
+                }
+                if q.grep.as_ref().is_some_and(|grep| grep.is_empty() || grep.len() > MAX_LOG_GREP_BYTES) {
+                    return Err(ValidationError {
+                        message: "log_query.grep must be non-empty and short".to_string(),
+                    });
+                }
+                if q.max_entries.is_some_and(|n| n == 0 || n > MAX_LOG_ENTRIES) {
+                    return Err(ValidationError {
+                        message: format!("log_query.max_entries must be between 1 and {MAX_LOG_ENTRIES}"),
+                    });
+                }
+                if q.reason.trim().is_empty() {
+                    return Err(ValidationError {
+                        message: "log_query.reason must be non-empty".to_string(),
+                    });
+                }
+                if q.reason.len() > MAX_REASON_BYTES {
+                    return Err(ValidationError {
+                        message: "log_query.reason is too long".to_string(),
+                    });
+                }
+            }
             Action::CgroupApply(cg) => {
-                if cg.pid.is_none() && cg.unit.is_none() {
+                cgroup_target("cgroup_apply", cg.pid, cg.unit.as_deref())?;
+                if cg.cpu_weight.is_some_and(|w| w == 0 || w > MAX_CGROUP_CPU_WEIGHT) {
+                    return Err(ValidationError {
+                        message: format!("cgroup_apply.cpu_weight must be between 1 and {MAX_CGROUP_CPU_WEIGHT}"),
+                    });
+                }
+                if let Some(cpu_max) = &cg.cpu_max {
+                    let period = cpu_max.period_us.unwrap_or(100_000);
+                    if !(MIN_CGROUP_CPU_PERIOD_US..=MAX_CGROUP_CPU_PERIOD_US).contains(&period) {
+                        return Err(ValidationError {
+                            message: format!(
+                                "cgroup_apply.cpu_max.period_us must be between {MIN_CGROUP_CPU_PERIOD_US} and {MAX_CGROUP_CPU_PERIOD_US}"
+                            ),
+                        });
+                    }
+                    if cpu_max
+                        .quota_us
+                        .is_some_and(|q| !(MIN_CGROUP_CPU_PERIOD_US..=MAX_CGROUP_CPU_QUOTA_US).contains(&q))
+                    {
+                        return Err(ValidationError {
+                            message: format!(
+                                "cgroup_apply.cpu_max.quota_us must be between {MIN_CGROUP_CPU_PERIOD_US} and {MAX_CGROUP_CPU_QUOTA_US}"
+                            ),
+                        });
+                    }
+                }
+                if let (Some(high), Some(max)) = (cg.mem_high_bytes, cg.mem_max_bytes) {
+                    if high > max {
+                        return Err(ValidationError {
+                            message: "cgroup_apply.mem_high_bytes must not exceed mem_max_bytes".to_string(),
+                        });
+                    }
+                }
+                if cg.pids_max.is_some_and(|p| p == 0 || p > MAX_CGROUP_PIDS) {
                     return Err(ValidationError {
-                        message: "cgroup_apply requires pid or unit".to_string(),
+                        message: format!("cgroup_apply.pids_max must be between 1 and {MAX_CGROUP_PIDS}"),
                     });
                 }
-                if cg.pid.is_some() && cg.unit.is_some() {
+                if cg.io_weight.is_some_and(|w| w == 0 || w > MAX_CGROUP_CPU_WEIGHT) {
                     return Err(ValidationError {
-                        message: "cgroup_apply must not set both pid and unit".to_string(),
+                        message: format!("cgroup_apply.io_weight must be between 1 and {MAX_CGROUP_CPU_WEIGHT}"),
                     });
                 }
-                if let Some(unit) = &cg.unit {
-                    if unit.trim().is_empty() {
+                if let Some(io_max) = &cg.io_max {
+                    if io_max.is_empty() || io_max.len() > MAX_CGROUP_IO_DEVICES {
                         return Err(ValidationError {
-                            message: "cgroup_apply.unit must be non-empty when provided".to_string(),
+                            message: format!("cgroup_apply.io_max must list 1 to {MAX_CGROUP_IO_DEVICES} devices"),
                         });
                     }
-                    if unit.as_bytes().len() > MAX_SYSTEMD_UNIT_BYTES {
+                    for (i, limit) in io_max.iter().enumerate() {
+                        let device = limit
+                            .device
+                            .split_once(':')
+                            .filter(|(major, minor)| major.parse::<u32>().is_ok() && minor.parse::<u32>().is_ok());
+                        if device.is_none() {
+                            return Err(ValidationError {
+                                message: format!("cgroup_apply.io_max[{i}].device must be major:minor"),
+                            });
+                        }
+                        if io_max[..i].iter().any(|other| other.device == limit.device) {
+                            return Err(ValidationError {
+                                message: format!("cgroup_apply.io_max[{i}].device is listed twice"),
+                            });
+                        }
+                        if [limit.rbps, limit.wbps, limit.riops, limit.wiops].contains(&Some(0)) {
+                            return Err(ValidationError {
+                                message: format!("cgroup_apply.io_max[{i}] limits must be positive"),
+                            });
+                        }
+                    }
+                }
+                if let Some(cpus) = &cg.cpuset_cpus {
+                    // a list of cpus and ranges such as `0-3,8`.
+                    let valid = cpus.split(',').all(|part| match part.split_once('-') {
+                        Some((lo, hi)) => matches!((lo.parse::<u32>(), hi.parse::<u32>()), (Ok(lo), Ok(hi)) if lo <= hi),
+                        None => part.parse::<u32>().is_ok(),
+                    });
+                    if !valid || cpus.len() > MAX_CGROUP_CPUSET_BYTES {
                         return Err(ValidationError {
-                            message: "cgroup_apply.unit is too long".to_string(),
+                            message: "cgroup_apply.cpuset_cpus must be a cpu list such as 0-3,8".to_string(),
                         });
                     }
                 }
-                if cg.cpu_weight.is_none() && cg.mem_max_bytes.is_none() {
+                let settings = [
+                    cg.cpu_weight.is_some(),
+                    cg.mem_max_bytes.is_some(),
+                    cg.cpu_max.is_some(),
+                    cg.mem_high_bytes.is_some(),
+                    cg.pids_max.is_some(),
+                    cg.io_weight.is_some(),
+                    cg.io_max.is_some(),
+                    cg.cpuset_cpus.is_some(),
+                ];
+                if !settings.contains(&true) {
                     return Err(ValidationError {
                         message: "cgroup_apply requires at least one setting".to_string(),
                     });
@@ -804,6 +1655,19 @@ pub fn validate_action_plan(plan: &ActionPlan) -> Result<(), ValidationError> {
                     });
                 }
             }
+            Action::CgroupInspect(cg) => {
+                cgroup_target("cgroup_inspect", cg.pid, cg.unit.as_deref())?;
+                if cg.reason.trim().is_empty() {
+                    return Err(ValidationError {
+                        message: "cgroup_inspect.reason must be non-empty".to_string(),
+                    });
+                }
+                if cg.reason.len() > MAX_REASON_BYTES {
+                    return Err(ValidationError {
+                        message: "cgroup_inspect.reason is too long".to_string(),
+                    });
+                }
+            }
             Action::FirmwareOp(fw) => {
                 if fw.reason.trim().is_empty() {
                     return Err(ValidationError {
@@ -837,6 +1701,123 @@ pub fn validate_action_plan(plan: &ActionPlan) -> Result<(), ValidationError> {
                     FirmwareOp::Inventory | FirmwareOp::FwupdUpdate => {}
                 }
             }
+            Action::Approval(approval) => {
+                if plan.actions.len() != 1 {
+                    return Err(ValidationError {
+                        message: "approval actions must be sent alone".to_string(),
+                    });
+                }
+                match (&approval.op, &approval.request_id) {
+                    (ApprovalOp::List, _) => {}
+                    (_, None) => {
+                        return Err(ValidationError {
+                            message: "approval.request_id must be set for show, approve and reject".to_string(),
+                        });
+                    }
+                    (_, Some(request_id)) => {
+                        if request_id.trim().is_empty() {
+                            return Err(ValidationError {
+                                message: "approval.request_id must be non-empty".to_string(),
+                            });
+                        }
+                        if request_id.len() > MAX_REQUEST_ID_BYTES {
+                            return Err(ValidationError {
+                                message: "approval.request_id is too long".to_string(),
+                            });
+                        }
+                    }
+                }
+                if let Some(note) = &approval.note {
+                    if note.len() > MAX_REASON_BYTES {
+                        return Err(ValidationError {
+                            message: "approval.note is too long".to_string(),
+                        });
+                    }
+                }
+            }
+            Action::SessionGrant(grant) => {
+                if plan.actions.len() != 1 {
+                    return Err(ValidationError {
+                        message: "session_grant actions must be sent alone".to_string(),
+                    });
+                }
+                if plan.session_id.is_none() {
+                    return Err(ValidationError {
+                        message: "session_grant requires session_id".to_string(),
+                    });
+                }
+                match grant.op {
+                    SessionGrantOp::Grant => {
+                        match grant.ttl_sec {
+                            Some(ttl) if (1..=MAX_GRANT_TTL_SEC).contains(&ttl) => {}
+                            _ => {
+                                return Err(ValidationError {
+                                    message: format!("session_grant.ttl_sec must be between 1 and {MAX_GRANT_TTL_SEC}"),
+                                });
+                            }
+                        }
+                        let (pattern, field) = match &grant.scope {
+                            Some(GrantScope::Exec(exec)) => {
+                                if let Some(args) = &exec.args {
+                                    if args.len() > MAX_EXEC_ARGC {
+                                        return Err(ValidationError {
+                                            message: "session_grant.scope.args has too many entries".to_string(),
+                                        });
+                                    }
+                                    if args.iter().any(|arg| arg.len() > MAX_EXEC_ARG_BYTES) {
+                                        return Err(ValidationError {
+                                            message: "session_grant.scope.args entry is too long".to_string(),
+                                        });
+                                    }
+                                    if args.iter().rev().skip(1).any(|arg| arg == "**") {
+                                        return Err(ValidationError {
+                                            message: "session_grant.scope.args may use `**` only as the last entry".to_string(),
+                                        });
+                                    }
+                                }
+                                (&exec.program, "program")
+                            }
+                            Some(GrantScope::ReadFile(path)) | Some(GrantScope::WriteFile(path)) => (&path.root, "root"),
+                            None => {
+                                return Err(ValidationError {
+                                    message: "session_grant.scope must be set for grant".to_string(),
+                                });
+                            }
+                        };
+                        if !pattern.starts_with('/') {
+                            return Err(ValidationError {
+                                message: format!("session_grant.scope.{field} must be an absolute path"),
+                            });
+                        }
+                        if pattern.len() > MAX_PATH_BYTES {
+                            return Err(ValidationError {
+                                message: format!("session_grant.scope.{field} is too long"),
+                            });
+                        }
+                    }
+                    SessionGrantOp::Revoke => {
+                        let grant_id = grant.grant_id.as_deref().unwrap_or("");
+                        if grant_id.trim().is_empty() {
+                            return Err(ValidationError {
+                                message: "session_grant.grant_id must be set for revoke".to_string(),
+                            });
+                        }
+                        if grant_id.len() > MAX_REQUEST_ID_BYTES {
+                            return Err(ValidationError {
+                                message: "session_grant.grant_id is too long".to_string(),
+                            });
+                        }
+                    }
+                    SessionGrantOp::List => {}
+                }
+                if let Some(note) = &grant.note {
+                    if note.len() > MAX_REASON_BYTES {
+                        return Err(ValidationError {
+                            message: "session_grant.note is too long".to_string(),
+                        });
+                    }
+                }
+            }
             Action::Ping => {}
         }
     }
@@ -844,6 +1825,19 @@ pub fn validate_action_plan(plan: &ActionPlan) -> Result<(), ValidationError> {
     Ok(())
 }
 
+// a name the manager looks up in its repositories; paths and archive names would make it install a
+// local file, maintainer scripts and all.
+fn is_package_name(name: &str) -> bool {
+    const ARCHIVE_SUFFIXES: [&str; 5] = [".deb", ".udeb", ".rpm", ".pkg.tar", ".tar"];
+    let lower = name.to_ascii_lowercase();
+    name.bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'@' | b'.' | b'_' | b'+' | b':' | b'-'))
+        && !name.starts_with(['-', '.'])
+        && !ARCHIVE_SUFFIXES
+            .iter()
+            .any(|suffix| lower.ends_with(suffix) || lower.contains(&format!("{suffix}.")))
+}
+
 fn is_octal_mode(mode: &str) -> bool {
     let mode = mode.trim();
     let mode = mode.strip_prefix("0o").unwrap_or(mode);
@@ -1184,6 +2178,79 @@ mod tests {
         assert_eq!(err.message, "request_id is too long");
     }
 
+    #[test]
+    fn validate_rejects_package_names_that_look_like_options() {
+        let plan = ActionPlan {
+            request_id: "req-1".to_string(),
+            session_id: None,
+            version: "0.1".to_string(),
+            mode: Mode::Execute,
+            actions: vec![Action::InstallPackages(InstallPackagesAction {
+                manager: PackageManager::Apt,
+                packages: vec!["curl".to_string(), "-oAPT::Get::AllowUnauthenticated=1".to_string()],
+                reason: "test".to_string(),
+                danger: None,
+                recovery: None,
+            })],
+            confirmation: None,
+        };
+        let err = validate_action_plan(&plan).unwrap_err();
+        assert_eq!(err.message, "install_packages.packages entries must not start with '-'");
+    }
+
+    #[test]
+    fn validate_rejects_package_names_that_are_paths_or_archives() {
+        let plan = |action: Action| ActionPlan {
+            request_id: "req-1".to_string(),
+            session_id: None,
+            version: "0.1".to_string(),
+            mode: Mode::Execute,
+            actions: vec![action],
+            confirmation: None,
+        };
+        let install = |name: &str| {
+            plan(Action::InstallPackages(InstallPackagesAction {
+                manager: PackageManager::Apt,
+                packages: vec![name.to_string()],
+                reason: "test".to_string(),
+                danger: None,
+                recovery: None,
+            }))
+        };
+        let remove = |name: &str| {
+            plan(Action::RemovePackages(RemovePackagesAction {
+                manager: PackageManager::Dnf,
+                packages: vec![name.to_string()],
+                reason: "test".to_string(),
+                danger: None,
+                recovery: None,
+            }))
+        };
+        for name in [
+            "/tmp/x.deb",
+            "./x.rpm",
+            "x.rpm",
+            "x.deb",
+            "x.pkg.tar.zst",
+            "x.tar.gz",
+            "../x",
+            "dir/x",
+            "x y",
+            "x*",
+            ".hidden",
+            "https://example.com/x",
+        ] {
+            let err = validate_action_plan(&install(name)).unwrap_err();
+            assert_eq!(err.message, "install_packages.packages entries must be repository package names", "{name}");
+            let err = validate_action_plan(&remove(name)).unwrap_err();
+            assert_eq!(err.message, "remove_packages.packages entries must be repository package names", "{name}");
+        }
+        for name in ["curl", "libstdc++6", "python3.12", "gcc-c++", "nodejs:18", "pkg@1.2", "lib32-glibc"] {
+            assert!(validate_action_plan(&install(name)).is_ok(), "{name}");
+            assert!(validate_action_plan(&remove(name)).is_ok(), "{name}");
+        }
+    }
+
     #[test]
     fn validate_rejects_session_id_too_long() {
         let plan = ActionPlan {
@@ -1265,6 +2332,7 @@ mod tests {
             actions: vec![Action::Ping],
             confirmation: Some(Confirmation {
                 token: "a".repeat(1025),
+                nonce: None,
             }),
         };
         let err = validate_action_plan(&plan).unwrap_err();
@@ -1290,6 +2358,7 @@ mod tests {
             })],
             confirmation: Some(Confirmation {
                 token: "i-understand".to_string(),
+                nonce: None,
             }),
         };
         let err = validate_action_plan(&plan).unwrap_err();
@@ -1315,6 +2384,7 @@ mod tests {
             })],
             confirmation: Some(Confirmation {
                 token: "i-understand".to_string(),
+                nonce: None,
             }),
         };
         let err = validate_action_plan(&plan).unwrap_err();
@@ -1387,6 +2457,294 @@ mod tests {
         let err = validate_action_plan(&plan).unwrap_err();
         assert_eq!(err.message, "write_file.mode is invalid");
     }
-}
 
+    #[test]
+    fn validate_requires_approval_actions_alone_and_named() {
+        let approval = |op: ApprovalOp, request_id: Option<&str>| {
+            Action::Approval(ApprovalAction {
+                op,
+                request_id: request_id.map(|id| id.to_string()),
+                note: None,
+            })
+        };
+        let plan = |actions: Vec<Action>| ActionPlan {
+            request_id: "req-1".to_string(),
+            session_id: None,
+            version: "0.1".to_string(),
+            mode: Mode::Execute,
+            actions,
+            confirmation: None,
+        };
+
+        assert!(validate_action_plan(&plan(vec![approval(ApprovalOp::List, None)])).is_ok());
+        assert!(validate_action_plan(&plan(vec![approval(ApprovalOp::Approve, Some("req-2"))])).is_ok());
+        let err = validate_action_plan(&plan(vec![approval(ApprovalOp::Reject, None)])).unwrap_err();
+        assert_eq!(err.message, "approval.request_id must be set for show, approve and reject");
+        let err = validate_action_plan(&plan(vec![approval(ApprovalOp::List, None), Action::Ping])).unwrap_err();
+        assert_eq!(err.message, "approval actions must be sent alone");
+    }
+
+    #[test]
+    fn validate_session_grants_need_a_session_scope_and_bounded_ttl() {
+        let grant = |scope: Option<GrantScope>, ttl_sec: Option<u64>| {
+            Action::SessionGrant(SessionGrantAction {
+                op: SessionGrantOp::Grant,
+                scope,
+                ttl_sec,
+                grant_id: None,
+                note: None,
+            })
+        };
+        let plan = |session_id: Option<&str>, action: Action| ActionPlan {
+            request_id: "req-1".to_string(),
+            session_id: session_id.map(|id| id.to_string()),
+            version: "0.1".to_string(),
+            mode: Mode::Execute,
+            actions: vec![action],
+            confirmation: None,
+        };
+        let exec = |program: &str| {
+            Some(GrantScope::Exec(ExecGrant {
+                program: program.to_string(),
+                args: Some(vec!["status".to_string(), "**".to_string()]),
+            }))
+        };
+
+        assert!(validate_action_plan(&plan(Some("sess-1"), grant(exec("/usr/bin/systemctl"), Some(600)))).is_ok());
+        let err = validate_action_plan(&plan(None, grant(exec("/usr/bin/systemctl"), Some(600)))).unwrap_err();
+        assert_eq!(err.message, "session_grant requires session_id");
+        let err = validate_action_plan(&plan(Some("sess-1"), grant(exec("systemctl"), Some(600)))).unwrap_err();
+        assert_eq!(err.message, "session_grant.scope.program must be an absolute path");
+        let err = validate_action_plan(&plan(Some("sess-1"), grant(exec("/usr/bin/systemctl"), Some(86401)))).unwrap_err();
+        assert_eq!(err.message, "session_grant.ttl_sec must be between 1 and 86400");
+        let err = validate_action_plan(&plan(Some("sess-1"), grant(None, Some(600)))).unwrap_err();
+        assert_eq!(err.message, "session_grant.scope must be set for grant");
+        let rest_first = Some(GrantScope::Exec(ExecGrant {
+            program: "/usr/bin/systemctl".to_string(),
+            args: Some(vec!["**".to_string(), "status".to_string()]),
+        }));
+        let err = validate_action_plan(&plan(Some("sess-1"), grant(rest_first, Some(600)))).unwrap_err();
+        assert_eq!(err.message, "session_grant.scope.args may use `**` only as the last entry");
+    }
+
+    #[test]
+    fn validate_cgroup_apply_bounds_weight_and_unit_names() {
+        let plan = |unit: &str, cpu_weight: u64| ActionPlan {
+            request_id: "req-1".to_string(),
+            session_id: None,
+            version: "0.1".to_string(),
+            mode: Mode::Execute,
+            actions: vec![Action::CgroupApply(CgroupApplyAction {
+                pid: None,
+                unit: Some(unit.to_string()),
+                cpu_weight: Some(cpu_weight),
+                mem_max_bytes: None,
+                cpu_max: None,
+                mem_high_bytes: None,
+                pids_max: None,
+                io_weight: None,
+                io_max: None,
+                cpuset_cpus: None,
+                reason: "test".to_string(),
+                danger: None,
+                recovery: None,
+            })],
+            confirmation: None,
+        };
+
+        assert!(validate_action_plan(&plan("batch-jobs.scope", 100)).is_ok());
+        for unit in ["../system.slice", "a/b", "..", "cgroup.procs"] {
+            let err = validate_action_plan(&plan(unit, 100)).unwrap_err();
+            assert_eq!(err.message, "cgroup_apply.unit must be a plain cgroup name");
+        }
+        let err = validate_action_plan(&plan("batch", 0)).unwrap_err();
+        assert_eq!(err.message, "cgroup_apply.cpu_weight must be between 1 and 10000");
+        assert!(validate_action_plan(&plan("batch", 10001)).is_err());
+
+        let with = |change: &dyn Fn(&mut CgroupApplyAction)| {
+            let mut plan = plan("batch", 100);
+            if let Action::CgroupApply(cg) = &mut plan.actions[0] {
+                change(cg);
+            }
+            validate_action_plan(&plan).map_err(|err| err.message)
+        };
+        let io = |device: &str, rbps: Option<u64>| IoMax {
+            device: device.to_string(),
+            rbps,
+            wbps: None,
+            riops: None,
+            wiops: None,
+        };
+        assert!(with(&|cg| cg.cpuset_cpus = Some("0-3,8".to_string())).is_ok());
+        assert!(with(&|cg| cg.io_max = Some(vec![io("8:0", Some(1048576)), io("8:16", None)])).is_ok());
+        assert_eq!(
+            with(&|cg| cg.cpu_max = Some(CpuMax { quota_us: Some(500), period_us: None })).unwrap_err(),
+            "cgroup_apply.cpu_max.quota_us must be between 1000 and 100000000"
+        );
+        assert_eq!(
+            with(&|cg| cg.cpu_max = Some(CpuMax { quota_us: None, period_us: Some(2_000_000) })).unwrap_err(),
+            "cgroup_apply.cpu_max.period_us must be between 1000 and 1000000"
+        );
+        assert_eq!(
+            with(&|cg| {
+                cg.mem_high_bytes = Some(2048);
+                cg.mem_max_bytes = Some(1024);
+            })
+            .unwrap_err(),
+            "cgroup_apply.mem_high_bytes must not exceed mem_max_bytes"
+        );
+        assert_eq!(
+            with(&|cg| cg.pids_max = Some(0)).unwrap_err(),
+            "cgroup_apply.pids_max must be between 1 and 4194304"
+        );
+        assert_eq!(
+            with(&|cg| cg.io_max = Some(vec![io("sda", Some(1))])).unwrap_err(),
+            "cgroup_apply.io_max[0].device must be major:minor"
+        );
+        assert_eq!(
+            with(&|cg| cg.io_max = Some(vec![io("8:0", Some(1)), io("8:0", None)])).unwrap_err(),
+            "cgroup_apply.io_max[1].device is listed twice"
+        );
+        assert_eq!(
+            with(&|cg| cg.cpuset_cpus = Some("3-1".to_string())).unwrap_err(),
+            "cgroup_apply.cpuset_cpus must be a cpu list such as 0-3,8"
+        );
+    }
+
+    #[test]
+    fn validate_log_query_checks_units_priorities_and_time_specs() {
+        let plan = |change: &dyn Fn(&mut LogQueryAction)| {
+            let mut q = LogQueryAction {
+                unit: Some("ssh.service".to_string()),
+                priority: Some(LogPriorityRange {
+                    from: LogPriority::Emerg,
+                    to: LogPriority::Err,
+                }),
+                since: Some("-1h".to_string()),
+                until: None,
+                grep: None,
+                max_entries: Some(50),
+                reason: "test".to_string(),
+                danger: None,
+                recovery: None,
+            };
+            change(&mut q);
+            let plan = ActionPlan {
+                request_id: "req-1".to_string(),
+                session_id: None,
+                version: "0.1".to_string(),
+                mode: Mode::Execute,
+                actions: vec![Action::LogQuery(q)],
+                confirmation: None,
+            };
+            validate_action_plan(&plan).map_err(|err| err.message)
+        };
+
+        assert!(plan(&|_| {}).is_ok());
+        for since in ["2026-10-16", "2026-10-16 08:30", "2026-10-16 08:30:15", "today", "-90s", "-2d"] {
+            assert!(plan(&|q| q.since = Some(since.to_string())).is_ok(), "{since}");
+        }
+        for since in ["1h", "-h", "--grep=x", "2026-10-16T08:30", "last tuesday"] {
+            assert!(plan(&|q| q.since = Some(since.to_string())).unwrap_err().starts_with("log_query.since must be"));
+        }
+        assert_eq!(
+            plan(&|q| q.unit = Some("ssh*".to_string())).unwrap_err(),
+            "log_query.unit must be a unit name"
+        );
+        assert_eq!(
+            plan(&|q| {
+                q.priority = Some(LogPriorityRange {
+                    from: LogPriority::Info,
+                    to: LogPriority::Err,
+                })
+            })
+            .unwrap_err(),
+            "log_query.priority.from must be at least as severe as priority.to"
+        );
+        assert_eq!(
+            plan(&|q| q.max_entries = Some(1001)).unwrap_err(),
+            "log_query.max_entries must be between 1 and 1000"
+        );
+        assert_eq!(
+            plan(&|q| q.grep = Some(String::new())).unwrap_err(),
+            "log_query.grep must be non-empty and short"
+        );
+    }
+
+    #[test]
+    fn validate_observe_timeouts_are_bounded_and_only_for_programs() {
+        let plan = |tool: ObserveTool, timeout_sec: Option<u64>| {
+            let plan = ActionPlan {
+                request_id: "req-1".to_string(),
+                session_id: None,
+                version: "0.1".to_string(),
+                mode: Mode::Execute,
+                actions: vec![Action::Observe(ObserveAction {
+                    tool,
+                    name: None,
+                    args: vec![],
+                    processes: None,
+                    timeout_sec,
+                    reason: "test".to_string(),
+                    danger: None,
+                    recovery: None,
+                })],
+                confirmation: None,
+            };
+            validate_action_plan(&plan).map_err(|err| err.message)
+        };
+
+        assert!(plan(ObserveTool::Perf, None).is_ok());
+        assert!(plan(ObserveTool::Perf, Some(600)).is_ok());
+        assert_eq!(plan(ObserveTool::Perf, Some(0)).unwrap_err(), "observe.timeout_sec must be >= 1");
+        assert_eq!(plan(ObserveTool::Perf, Some(601)).unwrap_err(), "observe.timeout_sec is too large");
+        assert_eq!(
+            plan(ObserveTool::Top, Some(5)).unwrap_err(),
+            "observe.timeout_sec is not valid for ps and top"
+        );
+    }
 
+    #[test]
+    fn validate_observe_ps_and_top_take_a_process_query_not_args() {
+        let plan = |tool: ObserveTool, args: &[&str], processes: Option<ProcessQuery>| {
+            let plan = ActionPlan {
+                request_id: "req-1".to_string(),
+                session_id: None,
+                version: "0.1".to_string(),
+                mode: Mode::Execute,
+                actions: vec![Action::Observe(ObserveAction {
+                    tool,
+                    name: None,
+                    args: args.iter().map(|a| a.to_string()).collect(),
+                    processes,
+                    timeout_sec: None,
+                    reason: "test".to_string(),
+                    danger: None,
+                    recovery: None,
+                })],
+                confirmation: None,
+            };
+            validate_action_plan(&plan).map_err(|err| err.message)
+        };
+        let query = || {
+            Some(ProcessQuery {
+                user: Some("root".to_string()),
+                limit: Some(10),
+                ..ProcessQuery::default()
+            })
+        };
+
+        assert!(plan(ObserveTool::Ps, &[], query()).is_ok());
+        assert!(plan(ObserveTool::Top, &[], None).is_ok());
+        for tool in [ObserveTool::Ps, ObserveTool::Top] {
+            assert_eq!(
+                plan(tool, &["aux"], None).unwrap_err(),
+                "observe.args must be empty for ps and top; use observe.processes"
+            );
+        }
+        assert_eq!(
+            plan(ObserveTool::Perf, &[], query()).unwrap_err(),
+            "observe.processes is only valid for ps and top"
+        );
+    }
+}
@@ -0,0 +1,623 @@
+// ABOUTME: resolves exec argv[0] to a canonical executable and decides exec policy over it.
+// ABOUTME: argument rules let the same program get different decisions depending on flags and operands.
+
+use crate::{glob_match, strings, Decision, Step};
+use llm_os_common::ExecAction;
+use serde::{Deserialize, Deserializer};
+use std::path::{Component, Path, PathBuf};
+
+// matches resolved executables: patterns containing `/` match the canonical path, bare names match the file name.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExecRules {
+    pub default: Decision,
+    #[serde(default = "default_search_path")]
+    pub search_path: Vec<String>,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub confirm: Vec<String>,
+    #[serde(default)]
+    pub approve: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<ExecRule>,
+    // applied to shell scripts and wrapper invocations that cannot be inspected.
+    #[serde(default = "default_unparsed")]
+    pub unparsed: Decision,
+}
+
+// an argument-aware rule: every condition that is set must hold for the rule to match.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExecRule {
+    pub id: String,
+    pub program: String,
+    pub decision: Decision,
+    // matches when any listed flag is present; short flags also match inside bundles like `-rf`.
+    #[serde(default)]
+    pub flags: Vec<String>,
+    #[serde(default)]
+    pub arg_glob: Option<String>,
+    #[serde(default)]
+    pub arg_regex: Option<ArgRegex>,
+    // matches when any operand or option value resolves outside every listed root.
+    #[serde(default)]
+    pub paths_outside: Vec<String>,
+}
+
+// compiled once when the policy is parsed, so an invalid pattern fails the load.
+#[derive(Debug, Clone)]
+pub struct ArgRegex(regex::Regex);
+
+impl ArgRegex {
+    pub fn new(pattern: &str) -> Result<ArgRegex, String> {
+        regex::Regex::new(pattern)
+            .map(ArgRegex)
+            .map_err(|err| format!("invalid arg_regex `{pattern}`: {err}"))
+    }
+}
+
+impl<'de> Deserialize<'de> for ArgRegex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        ArgRegex::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+// the canonical executable that argv[0] resolved to, plus what was originally requested.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedProgram {
+    pub requested: String,
+    pub path: PathBuf,
+    pub in_search_path: bool,
+}
+
+impl ExecRules {
+    // resolves argv[0] against the search path (or cwd for relative paths) and canonicalizes symlinks.
+    pub fn resolve(&self, program: &str, cwd: Option<&str>) -> Result<ResolvedProgram, String> {
+        let candidate = if program.contains('/') {
+            let path = Path::new(program);
+            if path.is_absolute() {
+                path.to_path_buf()
+            } else {
+                base_dir(cwd)?.join(path)
+            }
+        } else {
+            self.search_path
+                .iter()
+                .map(|dir| Path::new(dir).join(program))
+                .find(|c| is_executable_file(c))
+                .ok_or_else(|| format!("{program} not found in search path"))?
+        };
+
+        let path = std::fs::canonicalize(&candidate)
+            .map_err(|err| format!("resolve {}: {err}", candidate.display()))?;
+        if !is_executable_file(&path) {
+            return Err(format!("{} is not an executable file", path.display()));
+        }
+
+        let in_search_path = match path.parent() {
+            Some(parent) => self
+                .search_path
+                .iter()
+                .filter_map(|dir| std::fs::canonicalize(dir).ok())
+                .any(|dir| dir == parent),
+            None => false,
+        };
+
+        Ok(ResolvedProgram {
+            requested: program.to_string(),
+            path,
+            in_search_path,
+        })
+    }
+
+    // deny list first, then the strictest matching argument rule, then the approve, confirm and allow lists.
+    pub fn decide(&self, exec: &ExecAction, program: &ResolvedProgram) -> Step {
+        let path = program.path.display();
+        if let Some(pattern) = self.deny.iter().find(|p| matches_any_name(p, program)) {
+            return Step::new(
+                Decision::Deny,
+                format!("exec.deny:{pattern}"),
+                format!("{path} matches deny pattern `{pattern}`"),
+            );
+        }
+        let args = exec.argv.get(1..).unwrap_or_default();
+        let ruled = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(program, args, exec.cwd.as_deref()))
+            .fold(None, |best: Option<&ExecRule>, rule| match best {
+                Some(best) if best.decision >= rule.decision => Some(best),
+                _ => Some(rule),
+            });
+        if let Some(rule) = ruled {
+            return Step::new(
+                rule.decision,
+                format!("exec.rules:{}", rule.id),
+                format!("{path} with these arguments matches exec rule `{}`", rule.id),
+            );
+        }
+        if let Some(pattern) = self.approve.iter().find(|p| matches_any_name(p, program)) {
+            return Step::new(
+                Decision::Approve,
+                format!("exec.approve:{pattern}"),
+                format!("{path} matches approve pattern `{pattern}`"),
+            );
+        }
+        if let Some(pattern) = self.confirm.iter().find(|p| matches_any_name(p, program)) {
+            return Step::new(
+                Decision::Confirm,
+                format!("exec.confirm:{pattern}"),
+                format!("{path} matches confirm pattern `{pattern}`"),
+            );
+        }
+        if let Some(pattern) = self.allow.iter().find(|p| matches_resolved(p, program)) {
+            return Step::new(
+                Decision::Allow,
+                format!("exec.allow:{pattern}"),
+                format!("{path} matches allow pattern `{pattern}`"),
+            );
+        }
+        Step::new(
+            self.default,
+            "exec.default".to_string(),
+            format!("{path} matches no exec rule"),
+        )
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            if let Some(root) = rule.paths_outside.iter().find(|root| !Path::new(root).is_absolute()) {
+                return Err(format!("exec rule {}: paths_outside root {root} is not absolute", rule.id));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ExecRule {
+    pub fn matches(&self, program: &ResolvedProgram, args: &[String], cwd: Option<&str>) -> bool {
+        // an allow rule must not let a look-alike binary outside the search path through.
+        let program_matches = if self.decision == Decision::Allow {
+            matches_resolved(&self.program, program)
+        } else {
+            matches_any_name(&self.program, program)
+        };
+        if !program_matches {
+            return false;
+        }
+
+        let (options, operands) = split_args(args);
+        if !self.flags.is_empty()
+            && !self
+                .flags
+                .iter()
+                .any(|flag| options.iter().any(|opt| flag_matches(flag, opt)))
+        {
+            return false;
+        }
+        if let Some(pattern) = &self.arg_glob {
+            if !args.iter().any(|arg| glob_match(pattern, arg)) {
+                return false;
+            }
+        }
+        if let Some(ArgRegex(re)) = &self.arg_regex {
+            if !args.iter().any(|arg| re.is_match(arg)) {
+                return false;
+            }
+        }
+        if !self.paths_outside.is_empty() {
+            let base = match base_dir(cwd) {
+                Ok(base) => base,
+                Err(_) => return true,
+            };
+            let roots: Vec<PathBuf> = self.paths_outside.iter().map(|root| resolve_path(Path::new(root))).collect();
+            let values = options.iter().filter_map(|option| option_value(option));
+            let outside = operands.iter().copied().chain(values).any(|operand| {
+                let path = resolve_path(&base.join(operand));
+                !roots.iter().any(|root| path.starts_with(root))
+            });
+            if !outside {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// splits arguments into options and operands; everything after `--` is an operand.
+fn split_args(args: &[String]) -> (Vec<&str>, Vec<&str>) {
+    let mut options = Vec::new();
+    let mut operands = Vec::new();
+    let mut after_separator = false;
+    for arg in args {
+        if after_separator {
+            operands.push(arg.as_str());
+        } else if arg == "--" {
+            after_separator = true;
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            options.push(arg.as_str());
+        } else {
+            operands.push(arg.as_str());
+        }
+    }
+    (options, operands)
+}
+
+// `--long` matches `--long` and `--long=value`; `-x` also matches bundles like `-xvf`.
+fn flag_matches(flag: &str, option: &str) -> bool {
+    if option == flag {
+        return true;
+    }
+    if flag.starts_with("--") {
+        return option
+            .strip_prefix(flag)
+            .is_some_and(|rest| rest.starts_with('='));
+    }
+    match (flag.strip_prefix('-'), option.strip_prefix('-')) {
+        (Some(short), Some(bundle)) if short.chars().count() == 1 && !bundle.starts_with('-') => {
+            bundle.contains(short)
+        }
+        _ => false,
+    }
+}
+
+// the path an option carries inline: `--output=/etc/x`, or `-o/etc/x` when the rest holds a `/`.
+// a value in the next argument (`-o /etc/x`) is already an operand.
+fn option_value(option: &str) -> Option<&str> {
+    if let Some(long) = option.strip_prefix("--") {
+        return long.split_once('=').map(|(_, value)| value);
+    }
+    let rest = option.get(2..)?;
+    rest.contains('/').then_some(rest)
+}
+
+// the real location of a path that may not exist yet: the longest existing prefix is
+// canonicalized, so symlinks and `..` through them resolve the way the kernel would, and
+// the missing remainder is appended lexically.
+fn resolve_path(path: &Path) -> PathBuf {
+    let components: Vec<Component> = path.components().collect();
+    for split in (0..=components.len()).rev() {
+        let prefix: PathBuf = components[..split].iter().collect();
+        let Ok(mut out) = std::fs::canonicalize(&prefix) else {
+            continue;
+        };
+        for component in &components[split..] {
+            match component {
+                Component::ParentDir => {
+                    out.pop();
+                }
+                Component::CurDir => {}
+                other => out.push(other),
+            }
+        }
+        return out;
+    }
+    path.to_path_buf()
+}
+
+fn base_dir(cwd: Option<&str>) -> Result<PathBuf, String> {
+    match cwd {
+        Some(cwd) => Ok(PathBuf::from(cwd)),
+        None => std::env::current_dir().map_err(|err| format!("current dir: {err}")),
+    }
+}
+
+// allow rules only trust the canonical path, or the file name when it lives in the search path.
+fn matches_resolved(pattern: &str, program: &ResolvedProgram) -> bool {
+    if pattern.contains('/') {
+        return glob_match(&canonical_pattern(pattern), &program.path.to_string_lossy());
+    }
+    program.in_search_path && glob_match(pattern, &file_name(&program.path))
+}
+
+// deny and confirm rules also match the requested name so aliases (mkfs.ext4 -> mke2fs) stay covered.
+fn matches_any_name(pattern: &str, program: &ResolvedProgram) -> bool {
+    if pattern.contains('/') {
+        return glob_match(&canonical_pattern(pattern), &program.path.to_string_lossy())
+            || glob_match(pattern, &program.requested);
+    }
+    glob_match(pattern, &file_name(&program.path))
+        || glob_match(pattern, &file_name(Path::new(&program.requested)))
+}
+
+fn canonical_pattern(pattern: &str) -> String {
+    if pattern.contains(['*', '?']) {
+        return pattern.to_string();
+    }
+    match std::fs::canonicalize(pattern) {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(_) => pattern.to_string(),
+    }
+}
+
+pub(super) fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn is_executable_file(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    match std::fs::metadata(path) {
+        Ok(meta) => meta.is_file() && meta.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    }
+}
+
+pub(super) fn default_search_path() -> Vec<String> {
+    strings(&[
+        "/usr/local/sbin",
+        "/usr/local/bin",
+        "/usr/sbin",
+        "/usr/bin",
+        "/sbin",
+        "/bin",
+    ])
+}
+
+fn default_unparsed() -> Decision {
+    Decision::Confirm
+}
+
+pub(super) fn default_rules() -> ExecRules {
+    ExecRules {
+        default: Decision::Confirm,
+        search_path: default_search_path(),
+        allow: strings(&["/bin/echo", "echo"]),
+        confirm: strings(&["/bin/rm", "rm"]),
+        approve: vec![],
+        deny: strings(&[
+            "/bin/dd",
+            "dd",
+            "/sbin/mkfs",
+            "/sbin/mkfs.ext4",
+            "mkfs",
+            "mkfs.ext4",
+            "/sbin/shutdown",
+            "shutdown",
+            "/sbin/reboot",
+            "reboot",
+        ]),
+        rules: vec![ExecRule {
+            id: "rm-recursive-outside-tmp".to_string(),
+            program: "rm".to_string(),
+            decision: Decision::Deny,
+            flags: strings(&["-r", "-R", "--recursive"]),
+            arg_glob: None,
+            arg_regex: None,
+            paths_outside: strings(&["/tmp"]),
+        }],
+        unparsed: default_unparsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Policy;
+    use super::*;
+
+    fn exec(argv: &[&str]) -> ExecAction {
+        ExecAction {
+            argv: strings(argv),
+            cwd: None,
+            env: None,
+            timeout_sec: 5,
+            as_root: false,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        }
+    }
+
+    fn decide(policy: &Policy, argv: &[&str]) -> Decision {
+        let action = exec(argv);
+        let program = policy.resolve_exec(&action).unwrap();
+        policy.exec_verdict(&action, &program).decision()
+    }
+
+    #[test]
+    fn default_policy_matches_builtin_exec_rules() {
+        let policy = Policy::default();
+        assert_eq!(decide(&policy, &["/bin/echo", "hi"]), Decision::Allow);
+        assert_eq!(decide(&policy, &["echo", "hi"]), Decision::Allow);
+        assert_eq!(decide(&policy, &["rm", "x"]), Decision::Confirm);
+        assert_eq!(decide(&policy, &["/usr/bin/true"]), Decision::Confirm);
+        assert_eq!(decide(&policy, &["dd"]), Decision::Deny);
+        assert!(policy.resolve_exec(&exec(&[])).is_err());
+    }
+
+    #[test]
+    fn exec_rules_match_the_resolved_executable() {
+        let policy = Policy::default();
+        let dd = std::fs::canonicalize("/bin/dd").unwrap();
+        let dd_dir = dd.parent().unwrap().to_string_lossy().to_string();
+
+        for argv0 in ["/usr/bin/dd", "/bin/../bin/dd", "./dd"] {
+            let mut action = exec(&[argv0]);
+            action.cwd = Some(dd_dir.clone());
+            let program = policy.resolve_exec(&action).unwrap();
+            assert_eq!(program.path, dd);
+            assert_eq!(policy.exec_verdict(&action, &program).decision(), Decision::Deny, "{argv0}");
+        }
+
+        assert_eq!(decide(&policy, &["/usr/bin/rm", "x"]), Decision::Confirm);
+    }
+
+    #[test]
+    fn exec_allow_by_name_ignores_binaries_outside_search_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let fake = dir.path().join("echo");
+        std::fs::copy(std::fs::canonicalize("/bin/true").unwrap(), &fake).unwrap();
+
+        let policy = Policy::default();
+        let mut action = exec(&["./echo"]);
+        action.cwd = Some(dir.path().to_string_lossy().to_string());
+        let program = policy.resolve_exec(&action).unwrap();
+        assert!(!program.in_search_path);
+        assert_eq!(policy.exec_verdict(&action, &program).decision(), Decision::Confirm);
+
+        let copied_dd = dir.path().join("dd");
+        std::fs::copy(std::fs::canonicalize("/bin/dd").unwrap(), &copied_dd).unwrap();
+        assert_eq!(decide(&policy, &[&copied_dd.to_string_lossy()]), Decision::Deny);
+    }
+
+    #[test]
+    fn exec_resolution_rejects_missing_programs() {
+        let policy = Policy::default();
+        let err = policy.resolve_exec(&exec(&["definitely-not-a-real-program"])).unwrap_err();
+        assert!(err.contains("not found in search path"));
+    }
+
+    #[test]
+    fn default_rules_separate_recursive_rm_outside_tmp() {
+        let policy = Policy::default();
+        assert_eq!(decide(&policy, &["rm", "-rf", "/"]), Decision::Deny);
+        assert_eq!(decide(&policy, &["rm", "--recursive", "/etc"]), Decision::Deny);
+        assert_eq!(decide(&policy, &["rm", "-r", "/tmp/../etc"]), Decision::Deny);
+        assert_eq!(decide(&policy, &["rm", "-rf", "/tmp/build"]), Decision::Confirm);
+        assert_eq!(decide(&policy, &["rm", "/tmp/x"]), Decision::Confirm);
+        assert_eq!(decide(&policy, &["rm", "-f", "--", "-r"]), Decision::Confirm);
+
+        let mut action = exec(&["rm", "-R", "build"]);
+        action.cwd = Some("/tmp".to_string());
+        let program = policy.resolve_exec(&action).unwrap();
+        assert_eq!(policy.exec_verdict(&action, &program).decision(), Decision::Confirm);
+    }
+
+    #[test]
+    fn argument_rules_match_flags_globs_and_regexes() {
+        let policy: Policy = toml::from_str(
+            r#"
+            [exec]
+            default = "confirm"
+            allow = ["find", "curl"]
+
+            [[exec.rules]]
+            id = "find-delete"
+            program = "find"
+            flags = ["-delete", "-exec"]
+            decision = "deny"
+
+            [[exec.rules]]
+            id = "curl-upload"
+            program = "curl"
+            arg_glob = "@*"
+            decision = "confirm"
+
+            [[exec.rules]]
+            id = "curl-plain-http"
+            program = "curl"
+            arg_regex = "^http://"
+            decision = "deny"
+            "#,
+        )
+        .unwrap();
+        policy.exec.validate().unwrap();
+        assert!(policy.exec.rules[2].arg_regex.is_some());
+
+        assert_eq!(decide(&policy, &["find", "/tmp", "-name", "x"]), Decision::Allow);
+        assert_eq!(decide(&policy, &["find", "/tmp", "-delete"]), Decision::Deny);
+        assert_eq!(decide(&policy, &["curl", "https://example.com"]), Decision::Allow);
+        assert_eq!(decide(&policy, &["curl", "-d", "@/etc/shadow", "https://x"]), Decision::Confirm);
+        assert_eq!(decide(&policy, &["curl", "-d", "@f", "http://x"]), Decision::Deny);
+    }
+
+    #[test]
+    fn allow_rules_relax_the_confirm_list_within_roots() {
+        let policy: Policy = toml::from_str(
+            r#"
+            [exec]
+            default = "confirm"
+            confirm = ["rm"]
+
+            [[exec.rules]]
+            id = "rm-scratch"
+            program = "rm"
+            decision = "allow"
+
+            [[exec.rules]]
+            id = "rm-outside-scratch"
+            program = "rm"
+            paths_outside = ["/tmp/scratch"]
+            decision = "confirm"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(decide(&policy, &["rm", "/tmp/scratch/a", "/tmp/scratch/b"]), Decision::Allow);
+        assert_eq!(decide(&policy, &["rm", "/tmp/scratch/a", "/etc/x"]), Decision::Confirm);
+        assert_eq!(decide(&policy, &["rm", "/tmp/scratchpad"]), Decision::Confirm);
+    }
+
+    #[test]
+    fn flag_matching_handles_bundles_and_long_values() {
+        assert!(flag_matches("-r", "-rf"));
+        assert!(flag_matches("-f", "-rf"));
+        assert!(!flag_matches("-r", "--recursive"));
+        assert!(flag_matches("--recursive", "--recursive"));
+        assert!(flag_matches("--output", "--output=x"));
+        assert!(!flag_matches("--out", "--output"));
+    }
+
+    #[test]
+    fn policies_with_invalid_regexes_fail_to_parse() {
+        let err = toml::from_str::<Policy>(
+            r#"
+            [exec]
+            default = "confirm"
+
+            [[exec.rules]]
+            id = "broken"
+            program = "curl"
+            arg_regex = "("
+            decision = "deny"
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid arg_regex `(`"), "{err}");
+    }
+
+    #[test]
+    fn paths_outside_follows_symlinks_and_checks_option_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let scratch = dir.path().join("scratch");
+        std::fs::create_dir(&scratch).unwrap();
+        std::os::unix::fs::symlink("/etc", scratch.join("link")).unwrap();
+        let policy: Policy = toml::from_str(&format!(
+            r#"
+            [exec]
+            default = "confirm"
+            allow = ["rm", "sort"]
+
+            [[exec.rules]]
+            id = "outside-scratch"
+            program = "*"
+            paths_outside = ["{}"]
+            decision = "deny"
+            "#,
+            scratch.display()
+        ))
+        .unwrap();
+        let inside = scratch.join("a").to_string_lossy().to_string();
+        let through_link = scratch.join("link/passwd").to_string_lossy().to_string();
+
+        assert_eq!(decide(&policy, &["rm", &inside]), Decision::Allow);
+        assert_eq!(decide(&policy, &["rm", &through_link]), Decision::Deny);
+        assert_eq!(decide(&policy, &["rm", &scratch.join("link/../x").to_string_lossy()]), Decision::Deny);
+        assert_eq!(decide(&policy, &["sort", &format!("--output={inside}"), &inside]), Decision::Allow);
+        assert_eq!(decide(&policy, &["sort", "--output=/etc/x", &inside]), Decision::Deny);
+        assert_eq!(decide(&policy, &["sort", "-o/etc/x", &inside]), Decision::Deny);
+        assert_eq!(decide(&policy, &["sort", "-o", "/etc/x", &inside]), Decision::Deny);
+    }
+
+    #[test]
+    fn validate_rejects_relative_path_roots() {
+        let mut rules = default_rules();
+        rules.rules[0].paths_outside = strings(&["tmp"]);
+        assert!(rules.validate().unwrap_err().contains("not absolute"));
+    }
+}
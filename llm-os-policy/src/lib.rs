@@ -0,0 +1,804 @@
+// ABOUTME: enforces allow/deny policies over requested actions before execution.
+// ABOUTME: shared by the daemon and offline tooling so both reach the same decisions.
+
+use anyhow::Context;
+use llm_os_common::{Action, ApprovalOp, CgroupApplyAction, CgroupInspectAction, ExecAction, FirmwareOp, FirmwareOpAction, LogQueryAction, ObserveAction, ObserveTool, PolicyDecision, ServiceControlVerb, SessionGrantOp, UpdateScope};
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+
+mod exec;
+mod observe;
+mod profile;
+mod shell;
+
+pub use exec::{ExecRules, ResolvedProgram};
+pub use observe::{ObserveCatalog, RegisteredFlag, RegisteredTool, ToolTimeout, Value as FlagValue};
+pub use profile::{home_dir, Peer, Profile};
+
+// decisions are ordered from least to most restrictive so rule sets can combine them with max().
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Allow,
+    Confirm,
+    // a second person, on a different uid, must approve the parked plan before it runs.
+    Approve,
+    Deny,
+}
+
+// one consulted rule: what it decided, which rule it was, and a short reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub decision: Decision,
+    pub rule_id: String,
+    pub hint: String,
+}
+
+// every rule consulted for one action; the strictest step decides.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Verdict {
+    pub steps: Vec<Step>,
+}
+
+impl Step {
+    pub fn new(decision: Decision, rule_id: String, hint: String) -> Step {
+        Step {
+            decision,
+            rule_id,
+            hint,
+        }
+    }
+}
+
+impl Verdict {
+    pub fn decision(&self) -> Decision {
+        self.decisive().map(|step| step.decision).unwrap_or(Decision::Allow)
+    }
+
+    // the first step with the strictest decision, so explanations point at the earliest cause.
+    pub fn decisive(&self) -> Option<&Step> {
+        self.steps.iter().fold(None, |best: Option<&Step>, step| match best {
+            Some(best) if best.decision >= step.decision => Some(best),
+            _ => Some(step),
+        })
+    }
+
+    pub fn push(&mut self, step: Step) {
+        self.steps.push(step);
+    }
+
+    pub fn extend(&mut self, other: Verdict) {
+        self.steps.extend(other.steps);
+    }
+}
+
+impl From<Decision> for PolicyDecision {
+    fn from(decision: Decision) -> PolicyDecision {
+        match decision {
+            Decision::Allow => PolicyDecision::Allow,
+            Decision::Confirm => PolicyDecision::Confirm,
+            Decision::Approve => PolicyDecision::Approve,
+            Decision::Deny => PolicyDecision::Deny,
+        }
+    }
+}
+
+impl From<Step> for Verdict {
+    fn from(step: Step) -> Verdict {
+        Verdict { steps: vec![step] }
+    }
+}
+
+// matches names (programs, units, packages, tools) by exact string or simple `*`/`?` glob.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NameRules {
+    pub default: Decision,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub confirm: Vec<String>,
+    #[serde(default)]
+    pub approve: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+// matches the resolved real path against root directories; requested paths with `..` get their own floor.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathRules {
+    pub default: Decision,
+    pub parent_dir: Decision,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub confirm: Vec<String>,
+    #[serde(default)]
+    pub approve: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+// sections omitted from a policy document keep their built-in defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    pub exec: ExecRules,
+    pub read_file: PathRules,
+    pub write_file: PathRules,
+    pub service_control: NameRules,
+    pub install_packages: NameRules,
+    pub remove_packages: NameRules,
+    // keyed by update scope: `full`, `refresh_only`, `security_only`.
+    pub update_system: NameRules,
+    // keyed by the delegated cgroup name: the unit, or `pid-<pid>`.
+    pub cgroup_apply: NameRules,
+    // keyed the same way as cgroup_apply.
+    pub cgroup_inspect: NameRules,
+    // keyed by `inventory`, `fwupd_update`, or `uefi_var_read:<variable name>`.
+    pub firmware_op: NameRules,
+    pub observe: NameRules,
+    // keyed by the unit, or `all` for a query across every unit.
+    pub log_query: NameRules,
+    // keyed by approval op: `list`, `show`, `approve`, `reject`.
+    pub approvals: NameRules,
+    // keyed by session grant op: `grant`, `list`, `revoke`.
+    pub sessions: NameRules,
+    pub profiles: Vec<Profile>,
+    // set on the effective policy of a request to the profile that was selected.
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+    // where the rules came from: `builtin` or the policy document path.
+    #[serde(skip)]
+    pub source: String,
+    // set by the daemon from its own configuration rather than the policy document.
+    #[serde(skip)]
+    pub observe_tools: ObserveCatalog,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        let paths = PathRules {
+            default: Decision::Confirm,
+            parent_dir: Decision::Confirm,
+            allow: vec!["/tmp".to_string()],
+            confirm: vec![],
+            approve: vec![],
+            deny: vec![],
+        };
+        let allow_all = NameRules {
+            default: Decision::Allow,
+            allow: vec![],
+            confirm: vec![],
+            approve: vec![],
+            deny: vec![],
+        };
+
+        let confirm_all = NameRules {
+            default: Decision::Confirm,
+            ..allow_all.clone()
+        };
+
+        Policy {
+            exec: exec::default_rules(),
+            read_file: paths.clone(),
+            // writes under /etc reconfigure the whole machine, so a second person approves them by default.
+            write_file: PathRules {
+                approve: vec!["/etc".to_string()],
+                ..paths
+            },
+            // starting, stopping and disabling units runs as root, so every service change is confirmed by default.
+            service_control: confirm_all.clone(),
+            install_packages: confirm_all.clone(),
+            // removals can take the system down with them, so a second person approves them by default.
+            remove_packages: NameRules {
+                default: Decision::Approve,
+                ..allow_all.clone()
+            },
+            // refreshing package metadata changes nothing installed; upgrades are confirmed.
+            update_system: NameRules {
+                allow: strings(&["refresh_only"]),
+                ..confirm_all.clone()
+            },
+            // limits and moves apply to any pid, pid 1 included, so they are confirmed by default.
+            cgroup_apply: confirm_all.clone(),
+            cgroup_inspect: allow_all.clone(),
+            // uefi variables can hold boot and enrollment secrets, so reading them is confirmed by default.
+            firmware_op: NameRules {
+                default: Decision::Confirm,
+                allow: strings(&["inventory"]),
+                ..allow_all.clone()
+            },
+            observe: allow_all.clone(),
+            log_query: allow_all.clone(),
+            approvals: allow_all.clone(),
+            // a grant stands in for future confirmations, so creating one needs a confirmation itself.
+            sessions: NameRules {
+                confirm: strings(&["grant"]),
+                ..allow_all
+            },
+            profiles: vec![],
+            active_profile: None,
+            source: "builtin".to_string(),
+            observe_tools: ObserveCatalog::default(),
+        }
+    }
+}
+
+impl Policy {
+    // loads a policy document; `.json` files are parsed as json, everything else as toml.
+    pub fn load(path: &str) -> anyhow::Result<Policy> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("read policy at {path}"))?;
+        let mut policy: Policy = if path.ends_with(".json") {
+            serde_json::from_str(&text).with_context(|| format!("parse policy json at {path}"))?
+        } else {
+            toml::from_str(&text).with_context(|| format!("parse policy toml at {path}"))?
+        };
+        let profile_execs = policy.profiles.iter().filter_map(|p| p.exec.as_ref());
+        for exec in std::iter::once(&policy.exec).chain(profile_execs) {
+            exec.validate()
+                .map_err(|err| anyhow::anyhow!("invalid policy at {path}: {err}"))?;
+        }
+        policy.source = path.to_string();
+        Ok(policy)
+    }
+
+    pub fn resolve_exec(&self, exec: &ExecAction) -> Result<ResolvedProgram, String> {
+        match exec.argv.first() {
+            Some(program) => self.exec.resolve(program, exec.cwd.as_deref()),
+            None => Err("missing argv[0]".to_string()),
+        }
+    }
+
+    // the full verdict for one action, resolving argv[0] and file targets the way the daemon does.
+    // an unresolvable target is reported as a deny step under the `resolve` rule id.
+    pub fn action_verdict(&self, action: &Action) -> Verdict {
+        let unresolved = |err: String| Verdict::from(Step::new(Decision::Deny, "resolve".to_string(), err));
+        let unsupported = |what: &str| {
+            Verdict::from(Step::new(
+                Decision::Deny,
+                "unsupported".to_string(),
+                format!("{what} is not supported"),
+            ))
+        };
+        match action {
+            Action::Exec(exec) => match self.resolve_exec(exec) {
+                Ok(program) => self.exec_verdict(exec, &program),
+                Err(err) => unresolved(format!("argv[0] could not be resolved: {err}")),
+            },
+            Action::ReadFile(read) => match target_path(&read.path) {
+                Ok(target) => self.read_file_verdict(&read.path, &target),
+                Err(err) => unresolved(format!("path could not be resolved: {err}")),
+            },
+            Action::WriteFile(write) => match target_path(&write.path) {
+                Ok(target) => self.write_file_verdict(&write.path, &target),
+                Err(err) => unresolved(format!("path could not be resolved: {err}")),
+            },
+            Action::ServiceControl(svc) => self.service_control_verdict(&svc.action, &svc.unit),
+            Action::InstallPackages(pkgs) => self.install_packages_verdict(&pkgs.packages),
+            Action::RemovePackages(pkgs) => self.remove_packages_verdict(&pkgs.packages),
+            Action::Observe(obs) => self.observe_verdict(obs),
+            Action::LogQuery(q) => self.log_query_verdict(q),
+            Action::Approval(approval) => self.approval_verdict(approval.op),
+            Action::SessionGrant(grant) => self.session_grant_verdict(grant.op),
+            Action::UpdateSystem(upd) => self.update_system_verdict(upd.scope.unwrap_or_default()),
+            Action::CgroupApply(cg) => self.cgroup_apply_verdict(cg),
+            Action::CgroupInspect(cg) => self.cgroup_inspect_verdict(cg),
+            Action::FirmwareOp(fw) if fw.op == FirmwareOp::FwupdUpdate => unsupported("firmware_op fwupd_update"),
+            Action::FirmwareOp(fw) => self.firmware_op_verdict(fw),
+            Action::Ping => Verdict::default(),
+        }
+    }
+
+    // names the policy document and, when one was selected, the profile.
+    pub fn source_label(&self) -> String {
+        match &self.active_profile {
+            Some(profile) => format!("{} (profile {profile})", self.source),
+            None => self.source.clone(),
+        }
+    }
+
+    pub fn exec_verdict(&self, exec: &ExecAction, program: &ResolvedProgram) -> Verdict {
+        let mut verdict = Verdict::from(self.exec.decide(exec, program));
+        verdict.extend(shell::env_verdict(self, exec, program));
+        verdict.extend(shell::wrapped_verdict(self, exec, program, 0));
+        verdict
+    }
+
+    pub fn read_file_verdict(&self, requested: &str, resolved: &Path) -> Verdict {
+        self.read_file.decide("read_file", requested, resolved)
+    }
+
+    pub fn write_file_verdict(&self, requested: &str, resolved: &Path) -> Verdict {
+        self.write_file.decide("write_file", requested, resolved)
+    }
+
+    // status only reads unit state, so it skips confirm and approve; a denied unit stays denied.
+    pub fn service_control_verdict(&self, verb: &ServiceControlVerb, unit: &str) -> Verdict {
+        let step = self.service_control.decide("service_control", unit);
+        if *verb == ServiceControlVerb::Status && step.decision != Decision::Deny {
+            return Step::new(
+                Decision::Allow,
+                "service_control.status".to_string(),
+                "status only reads the unit's state".to_string(),
+            )
+            .into();
+        }
+        step.into()
+    }
+
+    pub fn install_packages_verdict(&self, packages: &[String]) -> Verdict {
+        decide_all(&self.install_packages, "install_packages", packages)
+    }
+
+    pub fn remove_packages_verdict(&self, packages: &[String]) -> Verdict {
+        decide_all(&self.remove_packages, "remove_packages", packages)
+    }
+
+    pub fn update_system_verdict(&self, scope: UpdateScope) -> Verdict {
+        self.update_system.decide("update_system", update_scope_name(scope)).into()
+    }
+
+    pub fn cgroup_apply_verdict(&self, cg: &CgroupApplyAction) -> Verdict {
+        self.cgroup_apply.decide("cgroup_apply", &cgroup_name(cg.pid, cg.unit.as_deref())).into()
+    }
+
+    pub fn cgroup_inspect_verdict(&self, cg: &CgroupInspectAction) -> Verdict {
+        self.cgroup_inspect.decide("cgroup_inspect", &cgroup_name(cg.pid, cg.unit.as_deref())).into()
+    }
+
+    pub fn firmware_op_verdict(&self, fw: &FirmwareOpAction) -> Verdict {
+        self.firmware_op.decide("firmware_op", &firmware_op_key(fw)).into()
+    }
+
+    // the tool rule, then one step per argument from the tool's argument grammar.
+    pub fn observe_verdict(&self, obs: &ObserveAction) -> Verdict {
+        let name = observe_name(obs);
+        let mut verdict = Verdict::from(self.observe.decide("observe", name));
+        if obs.tool == ObserveTool::Other && self.observe_tools.get(name).is_none() {
+            verdict.push(Step::new(
+                Decision::Deny,
+                "observe.other.unregistered".to_string(),
+                format!("no observe tool named `{name}` is registered"),
+            ));
+        }
+        verdict.steps.extend(self.observe_arg_steps(obs));
+        verdict
+    }
+
+    // exactly one step per element of `obs.args`.
+    pub fn observe_arg_steps(&self, obs: &ObserveAction) -> Vec<Step> {
+        match obs.tool {
+            ObserveTool::Other => {
+                let name = observe_name(obs);
+                observe::registered_arg_steps(self.observe_tools.get(name), name, &obs.args)
+            }
+            _ => observe::classify_observe_args(&obs.tool, &obs.args),
+        }
+    }
+
+    // registered tools this policy does not deny outright.
+    pub fn observe_tool_listing(&self) -> Vec<llm_os_common::ObserveToolInfo> {
+        let mut listing = self.observe_tools.listing();
+        listing.retain(|tool| self.observe.decide("observe", &tool.name).decision != Decision::Deny);
+        listing
+    }
+
+    pub fn log_query_verdict(&self, q: &LogQueryAction) -> Verdict {
+        self.log_query.decide("log_query", q.unit.as_deref().unwrap_or("all")).into()
+    }
+
+    pub fn approval_verdict(&self, op: ApprovalOp) -> Verdict {
+        self.approvals.decide("approvals", approval_op_name(op)).into()
+    }
+
+    pub fn session_grant_verdict(&self, op: SessionGrantOp) -> Verdict {
+        self.sessions.decide("sessions", session_grant_op_name(op)).into()
+    }
+}
+
+impl NameRules {
+    pub fn decide(&self, section: &str, name: &str) -> Step {
+        let lists = [
+            ("deny", &self.deny, Decision::Deny),
+            ("approve", &self.approve, Decision::Approve),
+            ("confirm", &self.confirm, Decision::Confirm),
+            ("allow", &self.allow, Decision::Allow),
+        ];
+        for (list, patterns, decision) in lists {
+            if let Some(pattern) = patterns.iter().find(|p| glob_match(p, name)) {
+                return Step::new(
+                    decision,
+                    format!("{section}.{list}:{pattern}"),
+                    format!("`{name}` matches {list} pattern `{pattern}`"),
+                );
+            }
+        }
+        Step::new(
+            self.default,
+            format!("{section}.default"),
+            format!("`{name}` matches no {section} rule"),
+        )
+    }
+}
+
+impl PathRules {
+    pub fn decide(&self, section: &str, requested: &str, resolved: &Path) -> Verdict {
+        let lists = [
+            ("deny", &self.deny, Decision::Deny),
+            ("approve", &self.approve, Decision::Approve),
+            ("confirm", &self.confirm, Decision::Confirm),
+            ("allow", &self.allow, Decision::Allow),
+        ];
+        let rooted = lists.iter().find_map(|(list, roots, decision)| {
+            roots
+                .iter()
+                .find(|root| resolved.starts_with(canonical_root(root)))
+                .map(|root| {
+                    Step::new(
+                        *decision,
+                        format!("{section}.{list}:{root}"),
+                        format!("{} is under {list} root {root}", resolved.display()),
+                    )
+                })
+        });
+        let mut verdict = Verdict::from(rooted.unwrap_or_else(|| {
+            Step::new(
+                self.default,
+                format!("{section}.default"),
+                format!("{} is not under any {section} root", resolved.display()),
+            )
+        }));
+
+        let has_parent_dir = Path::new(requested)
+            .components()
+            .any(|c| matches!(c, Component::ParentDir));
+        if has_parent_dir {
+            verdict.push(Step::new(
+                self.parent_dir,
+                format!("{section}.parent_dir"),
+                format!("requested path {requested} contains `..`"),
+            ));
+        }
+        verdict
+    }
+}
+
+// where a file action lands: the path made absolute against the cwd with its parent canonicalized.
+// the final component is never followed, so a symlink there is judged by its own location.
+pub fn target_path(path: &str) -> Result<PathBuf, String> {
+    let requested = Path::new(path);
+    let absolute = if requested.is_absolute() {
+        requested.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map_err(|err| format!("current dir: {err}"))?
+            .join(requested)
+    };
+
+    let mut components: Vec<Component> = absolute.components().collect();
+    let name = match components.pop() {
+        Some(Component::Normal(name)) => name.to_os_string(),
+        _ => return Err(format!("{path} does not name a file")),
+    };
+    let parent: PathBuf = components.iter().collect();
+    let parent = std::fs::canonicalize(&parent)
+        .map_err(|err| format!("resolve {}: {err}", parent.display()))?;
+    Ok(parent.join(name))
+}
+
+pub fn action_name(action: &Action) -> &'static str {
+    match action {
+        Action::Exec(_) => "exec",
+        Action::ReadFile(_) => "read_file",
+        Action::WriteFile(_) => "write_file",
+        Action::ServiceControl(_) => "service_control",
+        Action::InstallPackages(_) => "install_packages",
+        Action::RemovePackages(_) => "remove_packages",
+        Action::UpdateSystem(_) => "update_system",
+        Action::Observe(_) => "observe",
+        Action::LogQuery(_) => "log_query",
+        Action::CgroupApply(_) => "cgroup_apply",
+        Action::CgroupInspect(_) => "cgroup_inspect",
+        Action::FirmwareOp(_) => "firmware_op",
+        Action::Approval(_) => "approval",
+        Action::SessionGrant(_) => "session_grant",
+        Action::Ping => "ping",
+    }
+}
+
+// roots are compared as real paths too, so `/var/run` still covers targets under `/run`.
+fn canonical_root(root: &str) -> PathBuf {
+    std::fs::canonicalize(root).unwrap_or_else(|_| PathBuf::from(root))
+}
+
+fn decide_all(rules: &NameRules, section: &str, names: &[String]) -> Verdict {
+    if names.is_empty() {
+        return Step::new(rules.default, format!("{section}.default"), "no packages listed".to_string()).into();
+    }
+    Verdict {
+        steps: names.iter().map(|n| rules.decide(section, n)).collect(),
+    }
+}
+
+pub fn observe_tool_name(tool: &ObserveTool) -> &'static str {
+    match tool {
+        ObserveTool::Ps => "ps",
+        ObserveTool::Top => "top",
+        ObserveTool::Journalctl => "journalctl",
+        ObserveTool::Perf => "perf",
+        ObserveTool::Bpftrace => "bpftrace",
+        ObserveTool::Other => "other",
+    }
+}
+
+// the name observe rules match: the tool, or the registered name for tool `other`.
+pub fn observe_name(obs: &ObserveAction) -> &str {
+    match (&obs.tool, &obs.name) {
+        (ObserveTool::Other, Some(name)) => name,
+        (tool, _) => observe_tool_name(tool),
+    }
+}
+
+// the child of the daemon's delegated cgroup that the action creates or reuses.
+pub fn cgroup_name(pid: Option<u32>, unit: Option<&str>) -> String {
+    match (unit, pid) {
+        (Some(unit), _) => unit.to_string(),
+        (None, Some(pid)) => format!("pid-{pid}"),
+        (None, None) => String::new(),
+    }
+}
+
+pub fn update_scope_name(scope: UpdateScope) -> &'static str {
+    match scope {
+        UpdateScope::Full => "full",
+        UpdateScope::RefreshOnly => "refresh_only",
+        UpdateScope::SecurityOnly => "security_only",
+    }
+}
+
+pub fn firmware_op_key(fw: &FirmwareOpAction) -> String {
+    match fw.op {
+        FirmwareOp::Inventory => "inventory".to_string(),
+        FirmwareOp::FwupdUpdate => "fwupd_update".to_string(),
+        FirmwareOp::UefiVarRead => format!("uefi_var_read:{}", fw.uefi_var_name.as_deref().unwrap_or("")),
+    }
+}
+
+pub fn approval_op_name(op: ApprovalOp) -> &'static str {
+    match op {
+        ApprovalOp::List => "list",
+        ApprovalOp::Show => "show",
+        ApprovalOp::Approve => "approve",
+        ApprovalOp::Reject => "reject",
+    }
+}
+
+pub fn session_grant_op_name(op: SessionGrantOp) -> &'static str {
+    match op {
+        SessionGrantOp::Grant => "grant",
+        SessionGrantOp::List => "list",
+        SessionGrantOp::Revoke => "revoke",
+    }
+}
+
+// supports `*` (any run of characters) and `?` (any single character); everything else is literal.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0usize, 0usize);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+// compares in time that depends only on the lengths, so response timing does not reveal a matching prefix.
+pub fn confirmation_is_valid(token: Option<&str>, expected_token: &str) -> bool {
+    let token = match token {
+        Some(t) => t.trim().as_bytes(),
+        None => return false,
+    };
+    let expected = expected_token.as_bytes();
+    let mut diff = u8::from(token.len() != expected.len());
+    for i in 0..token.len().max(expected.len()) {
+        let a = token.get(i).copied().unwrap_or(0);
+        let b = expected.get(i).copied().unwrap_or(0);
+        diff |= a ^ b;
+    }
+    std::hint::black_box(diff) == 0 && !expected.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decide(policy: &Policy, argv: &[&str]) -> Decision {
+        let action = ExecAction {
+            argv: strings(argv),
+            cwd: None,
+            env: None,
+            timeout_sec: 5,
+            as_root: false,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        let program = policy.resolve_exec(&action).unwrap();
+        policy.exec_verdict(&action, &program).decision()
+    }
+
+    #[test]
+    fn default_policy_matches_builtin_path_rules() {
+        let policy = Policy::default();
+        let tmp = std::fs::canonicalize("/tmp").unwrap();
+        assert_eq!(policy.read_file_verdict("/tmp/x", &tmp.join("x")).decision(), Decision::Allow);
+        assert_eq!(policy.read_file_verdict("x", &tmp.join("x")).decision(), Decision::Allow);
+        assert_eq!(policy.read_file_verdict("/tmp/x", Path::new("/etc/shadow")).decision(), Decision::Confirm);
+        assert_eq!(policy.read_file_verdict("/etc/passwd", Path::new("/etc/passwd")).decision(), Decision::Confirm);
+        assert_eq!(policy.read_file_verdict("/tmpfoo", Path::new("/tmpfoo")).decision(), Decision::Confirm);
+        assert_eq!(policy.write_file_verdict("/tmp/a/../b", &tmp.join("b")).decision(), Decision::Confirm);
+
+        let verdict = policy.write_file_verdict("/etc/hosts", Path::new("/etc/hosts"));
+        assert_eq!(verdict.decision(), Decision::Approve);
+        assert_eq!(verdict.decisive().unwrap().rule_id, "write_file.approve:/etc");
+        assert_eq!(policy.write_file_verdict("/etcfoo", Path::new("/etcfoo")).decision(), Decision::Confirm);
+        assert_eq!(policy.read_file_verdict("/etc/hosts", Path::new("/etc/hosts")).decision(), Decision::Confirm);
+    }
+
+    #[test]
+    fn toml_policy_overrides_sections_and_keeps_defaults() {
+        let policy: Policy = toml::from_str(
+            r#"
+            [service_control]
+            default = "confirm"
+            allow = ["ssh*.service"]
+            deny = ["dbus.service"]
+
+            [install_packages]
+            default = "deny"
+            allow = ["curl"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(policy.service_control_verdict(&ServiceControlVerb::Stop, "sshd.service").decision(), Decision::Allow);
+        assert_eq!(policy.service_control_verdict(&ServiceControlVerb::Stop, "dbus.service").decision(), Decision::Deny);
+        assert_eq!(policy.service_control_verdict(&ServiceControlVerb::Stop, "nginx.service").decision(), Decision::Confirm);
+        assert_eq!(policy.service_control_verdict(&ServiceControlVerb::Status, "nginx.service").decision(), Decision::Allow);
+        assert_eq!(policy.service_control_verdict(&ServiceControlVerb::Status, "dbus.service").decision(), Decision::Deny);
+        assert_eq!(
+            policy.install_packages_verdict(&strings(&["curl", "git"])).decision(),
+            Decision::Deny
+        );
+        assert_eq!(decide(&policy, &["dd"]), Decision::Deny);
+    }
+
+    #[test]
+    fn load_parses_json_and_rejects_unknown_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("policy.json");
+        std::fs::write(
+            &json_path,
+            r#"{"observe":{"default":"allow","deny":["bpftrace"]}}"#,
+        )
+        .unwrap();
+        let policy = Policy::load(&json_path.to_string_lossy()).unwrap();
+        let observe = |tool: ObserveTool| ObserveAction {
+            tool,
+            name: None,
+            args: vec![],
+            processes: None,
+            timeout_sec: None,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        assert_eq!(policy.observe_verdict(&observe(ObserveTool::Bpftrace)).decision(), Decision::Deny);
+        assert_eq!(policy.observe_verdict(&observe(ObserveTool::Ps)).decision(), Decision::Allow);
+
+        let toml_path = dir.path().join("policy.toml");
+        std::fs::write(&toml_path, "[exec]\ndefault = \"allow\"\nunexpected = 1\n").unwrap();
+        assert!(Policy::load(&toml_path.to_string_lossy()).is_err());
+    }
+
+    #[test]
+    fn glob_match_handles_wildcards() {
+        assert!(glob_match("mkfs*", "mkfs.ext4"));
+        assert!(glob_match("*.service", "ssh.service"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "abbc"));
+        assert!(!glob_match("dd", "ddrescue"));
+    }
+
+    #[test]
+    fn verdicts_name_the_first_strictest_rule() {
+        let policy = Policy::default();
+        let packages = vec!["curl".to_string(), "git".to_string()];
+        let verdict = policy.install_packages_verdict(&packages);
+        assert_eq!(verdict.steps.len(), 2);
+        assert_eq!(verdict.decisive().unwrap().hint, "`curl` matches no install_packages rule");
+
+        let verdict = policy.write_file_verdict("/tmp/../var/x", Path::new("/var/x"));
+        let rules: Vec<&str> = verdict.steps.iter().map(|step| step.rule_id.as_str()).collect();
+        assert_eq!(rules, ["write_file.default", "write_file.parent_dir"]);
+        assert_eq!(verdict.decisive().unwrap().rule_id, "write_file.default");
+    }
+
+    #[test]
+    fn approve_sits_between_confirm_and_deny() {
+        let policy: Policy = toml::from_str(
+            r#"
+            [write_file]
+            default = "confirm"
+            parent_dir = "deny"
+            allow = ["/tmp"]
+            approve = ["/etc"]
+
+            [remove_packages]
+            default = "approve"
+            deny = ["systemd"]
+            "#,
+        )
+        .unwrap();
+
+        let verdict = policy.write_file_verdict("/etc/hosts", Path::new("/etc/hosts"));
+        assert_eq!(verdict.decision(), Decision::Approve);
+        assert_eq!(verdict.decisive().unwrap().rule_id, "write_file.approve:/etc");
+        assert_eq!(policy.write_file_verdict("/etc/../etc/x", Path::new("/etc/x")).decision(), Decision::Deny);
+        assert_eq!(
+            policy.remove_packages_verdict(&strings(&["curl", "systemd"])).decision(),
+            Decision::Deny
+        );
+        assert_eq!(policy.remove_packages_verdict(&strings(&["curl"])).decision(), Decision::Approve);
+        assert_eq!(policy.approval_verdict(ApprovalOp::Approve).decision(), Decision::Allow);
+        assert_eq!(policy.session_grant_verdict(SessionGrantOp::Grant).decision(), Decision::Confirm);
+        assert_eq!(policy.session_grant_verdict(SessionGrantOp::List).decision(), Decision::Allow);
+        assert_eq!(policy.update_system_verdict(UpdateScope::SecurityOnly).decision(), Decision::Confirm);
+        assert_eq!(policy.update_system_verdict(UpdateScope::RefreshOnly).decision(), Decision::Allow);
+        let firmware = |op: FirmwareOp, name: Option<&str>| FirmwareOpAction {
+            op,
+            uefi_var_name: name.map(|n| n.to_string()),
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        assert_eq!(policy.firmware_op_verdict(&firmware(FirmwareOp::Inventory, None)).decision(), Decision::Allow);
+        let boot_order = firmware(FirmwareOp::UefiVarRead, Some("BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c"));
+        assert_eq!(policy.firmware_op_verdict(&boot_order).decision(), Decision::Confirm);
+        assert_eq!(
+            policy.action_verdict(&Action::FirmwareOp(firmware(FirmwareOp::FwupdUpdate, None))).decision(),
+            Decision::Deny
+        );
+    }
+
+    #[test]
+    fn confirmation_tokens_must_match_exactly() {
+        assert!(confirmation_is_valid(Some(" secret\n"), "secret"));
+        assert!(!confirmation_is_valid(Some("secreT"), "secret"));
+        assert!(!confirmation_is_valid(Some("secret2"), "secret"));
+        assert!(!confirmation_is_valid(Some("secre"), "secret"));
+        assert!(!confirmation_is_valid(None, "secret"));
+        assert!(!confirmation_is_valid(Some(""), ""));
+    }
+}
@@ -0,0 +1,686 @@
+// ABOUTME: classifies observe arguments for journalctl, perf and bpftrace against a built-in grammar,
+// ABOUTME: and for admin-registered tools against the flags they declare; anything unknown is denied.
+
+use crate::{Decision, Step};
+use anyhow::Context;
+use llm_os_common::{ObserveToolInfo, ObserveTool};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Component, PathBuf};
+use Decision::{Allow, Confirm, Deny};
+use Value::{Attached, None as NoValue, Required};
+
+const MAX_TOOL_TIMEOUT_SEC: u64 = 600;
+// journalctl, perf and bpftrace run under these unless `[timeouts.<tool>]` says otherwise.
+const DEFAULT_TIMEOUT_SEC: u64 = 5;
+const DEFAULT_MAX_TIMEOUT_SEC: u64 = 60;
+const MAX_TOOL_OUTPUT_BYTES: usize = 1024 * 1024;
+const MAX_TOOL_NAME_BYTES: usize = 64;
+
+// tools an operator registered for observe with tool `other`, loaded from the daemon's
+// `--observe-tools-path` document as `[[tools]]` entries, and the built-in tools' timeouts.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObserveCatalog {
+    #[serde(default)]
+    pub tools: Vec<RegisteredTool>,
+    // keyed by `journalctl`, `perf` or `bpftrace`.
+    #[serde(default)]
+    pub timeouts: BTreeMap<String, ToolTimeout>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolTimeout {
+    pub default_sec: u64,
+    pub max_sec: u64,
+}
+
+// runs as `path args... extra...`, where every extra argument must be one of the declared `flags`
+// or the value of one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisteredTool {
+    pub name: String,
+    pub path: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub flags: Vec<RegisteredFlag>,
+    // the default timeout; actions may ask for up to `max_timeout_sec`, which defaults to it.
+    pub timeout_sec: u64,
+    #[serde(default)]
+    pub max_timeout_sec: Option<u64>,
+    pub max_output_bytes: usize,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+// a flag the tool accepts under any of its literal names, with the value it takes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisteredFlag {
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub value: Value,
+}
+
+impl RegisteredTool {
+    fn timeout(&self) -> ToolTimeout {
+        ToolTimeout {
+            default_sec: self.timeout_sec,
+            max_sec: self.max_timeout_sec.unwrap_or(self.timeout_sec),
+        }
+    }
+}
+
+impl ObserveCatalog {
+    pub fn load(path: &str) -> anyhow::Result<ObserveCatalog> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("read observe tools at {path}"))?;
+        let catalog: ObserveCatalog =
+            toml::from_str(&text).with_context(|| format!("parse observe tools toml at {path}"))?;
+        catalog
+            .validate()
+            .map_err(|err| anyhow::anyhow!("invalid observe tools at {path}: {err}"))?;
+        Ok(catalog)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, tool) in self.tools.iter().enumerate() {
+            let name = &tool.name;
+            let plain = name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.'));
+            if name.is_empty() || name.len() > MAX_TOOL_NAME_BYTES || !plain || name.starts_with(['-', '.']) {
+                return Err(format!("tool `{name}`: names are lowercase letters, digits, `-`, `_` and `.`"));
+            }
+            if BUILTIN_TOOLS.contains(&name.as_str()) {
+                return Err(format!("tool `{name}`: the name is taken by a built-in observe tool"));
+            }
+            if self.tools[..i].iter().any(|other| &other.name == name) {
+                return Err(format!("tool `{name}` is registered twice"));
+            }
+            if !tool.path.is_absolute() || tool.path.components().any(|c| matches!(c, Component::ParentDir)) {
+                return Err(format!("tool `{name}`: path must be absolute without `..`"));
+            }
+            validate_timeout(&format!("tool `{name}`"), tool.timeout())?;
+            if !(1..=MAX_TOOL_OUTPUT_BYTES).contains(&tool.max_output_bytes) {
+                return Err(format!(
+                    "tool `{name}`: max_output_bytes must be between 1 and {MAX_TOOL_OUTPUT_BYTES}"
+                ));
+            }
+            if tool.flags.iter().any(|flag| flag.names.is_empty()) {
+                return Err(format!("tool `{name}`: every flag needs at least one name"));
+            }
+            // a glob or an `=value` in a name would let the name stand in for a value it was never declared with.
+            if let Some(flag) = tool.flags.iter().flat_map(|flag| &flag.names).find(|flag| !is_flag_name(flag)) {
+                return Err(format!(
+                    "tool `{name}`: flag `{flag}` is not a plain `-x` or `--name`; declare values with `value`"
+                ));
+            }
+        }
+        for (name, timeout) in &self.timeouts {
+            if !TIMED_TOOLS.contains(&name.as_str()) {
+                return Err(format!("timeouts.{name}: only {} have configurable timeouts", TIMED_TOOLS.join(", ")));
+            }
+            validate_timeout(&format!("timeouts.{name}"), *timeout)?;
+        }
+        Ok(())
+    }
+
+    // the timeout a tool runs under: the action's own when it is within the tool's maximum,
+    // otherwise the tool's default.
+    pub fn timeout(&self, tool: &str, requested: Option<u64>) -> Result<u64, String> {
+        let timeout = match self.get(tool) {
+            Some(registered) => registered.timeout(),
+            None => self.timeouts.get(tool).copied().unwrap_or(ToolTimeout {
+                default_sec: DEFAULT_TIMEOUT_SEC,
+                max_sec: DEFAULT_MAX_TIMEOUT_SEC,
+            }),
+        };
+        match requested {
+            None => Ok(timeout.default_sec),
+            Some(sec) if sec <= timeout.max_sec => Ok(sec),
+            Some(sec) => Err(format!("timeout_sec {sec} is above the {}s maximum for {tool}", timeout.max_sec)),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegisteredTool> {
+        self.tools.iter().find(|tool| tool.name == name)
+    }
+
+    pub fn listing(&self) -> Vec<ObserveToolInfo> {
+        self.tools
+            .iter()
+            .map(|tool| ObserveToolInfo {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                allowed_flags: tool
+                    .flags
+                    .iter()
+                    .flat_map(|flag| flag.names.iter().map(|name| flag_usage(name, flag.value)))
+                    .collect(),
+                timeout_sec: tool.timeout_sec,
+                max_timeout_sec: tool.timeout().max_sec,
+                max_output_bytes: tool.max_output_bytes,
+            })
+            .collect()
+    }
+}
+
+const BUILTIN_TOOLS: [&str; 6] = ["ps", "top", "journalctl", "perf", "bpftrace", "other"];
+// the built-in tools that run a program; ps and top read /proc and take no timeout.
+const TIMED_TOOLS: [&str; 3] = ["journalctl", "perf", "bpftrace"];
+
+fn is_flag_name(flag: &str) -> bool {
+    let word = |s: &str| {
+        s.starts_with(|c: char| c.is_ascii_alphanumeric())
+            && s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    match flag.strip_prefix("--") {
+        Some(long) => word(long),
+        None => flag.len() == 2 && flag.starts_with('-') && word(&flag[1..]),
+    }
+}
+
+// how a declared flag is spelled with its value, as ping lists it.
+fn flag_usage(name: &str, value: Value) -> String {
+    match value {
+        NoValue => name.to_string(),
+        Required => format!("{name} <value>"),
+        Attached if name.starts_with("--") => format!("{name}=<value>"),
+        Attached => format!("{name}<value>"),
+    }
+}
+
+fn validate_timeout(what: &str, timeout: ToolTimeout) -> Result<(), String> {
+    if !(1..=MAX_TOOL_TIMEOUT_SEC).contains(&timeout.default_sec) {
+        return Err(format!("{what}: the default timeout must be between 1 and {MAX_TOOL_TIMEOUT_SEC}"));
+    }
+    if !(timeout.default_sec..=MAX_TOOL_TIMEOUT_SEC).contains(&timeout.max_sec) {
+        return Err(format!(
+            "{what}: the maximum timeout must be between the default and {MAX_TOOL_TIMEOUT_SEC}"
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Value {
+    #[default]
+    None,
+    // the next argument, or attached as `--flag=value` / `-fvalue`.
+    Required,
+    // only ever attached; a bare flag takes no value.
+    Attached,
+}
+
+struct Flag {
+    names: &'static [&'static str],
+    value: Value,
+    decision: Decision,
+}
+
+const fn flag(names: &'static [&'static str], value: Value, decision: Decision) -> Flag {
+    Flag { names, value, decision }
+}
+
+const JOURNALCTL: &[Flag] = &[
+    flag(&["-u", "--unit"], Required, Allow),
+    flag(&["--user-unit"], Required, Allow),
+    flag(&["-p", "--priority"], Required, Allow),
+    flag(&["-S", "--since"], Required, Allow),
+    flag(&["-U", "--until"], Required, Allow),
+    flag(&["-n", "--lines"], Required, Allow),
+    flag(&["-o", "--output"], Required, Allow),
+    flag(&["--output-fields"], Required, Allow),
+    flag(&["-g", "--grep"], Required, Allow),
+    flag(&["--case-sensitive"], Attached, Allow),
+    flag(&["-b", "--boot"], Attached, Allow),
+    flag(&["-t", "--identifier"], Required, Allow),
+    flag(&["-T", "--exclude-identifier"], Required, Allow),
+    flag(&["--facility"], Required, Allow),
+    flag(&["-F", "--field"], Required, Allow),
+    flag(&["--cursor", "--after-cursor"], Required, Allow),
+    flag(&["-k", "--dmesg"], NoValue, Allow),
+    flag(&["-r", "--reverse"], NoValue, Allow),
+    flag(&["-x", "--catalog"], NoValue, Allow),
+    flag(&["-q", "--quiet"], NoValue, Allow),
+    flag(&["-a", "--all"], NoValue, Allow),
+    flag(&["-l", "--full", "--no-full"], NoValue, Allow),
+    flag(&["-e", "--pager-end"], NoValue, Allow),
+    flag(&["-m", "--merge"], NoValue, Allow),
+    flag(&["--no-pager", "--utc", "--no-hostname", "--show-cursor", "--system", "--user"], NoValue, Allow),
+    flag(&["--list-boots", "--disk-usage", "--fields"], NoValue, Allow),
+    // follow streams until the observe timeout; the others read journals from arbitrary paths.
+    flag(&["-f", "--follow"], NoValue, Confirm),
+    flag(&["-D", "--directory", "--file", "--root"], Required, Confirm),
+    flag(&["--verify"], NoValue, Confirm),
+    // these delete, rotate or rewrite journal files, or write files of their own.
+    flag(&["--vacuum-size", "--vacuum-time", "--vacuum-files"], Attached, Deny),
+    flag(&["--rotate", "--flush", "--sync", "--relinquish-var", "--smart-relinquish-var"], NoValue, Deny),
+    flag(&["--setup-keys", "--update-catalog"], NoValue, Deny),
+    flag(&["--cursor-file"], Attached, Deny),
+];
+
+const PERF: &[Flag] = &[
+    flag(&["-a", "--all-cpus"], NoValue, Allow),
+    flag(&["-p", "--pid", "-t", "--tid"], Required, Allow),
+    flag(&["-e", "--event"], Required, Allow),
+    flag(&["-C", "--cpu"], Required, Allow),
+    flag(&["-G", "--cgroup"], Required, Allow),
+    flag(&["-F", "--freq", "-c", "--count"], Required, Allow),
+    flag(&["-I", "--interval-print", "-r", "--repeat"], Required, Allow),
+    flag(&["-s", "--sort", "-x", "--field-separator"], Required, Allow),
+    flag(&["--call-graph"], Required, Allow),
+    flag(&["-g", "-d", "--detailed", "-v", "--verbose", "-q", "--quiet"], NoValue, Allow),
+    flag(&["-K", "--hide_kernel_symbols", "-U", "--hide_user_symbols"], NoValue, Allow),
+    flag(&["--stdio", "--no-children"], NoValue, Allow),
+    flag(&["-i", "--input"], Required, Confirm),
+    flag(&["-o", "--output", "--control"], Attached, Deny),
+];
+
+const PERF_SUBCOMMANDS: &[(&str, Decision)] = &[
+    ("stat", Allow),
+    ("top", Allow),
+    ("list", Allow),
+    ("version", Allow),
+    // record writes perf.data into the daemon's working directory; the rest read it back.
+    ("record", Confirm),
+    ("report", Confirm),
+    ("script", Confirm),
+    ("annotate", Confirm),
+    ("trace", Confirm),
+    // these change kernel probes or perf's own configuration and caches.
+    ("probe", Deny),
+    ("config", Deny),
+    ("buildid-cache", Deny),
+    ("daemon", Deny),
+];
+
+const BPFTRACE: &[Flag] = &[
+    flag(&["-l"], NoValue, Allow),
+    flag(&["-p"], Required, Allow),
+    flag(&["-f", "-B"], Required, Allow),
+    flag(&["-v", "-q", "--no-warnings", "-h", "--help", "-V", "--version"], NoValue, Allow),
+    // a program runs in the kernel as root, so each one is confirmed.
+    flag(&["-e"], Required, Confirm),
+    flag(&["-c", "-o", "-I", "--include"], Attached, Deny),
+    flag(&["--unsafe"], NoValue, Deny),
+];
+
+fn grammar(tool: &ObserveTool) -> Option<&'static [Flag]> {
+    match tool {
+        ObserveTool::Journalctl => Some(JOURNALCTL),
+        ObserveTool::Perf => Some(PERF),
+        ObserveTool::Bpftrace => Some(BPFTRACE),
+        ObserveTool::Ps | ObserveTool::Top | ObserveTool::Other => None,
+    }
+}
+
+// registered tools take no positional arguments: each extra argument is a declared flag or its value,
+// read with the same value rules as the built-in grammars.
+pub(crate) fn registered_arg_steps(tool: Option<&RegisteredTool>, name: &str, args: &[String]) -> Vec<Step> {
+    let flags = tool.map_or(&[][..], |tool| &tool.flags[..]);
+    let allow = |flag: &RegisteredFlag, spelled: &str| {
+        Step::new(
+            Allow,
+            format!("observe.{name}.allow:{}", flag.names[0]),
+            format!("`{spelled}` is a declared {name} flag"),
+        )
+    };
+    let unknown = |arg: &str, hint: String| {
+        Step::new(Deny, format!("observe.{name}.unknown"), format!("`{arg}`: {hint}"))
+    };
+
+    let mut steps = Vec::with_capacity(args.len());
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        i += 1;
+        let (spelled, attached) = spelled_flag(arg);
+        let flag = match flags.iter().find(|flag| arg.starts_with('-') && flag.names.iter().any(|n| n == spelled)) {
+            Some(flag) => flag,
+            None => {
+                steps.push(unknown(arg, format!("not a declared flag of {name}")));
+                continue;
+            }
+        };
+        if attached && flag.value == NoValue {
+            steps.push(unknown(arg, format!("`{spelled}` takes no value")));
+            continue;
+        }
+        steps.push(allow(flag, spelled));
+        if flag.value == Required && !attached {
+            match args.get(i) {
+                Some(value) if !value.starts_with('-') => steps.push(allow(flag, spelled)),
+                Some(value) => steps.push(unknown(value, format!("pass the value of `{spelled}` as `{spelled}=...`"))),
+                None => {
+                    steps.pop();
+                    steps.push(unknown(arg, "missing its value".to_string()));
+                }
+            }
+            i += 1;
+        }
+    }
+    steps
+}
+
+// `--flag=value` and `-fvalue` split into the flag as spelled and whether a value is attached.
+fn spelled_flag(arg: &str) -> (&str, bool) {
+    match arg.strip_prefix("--") {
+        Some(long) => match long.split_once('=') {
+            Some((flag, _)) => (&arg[..flag.len() + 2], true),
+            None => (arg, false),
+        },
+        None => {
+            let end = arg.char_indices().nth(2).map_or(arg.len(), |(end, _)| end);
+            (&arg[..end], end < arg.len())
+        }
+    }
+}
+
+// one step per argument, in order; a flag's separate value gets the flag's step.
+pub(crate) fn classify_observe_args(tool: &ObserveTool, args: &[String]) -> Vec<Step> {
+    let name = crate::observe_tool_name(tool);
+    let flags = match grammar(tool) {
+        Some(flags) => flags,
+        None => return vec![],
+    };
+    let step = |decision: Decision, list: &str, subject: &str, hint: String| {
+        Step::new(decision, format!("observe.{name}.{list}:{subject}"), hint)
+    };
+    let class = |flag: &Flag, spelled: &str| {
+        let (list, what) = match flag.decision {
+            Allow => ("allow", "a read-only"),
+            Confirm => ("confirm", "a confirmed"),
+            Deny | Decision::Approve => ("deny", "a rejected"),
+        };
+        step(flag.decision, list, flag.names[0], format!("`{spelled}` is {what} {name} option"))
+    };
+    let unknown = |arg: &str, hint: String| {
+        Step::new(Deny, format!("observe.{name}.unknown"), format!("`{arg}`: {hint}"))
+    };
+
+    let mut steps = Vec::with_capacity(args.len());
+    let mut positional = 0usize;
+    let mut options_ended = false;
+    let mut listing = false;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        i += 1;
+        if !options_ended && arg == "--" {
+            options_ended = true;
+            steps.push(step(Allow, "allow", "--", "`--` ends the options".to_string()));
+            continue;
+        }
+        // `sleep <seconds>` as the last two arguments gives perf a bounded workload to count over.
+        if tool == &ObserveTool::Perf && positional > 0 && arg == "sleep" && i + 1 == args.len() && is_sleep_seconds(&args[i]) {
+            let hint = format!("`sleep {}` is a bounded perf workload", args[i]);
+            steps.push(step(Allow, "allow", "sleep", hint.clone()));
+            steps.push(step(Allow, "allow", "sleep", hint));
+            i += 1;
+            continue;
+        }
+        if options_ended || !arg.starts_with('-') || arg == "-" {
+            steps.push(positional_step(tool, name, positional, arg, listing));
+            positional += 1;
+            continue;
+        }
+
+        let (spelled, attached) = spelled_flag(arg);
+        let flag = match flags.iter().find(|flag| flag.names.contains(&spelled)) {
+            Some(flag) => flag,
+            None => {
+                steps.push(unknown(arg, format!("not in the {name} argument grammar")));
+                continue;
+            }
+        };
+        if attached && flag.value == NoValue {
+            steps.push(unknown(arg, format!("`{spelled}` takes no value")));
+            continue;
+        }
+        listing |= tool == &ObserveTool::Bpftrace && spelled == "-l";
+        steps.push(class(flag, spelled));
+        if flag.value == Required && !attached {
+            // a value that looks like an option could be read as one by the tool, so it must be attached.
+            match args.get(i) {
+                Some(value) if !value.starts_with('-') => steps.push(class(flag, spelled)),
+                Some(value) => steps.push(unknown(value, format!("pass the value of `{spelled}` as `{spelled}=...`"))),
+                None => {
+                    steps.pop();
+                    steps.push(unknown(arg, "missing its value".to_string()));
+                }
+            }
+            i += 1;
+        }
+    }
+    steps
+}
+
+// a plain number of seconds, as `sleep` reads it without suffixes.
+fn is_sleep_seconds(arg: &str) -> bool {
+    let (whole, fraction) = arg.split_once('.').unwrap_or((arg, "0"));
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    arg.len() <= 8 && digits(whole) && digits(fraction)
+}
+
+fn positional_step(tool: &ObserveTool, name: &str, index: usize, arg: &str, listing: bool) -> Step {
+    let positional = |decision: Decision, list: &str, subject: &str, hint: String| {
+        Step::new(decision, format!("observe.{name}.{list}:{subject}"), hint)
+    };
+    let unknown = |hint: &str| Step::new(Deny, format!("observe.{name}.unknown"), format!("`{arg}`: {hint}"));
+    match tool {
+        ObserveTool::Journalctl => {
+            let field = arg.split_once('=').map(|(field, _)| field).unwrap_or("");
+            let is_match = !field.is_empty()
+                && field.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+            if is_match {
+                positional(Allow, "allow", "FIELD=value", format!("`{arg}` is a journal field match"))
+            } else {
+                unknown("journalctl arguments must be FIELD=value matches")
+            }
+        }
+        ObserveTool::Perf if index == 0 => match PERF_SUBCOMMANDS.iter().find(|(sub, _)| *sub == arg) {
+            Some((sub, Allow)) => positional(Allow, "allow", sub, format!("`perf {sub}` only reads counters")),
+            Some((sub, Confirm)) => positional(Confirm, "confirm", sub, format!("`perf {sub}` reads or writes perf.data")),
+            Some((sub, _)) => positional(Deny, "deny", sub, format!("`perf {sub}` changes system state")),
+            None => unknown("not a perf subcommand in the grammar"),
+        },
+        ObserveTool::Perf => unknown("perf runs trailing arguments as a command; only `sleep <seconds>` is accepted"),
+        ObserveTool::Bpftrace if listing => {
+            positional(Allow, "allow", "probe", format!("`{arg}` is a probe pattern to list"))
+        }
+        _ => unknown("program files are not accepted; pass the program with -e"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(tool: ObserveTool, args: &[&str]) -> Vec<(Decision, String)> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        classify_observe_args(&tool, &args)
+            .into_iter()
+            .map(|step| (step.decision, step.rule_id))
+            .collect()
+    }
+
+    #[test]
+    fn journalctl_flags_are_allowed_confirmed_or_denied() {
+        assert_eq!(
+            classify(ObserveTool::Journalctl, &["-u", "ssh.service", "--since=-1h", "-n50", "_PID=1"]),
+            vec![
+                (Allow, "observe.journalctl.allow:-u".to_string()),
+                (Allow, "observe.journalctl.allow:-u".to_string()),
+                (Allow, "observe.journalctl.allow:-S".to_string()),
+                (Allow, "observe.journalctl.allow:-n".to_string()),
+                (Allow, "observe.journalctl.allow:FIELD=value".to_string()),
+            ]
+        );
+        assert_eq!(classify(ObserveTool::Journalctl, &["-f"])[0].0, Confirm);
+        assert_eq!(
+            classify(ObserveTool::Journalctl, &["--vacuum-size=1"])[0],
+            (Deny, "observe.journalctl.deny:--vacuum-size".to_string())
+        );
+        assert_eq!(
+            classify(ObserveTool::Journalctl, &["--bogus"])[0],
+            (Deny, "observe.journalctl.unknown".to_string())
+        );
+        // a separate value that looks like an option is not trusted to be read as a value.
+        let steps = classify(ObserveTool::Journalctl, &["-n", "--vacuum-size=1"]);
+        assert_eq!(steps[1], (Deny, "observe.journalctl.unknown".to_string()));
+        assert_eq!(
+            classify(ObserveTool::Journalctl, &["-u"]),
+            vec![(Deny, "observe.journalctl.unknown".to_string())]
+        );
+        assert_eq!(classify(ObserveTool::Journalctl, &["-rq"])[0].0, Deny);
+    }
+
+    #[test]
+    fn perf_and_bpftrace_positionals_are_classified_by_tool() {
+        let perf = classify(ObserveTool::Perf, &["stat", "-a", "-e", "cycles"]);
+        assert!(perf.iter().all(|(decision, _)| *decision == Allow), "{perf:?}");
+        assert_eq!(classify(ObserveTool::Perf, &["record", "-a"])[0].0, Confirm);
+        assert_eq!(classify(ObserveTool::Perf, &["record", "-o", "/etc/passwd"])[1].0, Deny);
+        assert_eq!(
+            classify(ObserveTool::Perf, &["stat", "--", "sh", "-c", "id"])[2],
+            (Deny, "observe.perf.unknown".to_string())
+        );
+        assert_eq!(classify(ObserveTool::Perf, &["probe", "-a", "vfs_read"])[0].0, Deny);
+
+        // the sampling run PROMPT.md suggests, with and without `--`.
+        let sampling = classify(ObserveTool::Perf, &["stat", "-a", "sleep", "10"]);
+        assert!(sampling.iter().all(|(decision, _)| *decision == Allow), "{sampling:?}");
+        assert_eq!(sampling[2].1, "observe.perf.allow:sleep");
+        let sampling = classify(ObserveTool::Perf, &["stat", "-a", "--", "sleep", "0.5"]);
+        assert!(sampling.iter().all(|(decision, _)| *decision == Allow), "{sampling:?}");
+        for args in [
+            &["stat", "-a", "sleep", "10", "id"][..],
+            &["stat", "-a", "sleep", "1d"],
+            &["stat", "-a", "sleep", "-1"],
+            &["stat", "-a", "sleep"],
+            &["stat", "-a", "--", "/bin/sleep", "10"],
+        ] {
+            let steps = classify(ObserveTool::Perf, args);
+            assert!(steps.iter().any(|(decision, _)| *decision == Deny), "{args:?}: {steps:?}");
+        }
+
+        assert_eq!(
+            classify(ObserveTool::Bpftrace, &["-l", "tracepoint:syscalls:*"]),
+            vec![
+                (Allow, "observe.bpftrace.allow:-l".to_string()),
+                (Allow, "observe.bpftrace.allow:probe".to_string()),
+            ]
+        );
+        assert_eq!(classify(ObserveTool::Bpftrace, &["-e", "BEGIN { exit(); }"])[1].0, Confirm);
+        assert_eq!(classify(ObserveTool::Bpftrace, &["/tmp/x.bt"])[0].0, Deny);
+        assert_eq!(classify(ObserveTool::Bpftrace, &["--unsafe", "-e", "x"])[0].0, Deny);
+    }
+
+    #[test]
+    fn registered_tools_are_validated_and_only_take_their_allowed_flags() {
+        let catalog: ObserveCatalog = toml::from_str(
+            r#"
+            [[tools]]
+            name = "ss"
+            path = "/usr/bin/ss"
+            args = ["-H"]
+            flags = [
+                { names = ["-t", "--tcp"] },
+                { names = ["-u", "-l", "-n"] },
+                { names = ["-A", "--query"], value = "required" },
+                { names = ["-o", "--options"], value = "attached" },
+            ]
+            timeout_sec = 5
+            max_output_bytes = 65536
+            "#,
+        )
+        .unwrap();
+        assert!(catalog.validate().is_ok());
+        let steps = |args: &[&str]| -> Vec<(Decision, String)> {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            registered_arg_steps(catalog.get("ss"), "ss", &args)
+                .into_iter()
+                .map(|step| (step.decision, step.rule_id))
+                .collect()
+        };
+        let allow = |flag: &str| (Allow, format!("observe.ss.allow:{flag}"));
+        let unknown = (Deny, "observe.ss.unknown".to_string());
+        assert_eq!(
+            steps(&["--tcp", "--query=tcp", "-A", "udp", "-oinfo", "-K", "/etc"]),
+            vec![allow("-t"), allow("-A"), allow("-A"), allow("-A"), allow("-o"), unknown.clone(), unknown.clone()]
+        );
+        // a flag without a value takes none, and a separate value that looks like an option is denied.
+        assert_eq!(steps(&["-t=x"]), vec![unknown.clone()]);
+        assert_eq!(steps(&["-tn"]), vec![unknown.clone()]);
+        assert_eq!(steps(&["--query", "-K"]), vec![allow("-A"), unknown.clone()]);
+        assert_eq!(steps(&["--query"]), vec![unknown.clone()]);
+        assert_eq!(steps(&["-o", "info"]), vec![allow("-o"), unknown]);
+        let listed = &catalog.listing()[0].allowed_flags;
+        assert_eq!(listed[..2], ["-t", "--tcp"]);
+        assert_eq!(listed[6..], ["--query <value>", "-o<value>", "--options=<value>"]);
+
+        let invalid = |change: &dyn Fn(&mut RegisteredTool)| {
+            let mut catalog = catalog.clone();
+            change(&mut catalog.tools[0]);
+            catalog.validate().unwrap_err()
+        };
+        assert!(invalid(&|tool| tool.name = "journalctl".to_string()).contains("built-in"));
+        assert!(invalid(&|tool| tool.name = "Bad Name".to_string()).contains("names are"));
+        assert!(invalid(&|tool| tool.path = PathBuf::from("bin/ss")).contains("absolute"));
+        assert!(invalid(&|tool| tool.timeout_sec = 0).contains("default timeout"));
+        let with_flag = |flag: &'static str| {
+            invalid(&move |tool| tool.flags.push(RegisteredFlag { names: vec![flag.to_string()], value: NoValue }))
+        };
+        for flag in ["/etc", "--query=*", "-o*", "--que?y", "--query=tcp", "-ab", "--", "-"] {
+            assert!(with_flag(flag).contains("is not a plain"), "{flag}");
+        }
+        assert!(invalid(&|tool| tool.flags.push(RegisteredFlag { names: vec![], value: Required }))
+            .contains("at least one name"));
+        let mut twice = catalog.clone();
+        twice.tools.push(catalog.tools[0].clone());
+        assert_eq!(twice.validate().unwrap_err(), "tool `ss` is registered twice");
+    }
+
+    #[test]
+    fn timeouts_default_per_tool_and_are_capped_at_the_tool_maximum() {
+        let catalog: ObserveCatalog = toml::from_str(
+            r#"
+            [timeouts.perf]
+            default_sec = 10
+            max_sec = 120
+
+            [[tools]]
+            name = "ss"
+            path = "/usr/bin/ss"
+            timeout_sec = 3
+            max_timeout_sec = 20
+            max_output_bytes = 65536
+            "#,
+        )
+        .unwrap();
+        assert!(catalog.validate().is_ok());
+        assert_eq!(catalog.timeout("perf", None), Ok(10));
+        assert_eq!(catalog.timeout("perf", Some(90)), Ok(90));
+        assert_eq!(
+            catalog.timeout("perf", Some(121)),
+            Err("timeout_sec 121 is above the 120s maximum for perf".to_string())
+        );
+        assert_eq!(catalog.timeout("journalctl", None), Ok(DEFAULT_TIMEOUT_SEC));
+        assert!(catalog.timeout("bpftrace", Some(DEFAULT_MAX_TIMEOUT_SEC + 1)).is_err());
+        assert_eq!(catalog.timeout("ss", None), Ok(3));
+        assert_eq!(catalog.timeout("ss", Some(20)), Ok(20));
+        assert_eq!(catalog.listing()[0].max_timeout_sec, 20);
+
+        let mut invalid = catalog.clone();
+        invalid.tools[0].max_timeout_sec = Some(2);
+        assert!(invalid.validate().unwrap_err().contains("maximum timeout"));
+        let mut invalid = catalog.clone();
+        invalid.timeouts.insert("ps".to_string(), ToolTimeout { default_sec: 1, max_sec: 1 });
+        assert!(invalid.validate().unwrap_err().contains("configurable timeouts"));
+    }
+}
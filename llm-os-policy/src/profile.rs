@@ -0,0 +1,297 @@
+// ABOUTME: selects a policy profile for the connecting peer by uid, gid or supplementary groups.
+// ABOUTME: profiles replace whole sections of the base policy; `{home}` in path roots expands per peer.
+
+use crate::{ExecRules, NameRules, PathRules, Policy};
+use serde::Deserialize;
+use std::ffi::{CStr, CString};
+
+// a profile matches when any selector matches; profiles are tried in order and the first match wins.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub name: String,
+    #[serde(default)]
+    pub uids: Vec<u32>,
+    #[serde(default)]
+    pub uid_range: Option<[u32; 2]>,
+    #[serde(default)]
+    pub gids: Vec<u32>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+    #[serde(default)]
+    pub exec: Option<ExecRules>,
+    #[serde(default)]
+    pub read_file: Option<PathRules>,
+    #[serde(default)]
+    pub write_file: Option<PathRules>,
+    #[serde(default)]
+    pub service_control: Option<NameRules>,
+    #[serde(default)]
+    pub install_packages: Option<NameRules>,
+    #[serde(default)]
+    pub remove_packages: Option<NameRules>,
+    #[serde(default)]
+    pub update_system: Option<NameRules>,
+    #[serde(default)]
+    pub cgroup_apply: Option<NameRules>,
+    #[serde(default)]
+    pub cgroup_inspect: Option<NameRules>,
+    #[serde(default)]
+    pub firmware_op: Option<NameRules>,
+    #[serde(default)]
+    pub observe: Option<NameRules>,
+    #[serde(default)]
+    pub log_query: Option<NameRules>,
+    #[serde(default)]
+    pub approvals: Option<NameRules>,
+    #[serde(default)]
+    pub sessions: Option<NameRules>,
+}
+
+// who is on the other end of the socket, as far as profile selection is concerned.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Peer {
+    pub uid: u32,
+    pub gid: u32,
+    #[serde(default)]
+    pub groups: Vec<u32>,
+    #[serde(default)]
+    pub home: Option<String>,
+}
+
+impl Profile {
+    pub fn matches(&self, peer: &Peer) -> bool {
+        if self.uids.contains(&peer.uid) {
+            return true;
+        }
+        if let Some([low, high]) = self.uid_range {
+            if (low..=high).contains(&peer.uid) {
+                return true;
+            }
+        }
+        let in_group = |gid: u32| peer.gid == gid || peer.groups.contains(&gid);
+        if self.gids.iter().any(|gid| in_group(*gid)) {
+            return true;
+        }
+        self.groups
+            .iter()
+            .filter_map(|name| group_gid(name))
+            .any(in_group)
+    }
+
+    fn apply(&self, base: &Policy) -> Policy {
+        let mut policy = base.clone();
+        policy.profiles.clear();
+        policy.active_profile = Some(self.name.clone());
+        if let Some(exec) = &self.exec {
+            policy.exec = exec.clone();
+        }
+        if let Some(rules) = &self.read_file {
+            policy.read_file = rules.clone();
+        }
+        if let Some(rules) = &self.write_file {
+            policy.write_file = rules.clone();
+        }
+        if let Some(rules) = &self.service_control {
+            policy.service_control = rules.clone();
+        }
+        if let Some(rules) = &self.install_packages {
+            policy.install_packages = rules.clone();
+        }
+        if let Some(rules) = &self.remove_packages {
+            policy.remove_packages = rules.clone();
+        }
+        if let Some(rules) = &self.update_system {
+            policy.update_system = rules.clone();
+        }
+        if let Some(rules) = &self.cgroup_apply {
+            policy.cgroup_apply = rules.clone();
+        }
+        if let Some(rules) = &self.cgroup_inspect {
+            policy.cgroup_inspect = rules.clone();
+        }
+        if let Some(rules) = &self.firmware_op {
+            policy.firmware_op = rules.clone();
+        }
+        if let Some(rules) = &self.observe {
+            policy.observe = rules.clone();
+        }
+        if let Some(rules) = &self.log_query {
+            policy.log_query = rules.clone();
+        }
+        if let Some(rules) = &self.approvals {
+            policy.approvals = rules.clone();
+        }
+        if let Some(rules) = &self.sessions {
+            policy.sessions = rules.clone();
+        }
+        policy
+    }
+}
+
+impl Policy {
+    // the effective policy for one request; without profiles every peer gets the base policy.
+    pub fn for_peer(&self, peer: Option<&Peer>) -> Result<Policy, String> {
+        let mut policy = if self.profiles.is_empty() {
+            self.clone()
+        } else {
+            let peer = peer.ok_or_else(|| "peer credentials are unavailable".to_string())?;
+            let profile = self
+                .profiles
+                .iter()
+                .find(|profile| profile.matches(peer))
+                .ok_or_else(|| format!("no policy profile matches uid {} gid {}", peer.uid, peer.gid))?;
+            profile.apply(self)
+        };
+
+        let home = peer.and_then(|peer| peer.home.as_deref());
+        policy.read_file.expand_home(home);
+        policy.write_file.expand_home(home);
+        Ok(policy)
+    }
+}
+
+impl PathRules {
+    // roots that mention `{home}` are dropped when the peer has no known home directory.
+    fn expand_home(&mut self, home: Option<&str>) {
+        for roots in [&mut self.allow, &mut self.confirm, &mut self.approve, &mut self.deny] {
+            *roots = roots
+                .iter()
+                .filter_map(|root| {
+                    if root.contains("{home}") {
+                        home.map(|home| root.replace("{home}", home))
+                    } else {
+                        Some(root.clone())
+                    }
+                })
+                .collect();
+        }
+    }
+}
+
+// resolves a group name through nss; unknown names never match.
+fn group_gid(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    let mut group: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 16 * 1024];
+    let rc = unsafe {
+        libc::getgrnam_r(
+            name.as_ptr(),
+            &mut group,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+    Some(group.gr_gid)
+}
+
+// looks up the home directory of a uid through nss.
+pub fn home_dir(uid: u32) -> Option<String> {
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 16 * 1024];
+    let rc = unsafe { libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() || passwd.pw_dir.is_null() {
+        return None;
+    }
+    let dir = unsafe { CStr::from_ptr(passwd.pw_dir) };
+    Some(dir.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Decision;
+    use llm_os_common::ServiceControlVerb;
+    use super::*;
+    use std::path::Path;
+
+    fn profiled() -> Policy {
+        toml::from_str(
+            r#"
+            [[profiles]]
+            name = "ops"
+            groups = ["ops-group-that-does-not-exist", "root"]
+            gids = [2000]
+
+            [profiles.service_control]
+            default = "allow"
+
+            [[profiles]]
+            name = "users"
+            uid_range = [1000, 59999]
+
+            [profiles.service_control]
+            default = "deny"
+
+            [profiles.read_file]
+            default = "deny"
+            parent_dir = "deny"
+            allow = ["{home}"]
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn peer(uid: u32, gid: u32, groups: &[u32]) -> Peer {
+        Peer {
+            uid,
+            gid,
+            groups: groups.to_vec(),
+            home: Some(format!("/home/u{uid}")),
+        }
+    }
+
+    #[test]
+    fn first_matching_profile_replaces_its_sections() {
+        let policy = profiled();
+
+        let ops = policy.for_peer(Some(&peer(1500, 100, &[2000]))).unwrap();
+        assert_eq!(ops.active_profile.as_deref(), Some("ops"));
+        assert_eq!(ops.service_control_verdict(&ServiceControlVerb::Stop, "nginx.service").decision(), Decision::Allow);
+        assert_eq!(ops.read_file_verdict("/tmp/x", Path::new("/tmp/x")).decision(), Decision::Allow);
+
+        let user = policy.for_peer(Some(&peer(1500, 100, &[]))).unwrap();
+        assert_eq!(user.service_control_verdict(&ServiceControlVerb::Stop, "nginx.service").decision(), Decision::Deny);
+        assert_eq!(
+            user.read_file_verdict("/home/u1500/notes", Path::new("/home/u1500/notes")).decision(),
+            Decision::Allow
+        );
+        assert_eq!(user.read_file_verdict("/tmp/x", Path::new("/tmp/x")).decision(), Decision::Deny);
+    }
+
+    #[test]
+    fn group_names_match_primary_and_supplementary_groups() {
+        let policy = profiled();
+        let root_gid = group_gid("root").unwrap();
+        for p in [peer(70000, root_gid, &[]), peer(70000, 5, &[root_gid])] {
+            let selected = policy.for_peer(Some(&p)).unwrap();
+            assert_eq!(selected.service_control_verdict(&ServiceControlVerb::Stop, "x.service").decision(), Decision::Allow);
+        }
+    }
+
+    #[test]
+    fn unmatched_or_unknown_peers_are_rejected() {
+        let policy = profiled();
+        let err = policy.for_peer(Some(&peer(70000, 70000, &[]))).unwrap_err();
+        assert!(err.contains("no policy profile matches uid 70000"));
+        assert!(policy.for_peer(None).is_err());
+
+        let base = Policy::default();
+        assert!(base.for_peer(None).is_ok());
+    }
+
+    #[test]
+    fn home_roots_are_dropped_without_a_home() {
+        let policy = profiled();
+        let mut p = peer(1500, 100, &[]);
+        p.home = None;
+        let user = policy.for_peer(Some(&p)).unwrap();
+        assert!(user.read_file.allow.is_empty());
+    }
+}
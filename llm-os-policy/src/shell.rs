@@ -0,0 +1,767 @@
+// ABOUTME: looks through interpreter and wrapper invocations (sh -c, env, sudo, xargs, ...) for exec policy.
+// ABOUTME: every command found inside is decided like a top-level exec; anything unparseable gets `exec.unparsed`.
+
+use crate::exec::file_name;
+use crate::{Policy, ResolvedProgram, Step, Verdict};
+use llm_os_common::ExecAction;
+use std::path::{Path, PathBuf};
+
+// nested wrappers beyond this depth are treated as unparseable.
+const MAX_DEPTH: usize = 8;
+// variables known not to change which program a name runs or load code into it. any other name
+// (PATH, LD_*, BASH_FUNC_*, SHELLOPTS, PS4, GCONV_PATH, ...) might, so a command that sets one
+// cannot be vouched for.
+const INERT_VARS: [&str; 7] = ["LANG", "LANGUAGE", "TZ", "TERM", "COLUMNS", "LINES", "NO_COLOR"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wrapper {
+    Shell,
+    Env,
+    Sudo,
+    Nohup,
+    Timeout,
+    Xargs,
+}
+
+// one simple command from a shell script, with the files its redirections touch.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct ShellCommand {
+    pub argv: Vec<String>,
+    // names of the `NAME=value` prefix assignments.
+    pub assignments: Vec<String>,
+    pub reads: Vec<String>,
+    pub writes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Separator,
+    Redirect { write: bool, dup: bool },
+}
+
+// every command a wrapper would run, as verdict steps; empty when the program is not a wrapper.
+pub(super) fn wrapped_verdict(
+    policy: &Policy,
+    exec: &ExecAction,
+    program: &ResolvedProgram,
+    depth: usize,
+) -> Verdict {
+    let wrapper = match wrapper_kind(program) {
+        Some(wrapper) => wrapper,
+        None => return Verdict::default(),
+    };
+    let name = file_name(Path::new(&program.requested));
+    if depth >= MAX_DEPTH {
+        return unparsed(policy, &name, "wrappers are nested too deeply");
+    }
+    let args = exec.argv.get(1..).unwrap_or_default();
+
+    if wrapper == Wrapper::Shell {
+        return match shell_script(args) {
+            Ok(script) => script_verdict(policy, exec, &name, script, depth),
+            Err(err) => unparsed(policy, &name, &err),
+        };
+    }
+
+    let (inner, changed) = match wrapped_argv(wrapper, args) {
+        Ok(inner) => inner,
+        Err(err) => return unparsed(policy, &name, &err),
+    };
+    let mut verdict = match inner {
+        Some(argv) => command_verdict(policy, exec, &name, argv, exec.cwd.clone(), depth),
+        None => Verdict::default(),
+    };
+    if let Some(var) = changed {
+        verdict.extend(unparsed(policy, &name, &format!("the command runs with {var} changed")));
+    }
+    // xargs appends arguments read from stdin, so argument rules cannot vouch for the final command.
+    if wrapper == Wrapper::Xargs {
+        verdict.extend(unparsed(policy, &name, "arguments are read from stdin"));
+    }
+    verdict
+}
+
+// exec.env reaches every program, wrapper or not, so any variable that is not known to be inert is unparsed.
+pub(super) fn env_verdict(policy: &Policy, exec: &ExecAction, program: &ResolvedProgram) -> Verdict {
+    match exec.env.iter().flatten().map(|(key, _)| key).find(|key| is_program_var(key)) {
+        Some(var) => unparsed(policy, &file_name(Path::new(&program.requested)), &format!("exec.env sets {var}")),
+        None => Verdict::default(),
+    }
+}
+
+fn unparsed(policy: &Policy, wrapper: &str, reason: &str) -> Verdict {
+    Step::new(
+        policy.exec.unparsed,
+        "exec.unparsed".to_string(),
+        format!("{wrapper} could not be inspected: {reason}"),
+    )
+    .into()
+}
+
+fn wrapper_kind(program: &ResolvedProgram) -> Option<Wrapper> {
+    let names = [
+        file_name(&program.path),
+        file_name(Path::new(&program.requested)),
+    ];
+    names.iter().find_map(|name| match name.as_str() {
+        "sh" | "bash" | "dash" => Some(Wrapper::Shell),
+        "env" => Some(Wrapper::Env),
+        "sudo" => Some(Wrapper::Sudo),
+        "nohup" => Some(Wrapper::Nohup),
+        "timeout" => Some(Wrapper::Timeout),
+        "xargs" => Some(Wrapper::Xargs),
+        _ => None,
+    })
+}
+
+fn command_verdict(
+    policy: &Policy,
+    template: &ExecAction,
+    wrapper: &str,
+    argv: Vec<String>,
+    cwd: Option<String>,
+    depth: usize,
+) -> Verdict {
+    let inner = ExecAction {
+        argv,
+        cwd,
+        ..template.clone()
+    };
+    let program = match policy.resolve_exec(&inner) {
+        Ok(program) => program,
+        Err(err) => return unparsed(policy, wrapper, &err),
+    };
+    let mut verdict = Verdict::from(policy.exec.decide(&inner, &program));
+    verdict.extend(wrapped_verdict(policy, &inner, &program, depth + 1));
+    for step in &mut verdict.steps {
+        step.hint = format!("via {wrapper}: {}", step.hint);
+    }
+    verdict
+}
+
+fn script_verdict(
+    policy: &Policy,
+    exec: &ExecAction,
+    wrapper: &str,
+    script: &str,
+    depth: usize,
+) -> Verdict {
+    let commands = match parse(script) {
+        Ok(commands) => commands,
+        Err(err) => return unparsed(policy, wrapper, &err),
+    };
+
+    let mut cwd = exec.cwd.clone();
+    let mut verdict = Verdict::default();
+    for command in commands {
+        if let Some(var) = command.assignments.iter().find(|name| is_program_var(name)) {
+            verdict.extend(unparsed(policy, wrapper, &format!("the script sets {var}")));
+        }
+        for target in &command.reads {
+            let path = join(cwd.as_deref(), target);
+            verdict.extend(policy.read_file_verdict(target, &real_path(&path)));
+        }
+        for target in command.writes.iter().filter(|t| t.as_str() != "/dev/null") {
+            let path = join(cwd.as_deref(), target);
+            verdict.extend(policy.write_file_verdict(target, &real_path(&path)));
+        }
+
+        let mut argv = command.argv;
+        if argv.first().map(String::as_str) == Some("exec") {
+            argv.remove(0);
+        }
+        let name = match argv.first() {
+            Some(name) => name.as_str(),
+            None => continue,
+        };
+        match name {
+            // later relative operands depend on where `cd` lands, so follow it.
+            "cd" => match argv.get(1) {
+                Some(dir) if argv.len() == 2 && dir != "-" => {
+                    cwd = Some(join(cwd.as_deref(), dir));
+                }
+                _ => verdict.extend(unparsed(policy, wrapper, "cd target is not a single path")),
+            },
+            "export" | "unset" | "readonly" | "declare" | "typeset" | "local" => {
+                let mut names = argv[1..].iter().map(|arg| arg.split('=').next().unwrap_or_default());
+                match names.find(|name| is_program_var(name)) {
+                    Some(var) => verdict.extend(unparsed(policy, wrapper, &format!("`{name}` changes {var}"))),
+                    None if matches!(name, "export" | "unset") => {}
+                    None => verdict.extend(command_verdict(policy, exec, wrapper, argv, cwd.clone(), depth)),
+                }
+            }
+            ":" | "exit" | "set" | "shift" | "return" | "wait" => {}
+            "eval" | "source" | "." | "exec" | "command" | "builtin" | "trap" | "alias" => {
+                verdict.extend(unparsed(policy, wrapper, &format!("`{name}` runs code we cannot see")));
+            }
+            _ => {
+                verdict.extend(command_verdict(policy, exec, wrapper, argv, cwd.clone(), depth));
+            }
+        }
+    }
+    verdict
+}
+
+fn join(cwd: Option<&str>, path: &str) -> String {
+    match cwd {
+        Some(cwd) if !path.starts_with('/') => Path::new(cwd).join(path).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
+
+// the shell follows symlinks when redirecting, so decide on where the target really lands.
+fn real_path(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if let Ok(real) = std::fs::canonicalize(path) {
+        return real;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => match std::fs::canonicalize(parent) {
+            Ok(parent) => parent.join(name),
+            Err(_) => path.to_path_buf(),
+        },
+        _ => path.to_path_buf(),
+    }
+}
+
+// finds the `-c` command string in sh/bash/dash arguments; scripts and stdin cannot be inspected.
+fn shell_script(args: &[String]) -> Result<&str, String> {
+    let mut command_mode = false;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if arg == "--" || arg == "-" {
+            i += 1;
+            break;
+        }
+        if arg.starts_with("--") {
+            if arg == "--rcfile" || arg == "--init-file" {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+        if (arg.starts_with('-') || arg.starts_with('+')) && arg.len() > 1 {
+            let letters = &arg[1..];
+            if arg.starts_with('-') && letters.contains('c') {
+                command_mode = true;
+            }
+            if letters.contains('o') || letters.contains('O') {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+        break;
+    }
+    if !command_mode {
+        return Err("shell without -c runs a script that cannot be inspected".to_string());
+    }
+    args.get(i)
+        .map(String::as_str)
+        .ok_or_else(|| "-c without a command string".to_string())
+}
+
+// strips the wrapper's own options (and env assignments) to find the command it runs, along with
+// the first assigned variable that is not known to be inert.
+fn wrapped_argv(wrapper: Wrapper, args: &[String]) -> Result<(Option<Vec<String>>, Option<String>), String> {
+    let start = match wrapper {
+        Wrapper::Env => skip_options(args, "u", "CS", &["--unset"], &["--chdir", "--split-string"])?,
+        Wrapper::Sudo => skip_options(
+            args,
+            "ugChprtTU",
+            "Deis",
+            &["--user", "--group", "--close-from", "--host", "--prompt", "--role", "--type", "--other-user", "--command-timeout"],
+            &["--chdir", "--edit", "--login", "--shell"],
+        )?,
+        Wrapper::Nohup => skip_options(args, "", "", &[], &[])?,
+        Wrapper::Timeout => {
+            let duration = skip_options(args, "sk", "", &["--signal", "--kill-after"], &[])?;
+            if duration >= args.len() {
+                return Err("timeout without a duration".to_string());
+            }
+            duration + 1
+        }
+        Wrapper::Xargs => skip_options(
+            args,
+            "adEILnPs",
+            "",
+            &["--arg-file", "--delimiter", "--max-lines", "--max-args", "--max-procs", "--max-chars", "--process-slot-var"],
+            &[],
+        )?,
+        Wrapper::Shell => return Err("shells are inspected as scripts".to_string()),
+    };
+
+    let mut rest: Vec<String> = args[start..].to_vec();
+    let mut changed = None;
+    if matches!(wrapper, Wrapper::Env | Wrapper::Sudo) {
+        let assignments = rest.iter().take_while(|arg| is_assignment(arg)).count();
+        changed = rest
+            .drain(..assignments)
+            .map(|assignment| assignment.split('=').next().unwrap_or_default().to_string())
+            .find(|name| is_program_var(name));
+    }
+    if rest.is_empty() {
+        let inner = match wrapper {
+            Wrapper::Xargs => Some(vec!["echo".to_string()]),
+            _ => None,
+        };
+        return Ok((inner, changed));
+    }
+    Ok((Some(rest), changed))
+}
+
+// returns the index of the first operand; `unsupported` options change what runs in ways we do not model.
+fn skip_options(
+    args: &[String],
+    short_with_value: &str,
+    unsupported: &str,
+    long_with_value: &[&str],
+    long_unsupported: &[&str],
+) -> Result<usize, String> {
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        if arg == "--" {
+            return Ok(i + 1);
+        }
+        if let Some(long) = arg.strip_prefix("--") {
+            let name = format!("--{}", long.split('=').next().unwrap_or_default());
+            if long_unsupported.contains(&name.as_str()) {
+                return Err(format!("unsupported option {name}"));
+            }
+            if long_with_value.contains(&name.as_str()) && !long.contains('=') {
+                i += 1;
+            }
+            i += 1;
+            continue;
+        }
+        if arg == "-" {
+            i += 1;
+            continue;
+        }
+        let Some(letters) = arg.strip_prefix('-') else {
+            return Ok(i);
+        };
+        for (pos, letter) in letters.char_indices() {
+            if unsupported.contains(letter) {
+                return Err(format!("unsupported option -{letter}"));
+            }
+            if short_with_value.contains(letter) {
+                if pos + letter.len_utf8() == letters.len() {
+                    i += 1;
+                }
+                break;
+            }
+        }
+        i += 1;
+    }
+    Ok(i)
+}
+
+fn is_program_var(name: &str) -> bool {
+    !(INERT_VARS.contains(&name) || name.starts_with("LC_"))
+}
+
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    }
+}
+
+// splits a script into simple commands; expansions, globs, subshells and control flow are rejected.
+pub(super) fn parse(script: &str) -> Result<Vec<ShellCommand>, String> {
+    let mut commands = Vec::new();
+    let mut current = ShellCommand::default();
+    let mut pending: Option<(bool, bool)> = None;
+
+    for token in tokenize(script)? {
+        match token {
+            Token::Word(word) => match pending.take() {
+                Some((_, true)) if word == "-" || word.chars().all(|c| c.is_ascii_digit()) => {}
+                Some((true, _)) => current.writes.push(word),
+                Some((false, _)) => current.reads.push(word),
+                None => {
+                    if current.argv.is_empty() && is_keyword(&word) {
+                        return Err(format!("shell keyword {word} is not supported"));
+                    }
+                    if current.argv.is_empty() && is_assignment(&word) {
+                        current.assignments.push(word.split('=').next().unwrap_or_default().to_string());
+                    } else {
+                        current.argv.push(word);
+                    }
+                }
+            },
+            Token::Redirect { write, dup } => {
+                if pending.is_some() {
+                    return Err("redirection without a target".to_string());
+                }
+                pending = Some((write, dup));
+            }
+            Token::Separator => {
+                if pending.is_some() {
+                    return Err("redirection without a target".to_string());
+                }
+                if current != ShellCommand::default() {
+                    commands.push(std::mem::take(&mut current));
+                }
+            }
+        }
+    }
+    if pending.is_some() {
+        return Err("redirection without a target".to_string());
+    }
+    if current != ShellCommand::default() {
+        commands.push(current);
+    }
+    Ok(commands)
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "if" | "then" | "else" | "elif" | "fi" | "for" | "while" | "until" | "do" | "done" | "case"
+            | "esac" | "function" | "select" | "time" | "coproc" | "!" | "[[" | "]]"
+    )
+}
+
+fn tokenize(script: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = script.chars().collect();
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    let mut i = 0;
+
+    fn flush(tokens: &mut Vec<Token>, word: &mut String, in_word: &mut bool, quoted: &mut bool) {
+        if *in_word {
+            tokens.push(Token::Word(std::mem::take(word)));
+        }
+        *in_word = false;
+        *quoted = false;
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            ' ' | '\t' => {
+                flush(&mut tokens, &mut word, &mut in_word, &mut quoted);
+                i += 1;
+            }
+            '\n' | ';' => {
+                flush(&mut tokens, &mut word, &mut in_word, &mut quoted);
+                tokens.push(Token::Separator);
+                i += 1;
+            }
+            '&' if next == Some('>') => {
+                flush(&mut tokens, &mut word, &mut in_word, &mut quoted);
+                tokens.push(Token::Redirect { write: true, dup: false });
+                i += if chars.get(i + 2) == Some(&'>') { 3 } else { 2 };
+            }
+            '&' | '|' => {
+                flush(&mut tokens, &mut word, &mut in_word, &mut quoted);
+                tokens.push(Token::Separator);
+                i += if next == Some('&') || next == Some('|') { 2 } else { 1 };
+            }
+            '<' | '>' => {
+                // a bare fd number right before the operator belongs to the redirection.
+                if in_word && !quoted && word.chars().all(|c| c.is_ascii_digit()) {
+                    word.clear();
+                    in_word = false;
+                }
+                flush(&mut tokens, &mut word, &mut in_word, &mut quoted);
+                let (write, dup, width) = match (c, next) {
+                    ('<', Some('<')) => return Err("here-documents are not supported".to_string()),
+                    (_, Some('(')) => return Err("process substitution is not supported".to_string()),
+                    ('<', Some('&')) => (false, true, 2),
+                    ('<', Some('>')) => (true, false, 2),
+                    ('<', _) => (false, false, 1),
+                    (_, Some('>')) | (_, Some('|')) => (true, false, 2),
+                    (_, Some('&')) => (true, true, 2),
+                    _ => (true, false, 1),
+                };
+                tokens.push(Token::Redirect { write, dup });
+                i += width;
+            }
+            '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|c| *c == '\'')
+                    .ok_or_else(|| "unterminated single quote".to_string())?;
+                word.extend(&chars[i + 1..i + 1 + end]);
+                in_word = true;
+                quoted = true;
+                i += end + 2;
+            }
+            '"' => {
+                in_word = true;
+                quoted = true;
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("unterminated double quote".to_string()),
+                        Some('"') => break,
+                        Some('$') | Some('`') => {
+                            return Err("expansions are not supported".to_string())
+                        }
+                        Some('\\') => match chars.get(i + 1) {
+                            Some('\n') => i += 1,
+                            Some(escaped @ ('$' | '`' | '"' | '\\')) => {
+                                word.push(*escaped);
+                                i += 1;
+                            }
+                            _ => word.push('\\'),
+                        },
+                        Some(other) => word.push(*other),
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            '\\' => {
+                match next {
+                    Some('\n') => {}
+                    Some(escaped) => {
+                        word.push(escaped);
+                        in_word = true;
+                        quoted = true;
+                    }
+                    None => return Err("trailing backslash".to_string()),
+                }
+                i += 2;
+            }
+            '#' if !in_word => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '~' if !in_word => return Err("tilde expansion is not supported".to_string()),
+            '$' | '`' => return Err("expansions are not supported".to_string()),
+            '(' | ')' | '{' | '}' => return Err("subshells and brace expansion are not supported".to_string()),
+            '*' | '?' | '[' | ']' => return Err("glob patterns are not supported".to_string()),
+            _ => {
+                word.push(c);
+                in_word = true;
+                i += 1;
+            }
+        }
+    }
+    flush(&mut tokens, &mut word, &mut in_word, &mut quoted);
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Decision;
+    use super::*;
+
+    fn decide(policy: &Policy, argv: &[&str], cwd: Option<&str>) -> Decision {
+        let action = ExecAction {
+            argv: argv.iter().map(|s| s.to_string()).collect(),
+            cwd: cwd.map(str::to_string),
+            env: None,
+            timeout_sec: 5,
+            as_root: false,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        let program = policy.resolve_exec(&action).unwrap();
+        policy.exec_verdict(&action, &program).decision()
+    }
+
+    // sudo is not installed everywhere, so a stand-in lives in an extra search directory.
+    fn shell_policy(unparsed: &str) -> (Policy, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::copy(std::fs::canonicalize("/bin/true").unwrap(), dir.path().join("sudo")).unwrap();
+        let policy = toml::from_str(&format!(
+            r#"
+            [exec]
+            default = "confirm"
+            unparsed = "{unparsed}"
+            search_path = ["/usr/bin", "/bin", "{}"]
+            allow = ["sh", "dash", "bash", "env", "sudo", "nohup", "timeout", "xargs", "echo", "true"]
+            deny = ["dd"]
+
+            [[exec.rules]]
+            id = "rm-recursive-outside-tmp"
+            program = "rm"
+            flags = ["-r"]
+            paths_outside = ["/tmp"]
+            decision = "deny"
+            "#,
+            dir.path().display()
+        ))
+        .unwrap();
+        (policy, dir)
+    }
+
+    #[test]
+    fn parse_splits_commands_and_redirections() {
+        let commands = parse("A=1 echo 'a b' \"c\\\"d\" 2>/dev/null; true && cat < in.txt | wc -l >> out # note").unwrap();
+        assert_eq!(commands.len(), 4);
+        assert_eq!(commands[0].argv, vec!["echo", "a b", "c\"d"]);
+        assert_eq!(commands[0].writes, vec!["/dev/null"]);
+        assert_eq!(commands[1].argv, vec!["true"]);
+        assert_eq!(commands[2].reads, vec!["in.txt"]);
+        assert_eq!(commands[3].argv, vec!["wc", "-l"]);
+        assert_eq!(commands[3].writes, vec!["out"]);
+
+        let dup = parse("echo hi >&2").unwrap();
+        assert!(dup[0].writes.is_empty());
+    }
+
+    #[test]
+    fn parse_rejects_constructs_it_cannot_follow() {
+        for script in [
+            "echo $(dd if=/dev/zero)",
+            "echo `id`",
+            "echo \"$HOME\"",
+            "rm -rf ~",
+            "rm -rf /tmp/.*",
+            "(dd)",
+            "echo {a,b}",
+            "cat <<EOF",
+            "if true; then dd; fi",
+            "echo 'unterminated",
+        ] {
+            assert!(parse(script).is_err(), "{script}");
+        }
+    }
+
+    #[test]
+    fn shell_commands_are_decided_like_top_level_execs() {
+        let (policy, _dir) = shell_policy("confirm");
+        assert_eq!(decide(&policy, &["/bin/sh", "-c", "echo hi; true"], None), Decision::Allow);
+        assert_eq!(
+            decide(&policy, &["/bin/sh", "-c", "dd if=/dev/zero of=/dev/sda"], None),
+            Decision::Deny
+        );
+        assert_eq!(decide(&policy, &["bash", "-ec", "echo ok && rm -rf /"], None), Decision::Deny);
+        assert_eq!(decide(&policy, &["sh", "-c", "cd /etc && rm -r x"], Some("/tmp")), Decision::Deny);
+        assert_eq!(decide(&policy, &["sh", "-c", "sh -c 'sudo dd'"], None), Decision::Deny);
+        assert_eq!(decide(&policy, &["sh", "-c", "echo x > /etc/passwd"], None), Decision::Approve);
+        assert_eq!(decide(&policy, &["sh", "-c", "echo x > /var/x"], None), Decision::Confirm);
+        assert_eq!(decide(&policy, &["sh", "-c", "echo x > /tmp/out"], None), Decision::Allow);
+    }
+
+    #[test]
+    fn wrappers_are_stripped_to_the_command_they_run() {
+        let (policy, _dir) = shell_policy("confirm");
+        assert_eq!(decide(&policy, &["env", "-i", "FOO=1", "dd"], None), Decision::Deny);
+        assert_eq!(decide(&policy, &["env", "LANG=C"], None), Decision::Allow);
+        assert_eq!(decide(&policy, &["env", "FOO=1"], None), Decision::Confirm);
+        assert_eq!(
+            decide(&policy, &["sudo", "-u", "root", "nohup", "timeout", "-s", "KILL", "5", "dd"], None),
+            Decision::Deny
+        );
+        assert_eq!(decide(&policy, &["timeout", "5", "echo", "hi"], None), Decision::Allow);
+        assert_eq!(decide(&policy, &["xargs", "-n", "1", "dd"], None), Decision::Deny);
+        assert_eq!(decide(&policy, &["xargs", "echo"], None), Decision::Confirm);
+    }
+
+    #[test]
+    fn unparseable_input_uses_the_configured_decision() {
+        for (unparsed, expected) in [("confirm", Decision::Confirm), ("deny", Decision::Deny)] {
+            let (policy, _dir) = shell_policy(unparsed);
+            assert_eq!(decide(&policy, &["sh", "-c", "echo $(dd)"], None), expected);
+            assert_eq!(decide(&policy, &["sh", "/tmp/script.sh"], None), expected);
+            assert_eq!(decide(&policy, &["sh", "-c", "eval dd"], None), expected);
+            assert_eq!(decide(&policy, &["env", "-S", "dd if=x"], None), expected);
+            assert_eq!(decide(&policy, &["sudo", "-s"], None), expected);
+            assert_eq!(decide(&policy, &["sh", "-c", "no-such-program-here"], None), expected);
+        }
+    }
+
+    #[test]
+    fn path_changes_reaching_a_wrapper_are_unparsed() {
+        let (policy, _dir) = shell_policy("deny");
+        assert_eq!(decide(&policy, &["sh", "-c", "echo hi"], None), Decision::Allow);
+        assert_eq!(decide(&policy, &["sh", "-c", "PATH=/tmp/x echo hi"], None), Decision::Deny);
+        assert_eq!(decide(&policy, &["sh", "-c", "PATH=/tmp/x; echo hi"], None), Decision::Deny);
+        assert_eq!(decide(&policy, &["env", "PATH=/tmp/x", "echo", "hi"], None), Decision::Deny);
+        assert_eq!(decide(&policy, &["sudo", "LD_PRELOAD=/tmp/x.so", "echo"], None), Decision::Deny);
+        assert_eq!(decide(&policy, &["sh", "-c", "export PATH=/tmp/x; echo hi"], None), Decision::Deny);
+        assert_eq!(decide(&policy, &["sh", "-c", "export LANG=C; echo hi"], None), Decision::Allow);
+        assert_eq!(decide(&policy, &["sh", "-c", "export SHELLOPTS=xtrace; echo hi"], None), Decision::Deny);
+        assert_eq!(decide(&policy, &["sh", "-c", "PS4=x GCONV_PATH=/tmp echo hi"], None), Decision::Deny);
+
+        let mut action = ExecAction {
+            argv: vec!["sh".to_string(), "-c".to_string(), "echo hi".to_string()],
+            cwd: None,
+            env: Some([("PATH".to_string(), "/tmp/x".to_string())].into_iter().collect()),
+            timeout_sec: 5,
+            as_root: false,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        let program = policy.resolve_exec(&action).unwrap();
+        let verdict = policy.exec_verdict(&action, &program);
+        assert_eq!(verdict.decision(), Decision::Deny);
+        assert!(verdict.steps.iter().any(|step| step.rule_id == "exec.unparsed"));
+
+        action.env = Some([("LANG".to_string(), "C".to_string())].into_iter().collect());
+        assert_eq!(policy.exec_verdict(&action, &program).decision(), Decision::Allow);
+    }
+
+    #[test]
+    fn loader_variables_are_unparsed_for_programs_that_are_not_wrappers() {
+        let policy = Policy::default();
+        let mut action = ExecAction {
+            argv: vec!["echo".to_string(), "hi".to_string()],
+            cwd: None,
+            env: None,
+            timeout_sec: 5,
+            as_root: false,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        let program = policy.resolve_exec(&action).unwrap();
+        assert_eq!(policy.exec_verdict(&action, &program).decision(), Decision::Allow);
+
+        for var in ["LD_PRELOAD", "LD_LIBRARY_PATH", "LD_AUDIT", "LD_BIND_NOW", "PATH", "GCONV_PATH", "FOO"] {
+            action.env = Some([(var.to_string(), "/tmp/x".to_string())].into_iter().collect());
+            let verdict = policy.exec_verdict(&action, &program);
+            assert_eq!(verdict.decision(), Decision::Confirm, "{var}");
+            assert_eq!(verdict.decisive().unwrap().rule_id, "exec.unparsed");
+        }
+    }
+
+    #[test]
+    fn environment_that_is_not_known_to_be_inert_is_unparsed() {
+        let (policy, _dir) = shell_policy("deny");
+        let mut action = ExecAction {
+            argv: vec!["bash".to_string(), "-c".to_string(), "echo hi".to_string()],
+            cwd: None,
+            env: None,
+            timeout_sec: 5,
+            as_root: false,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        let program = policy.resolve_exec(&action).unwrap();
+        assert_eq!(policy.exec_verdict(&action, &program).decision(), Decision::Allow);
+
+        for var in ["BASH_FUNC_echo%%", "BASH_FUNC_x%%", "SHELLOPTS", "BASHOPTS", "PS4", "LD_BIND_NOW", "LD_DEBUG"] {
+            action.env = Some([(var.to_string(), "() { id; }".to_string())].into_iter().collect());
+            let verdict = policy.exec_verdict(&action, &program);
+            assert_eq!(verdict.decision(), Decision::Deny, "{var}");
+            assert!(verdict.steps.iter().any(|step| step.rule_id == "exec.unparsed"), "{var}");
+        }
+
+        for var in ["LANG", "LC_ALL", "TZ"] {
+            action.env = Some([(var.to_string(), "C".to_string())].into_iter().collect());
+            assert_eq!(policy.exec_verdict(&action, &program).decision(), Decision::Allow, "{var}");
+        }
+    }
+}
@@ -0,0 +1,465 @@
+// ABOUTME: applies cgroup_apply and cgroup_inspect actions directly through cgroup v2 interface files.
+// ABOUTME: every cgroup written lives under the daemon's delegated subtree; previous values are reported for revert.
+
+use std::io::ErrorKind;
+use std::path::Path;
+
+use llm_os_common::{
+    ActionError, ActionErrorCode, ActionResult, CgroupApplyAction, CgroupApplyResult, CgroupFile, CgroupInspectAction,
+    CgroupInspectResult, CgroupPressure, PressureStall,
+};
+
+pub const DEFAULT_ROOT: &str = "/sys/fs/cgroup";
+// the child of the cgroupfs root the daemon owns; it never writes outside it.
+pub const DELEGATE: &str = "llm-osd";
+
+const DEFAULT_CPU_PERIOD_US: u64 = 100_000;
+const PRESSURE_RESOURCES: [&str; 3] = ["cpu", "memory", "io"];
+
+// one interface file write and the controller it needs enabled in the parent.
+// keyed files (io.max, io.weight) hold one line per device; `key` picks the line this write owns.
+struct Write {
+    controller: &'static str,
+    file: &'static str,
+    value: String,
+    key: Option<String>,
+}
+
+impl Write {
+    fn new(controller: &'static str, file: &'static str, value: String) -> Self {
+        Write {
+            controller,
+            file,
+            value,
+            key: None,
+        }
+    }
+
+    fn keyed(controller: &'static str, file: &'static str, key: &str, value: String) -> Self {
+        Write {
+            key: Some(key.to_string()),
+            ..Write::new(controller, file, value)
+        }
+    }
+}
+
+fn limit(value: Option<u64>) -> String {
+    value.map_or("max".to_string(), |v| v.to_string())
+}
+
+// cpuset goes first so cpu limits are measured against the cpus the group will actually get.
+fn writes(cg: &CgroupApplyAction) -> Vec<Write> {
+    let mut writes = Vec::new();
+    if let Some(cpus) = &cg.cpuset_cpus {
+        writes.push(Write::new("cpuset", "cpuset.cpus", cpus.clone()));
+    }
+    if let Some(weight) = cg.cpu_weight {
+        writes.push(Write::new("cpu", "cpu.weight", weight.to_string()));
+    }
+    if let Some(cpu_max) = &cg.cpu_max {
+        let period = cpu_max.period_us.unwrap_or(DEFAULT_CPU_PERIOD_US);
+        writes.push(Write::new("cpu", "cpu.max", format!("{} {period}", limit(cpu_max.quota_us))));
+    }
+    if let Some(bytes) = cg.mem_high_bytes {
+        writes.push(Write::new("memory", "memory.high", bytes.to_string()));
+    }
+    if let Some(bytes) = cg.mem_max_bytes {
+        writes.push(Write::new("memory", "memory.max", bytes.to_string()));
+    }
+    if let Some(pids) = cg.pids_max {
+        writes.push(Write::new("pids", "pids.max", pids.to_string()));
+    }
+    if let Some(weight) = cg.io_weight {
+        writes.push(Write::keyed("io", "io.weight", "default", format!("default {weight}")));
+    }
+    for io in cg.io_max.iter().flatten() {
+        let value = format!(
+            "{} rbps={} wbps={} riops={} wiops={}",
+            io.device,
+            limit(io.rbps),
+            limit(io.wbps),
+            limit(io.riops),
+            limit(io.wiops)
+        );
+        writes.push(Write::keyed("io", "io.max", &io.device, value));
+    }
+    writes
+}
+
+fn relative(name: &str) -> String {
+    format!("/{DELEGATE}/{name}")
+}
+
+pub fn planned(cg: &CgroupApplyAction) -> ActionResult {
+    let files = writes(cg)
+        .into_iter()
+        .map(|w| CgroupFile {
+            file: w.file.to_string(),
+            requested: w.value,
+            previous: None,
+            effective: None,
+        })
+        .collect();
+    ActionResult::CgroupApply(CgroupApplyResult {
+        ok: true,
+        cgroup: relative(&llm_os_policy::cgroup_name(cg.pid, cg.unit.as_deref())),
+        created: false,
+        pid: cg.pid,
+        previous_cgroup: None,
+        files,
+        error: None,
+    })
+}
+
+// limits are written before the pid moves, so the process never runs unconstrained in the new cgroup.
+// on failure the result still lists every write that happened, with its previous value.
+pub fn apply(root: &Path, cg: &CgroupApplyAction) -> ActionResult {
+    let name = llm_os_policy::cgroup_name(cg.pid, cg.unit.as_deref());
+    let mut result = CgroupApplyResult {
+        ok: false,
+        cgroup: relative(&name),
+        created: false,
+        pid: cg.pid,
+        previous_cgroup: None,
+        files: vec![],
+        error: None,
+    };
+    if let Err(message) = apply_into(root, cg, &name, &mut result) {
+        result.error = Some(ActionError {
+            code: ActionErrorCode::CgroupFailed,
+            message,
+            policy: None,
+        });
+    }
+    result.ok = result.error.is_none();
+    ActionResult::CgroupApply(result)
+}
+
+fn apply_into(root: &Path, cg: &CgroupApplyAction, name: &str, result: &mut CgroupApplyResult) -> Result<(), String> {
+    if !root.is_dir() {
+        return Err(format!("cgroup root {} is not a directory", root.display()));
+    }
+    if let Some(pid) = cg.pid {
+        result.previous_cgroup = Some(current_cgroup(pid)?);
+    }
+
+    let delegate = root.join(DELEGATE);
+    let group = delegate.join(name);
+    let writes = writes(cg);
+    make_dir(&delegate)?;
+    for write in &writes {
+        enable_controller(root, write.controller)?;
+        enable_controller(&delegate, write.controller)?;
+    }
+    result.created = make_dir(&group)?;
+
+    for write in writes {
+        let path = group.join(write.file);
+        let key = write.key.as_deref();
+        let previous = read_line(&path, key);
+        std::fs::write(&path, &write.value).map_err(|err| format!("write {} to {}: {err}", write.value, path.display()))?;
+        result.files.push(CgroupFile {
+            file: write.file.to_string(),
+            requested: write.value,
+            previous,
+            effective: read_line(&path, key),
+        });
+    }
+
+    if let Some(pid) = cg.pid {
+        let procs = group.join("cgroup.procs");
+        std::fs::write(&procs, pid.to_string()).map_err(|err| format!("move pid {pid} into {}: {err}", result.cgroup))?;
+    }
+    Ok(())
+}
+
+// returns whether the directory was created; an existing cgroup is reused as is.
+fn make_dir(path: &Path) -> Result<bool, String> {
+    match std::fs::create_dir(path) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::AlreadyExists && path.is_dir() => Ok(false),
+        Err(err) => Err(format!("create cgroup {}: {err}", path.display())),
+    }
+}
+
+fn enable_controller(dir: &Path, controller: &str) -> Result<(), String> {
+    let path = dir.join("cgroup.subtree_control");
+    let enabled = read_value(&path).unwrap_or_default();
+    if enabled.split_whitespace().any(|c| c == controller) {
+        return Ok(());
+    }
+    std::fs::write(&path, format!("+{controller}"))
+        .map_err(|err| format!("enable the {controller} controller in {}: {err}", dir.display()))
+}
+
+fn read_value(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok().map(|value| value.trim().to_string())
+}
+
+// the whole file, or only the line whose first field is `key`.
+fn read_line(path: &Path, key: Option<&str>) -> Option<String> {
+    let value = read_value(path)?;
+    match key {
+        None => Some(value),
+        Some(key) => value
+            .lines()
+            .find(|line| line.split_whitespace().next() == Some(key))
+            .map(|line| line.to_string()),
+    }
+}
+
+// a pid is looked up where it is now; a unit names a cgroup under the delegated subtree.
+fn locate(cg: &CgroupInspectAction) -> Result<String, String> {
+    match cg.pid {
+        Some(pid) => current_cgroup(pid),
+        None => Ok(relative(&llm_os_policy::cgroup_name(None, cg.unit.as_deref()))),
+    }
+}
+
+fn inspect_result(cgroup: String, error: Option<String>) -> CgroupInspectResult {
+    CgroupInspectResult {
+        ok: error.is_none(),
+        cgroup,
+        memory_current: None,
+        cpu_stat: Default::default(),
+        pressure: vec![],
+        error: error.map(|message| ActionError {
+            code: ActionErrorCode::CgroupFailed,
+            message,
+            policy: None,
+        }),
+    }
+}
+
+pub fn planned_inspect(cg: &CgroupInspectAction) -> ActionResult {
+    ActionResult::CgroupInspect(match locate(cg) {
+        Ok(cgroup) => inspect_result(cgroup, None),
+        Err(message) => inspect_result(String::new(), Some(message)),
+    })
+}
+
+pub fn inspect(root: &Path, cg: &CgroupInspectAction) -> ActionResult {
+    let cgroup = match locate(cg) {
+        Ok(cgroup) => cgroup,
+        Err(message) => return ActionResult::CgroupInspect(inspect_result(String::new(), Some(message))),
+    };
+    let dir = root.join(cgroup.trim_start_matches('/'));
+    if !dir.is_dir() {
+        let message = format!("cgroup {cgroup} does not exist under {}", root.display());
+        return ActionResult::CgroupInspect(inspect_result(cgroup, Some(message)));
+    }
+
+    let mut result = inspect_result(cgroup, None);
+    result.memory_current = read_value(&dir.join("memory.current")).and_then(|v| v.parse().ok());
+    if let Some(stat) = read_value(&dir.join("cpu.stat")) {
+        result.cpu_stat = stat
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(' ')?;
+                Some((key.to_string(), value.trim().parse().ok()?))
+            })
+            .collect();
+    }
+    for resource in PRESSURE_RESOURCES {
+        if let Some(text) = read_value(&dir.join(format!("{resource}.pressure"))) {
+            result.pressure.push(parse_pressure(resource, &text));
+        }
+    }
+    ActionResult::CgroupInspect(result)
+}
+
+// lines look like `some avg10=0.12 avg60=0.05 avg300=0.00 total=123456`.
+pub fn parse_pressure(resource: &str, text: &str) -> CgroupPressure {
+    let mut pressure = CgroupPressure {
+        resource: resource.to_string(),
+        some: None,
+        full: None,
+    };
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next();
+        let mut stall = PressureStall {
+            avg10: 0.0,
+            avg60: 0.0,
+            avg300: 0.0,
+            total_usec: 0,
+        };
+        for field in fields {
+            match field.split_once('=') {
+                Some(("avg10", v)) => stall.avg10 = v.parse().unwrap_or_default(),
+                Some(("avg60", v)) => stall.avg60 = v.parse().unwrap_or_default(),
+                Some(("avg300", v)) => stall.avg300 = v.parse().unwrap_or_default(),
+                Some(("total", v)) => stall.total_usec = v.parse().unwrap_or_default(),
+                _ => {}
+            }
+        }
+        match kind {
+            Some("some") => pressure.some = Some(stall),
+            Some("full") => pressure.full = Some(stall),
+            _ => {}
+        }
+    }
+    pressure
+}
+
+// the unified hierarchy entry is the `0::<path>` line.
+fn current_cgroup(pid: u32) -> Result<String, String> {
+    let text = match std::fs::read_to_string(format!("/proc/{pid}/cgroup")) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Err(format!("pid {pid} is not running")),
+        Err(err) => return Err(format!("read the cgroup of pid {pid}: {err}")),
+    };
+    text.lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(|path| path.to_string())
+        .ok_or_else(|| format!("pid {pid} is not in a cgroup v2 hierarchy"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn action(pid: Option<u32>, unit: Option<&str>) -> CgroupApplyAction {
+        CgroupApplyAction {
+            pid,
+            unit: unit.map(|u| u.to_string()),
+            cpu_weight: Some(200),
+            mem_max_bytes: Some(1048576),
+            cpu_max: None,
+            mem_high_bytes: None,
+            pids_max: None,
+            io_weight: None,
+            io_max: None,
+            cpuset_cpus: None,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        }
+    }
+
+    fn applied(result: ActionResult) -> CgroupApplyResult {
+        match result {
+            ActionResult::CgroupApply(r) => r,
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn applies_limits_then_moves_the_pid_and_reports_previous_values() {
+        let root = tempfile::tempdir().unwrap();
+        let pid = std::process::id();
+        let group = root.path().join(DELEGATE).join(format!("pid-{pid}"));
+        std::fs::create_dir_all(&group).unwrap();
+        std::fs::write(group.join("cpu.weight"), "100\n").unwrap();
+        std::fs::write(group.join("memory.max"), "max\n").unwrap();
+
+        let r = applied(apply(root.path(), &action(Some(pid), None)));
+        assert!(r.ok, "{:?}", r.error);
+        assert_eq!(r.cgroup, format!("/llm-osd/pid-{pid}"));
+        assert!(!r.created);
+        assert!(r.previous_cgroup.is_some());
+        assert_eq!(r.files.len(), 2);
+        assert_eq!(r.files[0].file, "cpu.weight");
+        assert_eq!(r.files[0].previous.as_deref(), Some("100"));
+        assert_eq!(r.files[0].effective.as_deref(), Some("200"));
+        assert_eq!(r.files[1].previous.as_deref(), Some("max"));
+        assert_eq!(r.files[1].effective.as_deref(), Some("1048576"));
+        assert_eq!(std::fs::read_to_string(group.join("cgroup.procs")).unwrap(), pid.to_string());
+        assert_eq!(
+            std::fs::read_to_string(root.path().join(DELEGATE).join("cgroup.subtree_control")).unwrap(),
+            "+memory"
+        );
+    }
+
+    #[test]
+    fn creates_named_cgroups_and_rejects_missing_pids_and_roots() {
+        let root = tempfile::tempdir().unwrap();
+        let r = applied(apply(root.path(), &action(None, Some("batch.scope"))));
+        assert!(r.ok, "{:?}", r.error);
+        assert!(r.created);
+        assert_eq!(r.files[0].previous, None);
+        assert!(root.path().join("llm-osd/batch.scope/cpu.weight").is_file());
+        assert!(!root.path().join("llm-osd/batch.scope/cgroup.procs").exists());
+
+        let r = applied(apply(root.path(), &action(Some(u32::MAX), None)));
+        assert_eq!(r.error.unwrap().message, format!("pid {} is not running", u32::MAX));
+
+        let r = applied(apply(&root.path().join("missing"), &action(None, Some("batch.scope"))));
+        assert_eq!(r.error.unwrap().code, ActionErrorCode::CgroupFailed);
+    }
+
+    #[test]
+    fn writes_extended_limits_and_reports_only_the_written_device_line() {
+        let root = tempfile::tempdir().unwrap();
+        let group = root.path().join("llm-osd/batch");
+        std::fs::create_dir_all(&group).unwrap();
+        std::fs::write(group.join("io.max"), "8:16 rbps=1000 wbps=max riops=max wiops=max\n").unwrap();
+
+        let mut cg = action(None, Some("batch"));
+        cg.cpu_weight = None;
+        cg.mem_max_bytes = None;
+        cg.cpu_max = Some(llm_os_common::CpuMax {
+            quota_us: Some(50_000),
+            period_us: None,
+        });
+        cg.pids_max = Some(64);
+        cg.io_weight = Some(50);
+        cg.io_max = Some(vec![llm_os_common::IoMax {
+            device: "8:16".to_string(),
+            rbps: None,
+            wbps: Some(1048576),
+            riops: None,
+            wiops: None,
+        }]);
+        cg.cpuset_cpus = Some("0-1".to_string());
+
+        let r = applied(apply(root.path(), &cg));
+        assert!(r.ok, "{:?}", r.error);
+        let files: Vec<&str> = r.files.iter().map(|f| f.file.as_str()).collect();
+        assert_eq!(files, vec!["cpuset.cpus", "cpu.max", "pids.max", "io.weight", "io.max"]);
+        assert_eq!(r.files[1].requested, "50000 100000");
+        assert_eq!(r.files[3].requested, "default 50");
+        let io = &r.files[4];
+        assert_eq!(io.previous.as_deref(), Some("8:16 rbps=1000 wbps=max riops=max wiops=max"));
+        assert_eq!(io.effective.as_deref(), Some("8:16 rbps=max wbps=1048576 riops=max wiops=max"));
+    }
+
+    #[test]
+    fn inspects_usage_and_pressure_of_a_delegated_cgroup() {
+        let root = tempfile::tempdir().unwrap();
+        let group = root.path().join("llm-osd/batch");
+        std::fs::create_dir_all(&group).unwrap();
+        std::fs::write(group.join("memory.current"), "4096\n").unwrap();
+        std::fs::write(group.join("cpu.stat"), "usage_usec 1500\nnr_throttled 3\n").unwrap();
+        std::fs::write(
+            group.join("memory.pressure"),
+            "some avg10=1.25 avg60=0.50 avg300=0.00 total=9000\nfull avg10=0.00 avg60=0.00 avg300=0.00 total=10\n",
+        )
+        .unwrap();
+
+        let cg = |unit: &str| CgroupInspectAction {
+            pid: None,
+            unit: Some(unit.to_string()),
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        let r = match inspect(root.path(), &cg("batch")) {
+            ActionResult::CgroupInspect(r) => r,
+            other => panic!("unexpected result {other:?}"),
+        };
+        assert!(r.ok, "{:?}", r.error);
+        assert_eq!(r.cgroup, "/llm-osd/batch");
+        assert_eq!(r.memory_current, Some(4096));
+        assert_eq!(r.cpu_stat.get("nr_throttled"), Some(&3));
+        assert_eq!(r.pressure.len(), 1);
+        let some = r.pressure[0].some.as_ref().unwrap();
+        assert_eq!((r.pressure[0].resource.as_str(), some.avg10, some.total_usec), ("memory", 1.25, 9000));
+        assert_eq!(r.pressure[0].full.as_ref().unwrap().total_usec, 10);
+
+        match inspect(root.path(), &cg("missing")) {
+            ActionResult::CgroupInspect(r) => assert_eq!(r.error.unwrap().code, ActionErrorCode::CgroupFailed),
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+}
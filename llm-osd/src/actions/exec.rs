@@ -2,28 +2,33 @@
 // ABOUTME: returns structured results suitable for deterministic consumption by llmsh.
 
 use llm_os_common::{ActionError, ActionErrorCode, ActionResult, ExecAction, ExecResult};
-use tokio::process::Command;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
 
 const MAX_STDIO_BYTES: usize = 8192;
+// how long a timed-out process group gets to exit after SIGTERM before it is sent SIGKILL.
+const KILL_GRACE: Duration = Duration::from_secs(2);
 
-pub async fn run(exec: &ExecAction) -> ActionResult {
-    let mut cmd = match exec.argv.first() {
-        Some(program) => Command::new(program),
-        None => {
-            return ActionResult::Exec(ExecResult {
-                ok: false,
-                exit_code: None,
-                stdout: "".to_string(),
-                stdout_truncated: false,
-                stderr: "".to_string(),
-                stderr_truncated: false,
-                error: Some(ActionError {
-                    code: ActionErrorCode::ExecFailed,
-                    message: "missing argv[0]".to_string(),
-                }),
-            })
+// runs exactly the resolved executable; argv[0] is passed through so multi-call binaries behave.
+// the child's PATH is the policy's search path, so commands it starts by name resolve the way the
+// policy resolved them.
+pub async fn run(exec: &ExecAction, program: &Path, search_path: &[String]) -> ActionResult {
+    run_capped(exec, program, search_path, MAX_STDIO_BYTES).await
+}
+
+// like `run`, keeping at most `max_bytes` of stdout and of stderr.
+pub async fn run_capped(exec: &ExecAction, program: &Path, search_path: &[String], max_bytes: usize) -> ActionResult {
+    let resolved_path = Some(program.to_string_lossy().to_string());
+    let mut cmd = Command::new(program);
+    match exec.argv.first() {
+        Some(arg0) => {
+            cmd.arg0(arg0);
         }
-    };
+        None => return failed(resolved_path, ActionErrorCode::ExecFailed, "missing argv[0]".to_string(), false, 0),
+    }
 
     if exec.argv.len() > 1 {
         cmd.args(&exec.argv[1..]);
@@ -33,64 +38,235 @@ pub async fn run(exec: &ExecAction) -> ActionResult {
         cmd.current_dir(cwd);
     }
 
+    cmd.env("PATH", search_path.join(":"));
     if let Some(env) = &exec.env {
         cmd.envs(env);
     }
 
-    let output = match tokio::time::timeout(std::time::Duration::from_secs(exec.timeout_sec), cmd.output()).await {
+    // the child leads a new process group, so a timeout can signal everything it started.
+    cmd.process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(err) => return failed(resolved_path, ActionErrorCode::ExecFailed, format!("exec failed: {err}"), false, 0),
+    };
+    let pgid = child.id().map(|pid| pid as i32);
+    let (Some(mut stdout), Some(mut stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return failed(resolved_path, ActionErrorCode::ExecFailed, "exec failed: no stdio pipes".to_string(), false, 0);
+    };
+
+    let timeout = Duration::from_secs(exec.timeout_sec);
+    // one byte past the cap is enough to tell that the output was truncated.
+    let limit = max_bytes.saturating_add(1);
+    let finished = tokio::time::timeout(timeout, async {
+        let (status, out, err) =
+            tokio::join!(child.wait(), read_capped(&mut stdout, limit), read_capped(&mut stderr, limit));
+        status.map(|status| (status, out.unwrap_or_default(), err.unwrap_or_default()))
+    })
+    .await;
+
+    let (status, out, err) = match finished {
         Ok(Ok(output)) => output,
         Ok(Err(err)) => {
-            return ActionResult::Exec(ExecResult {
-                ok: false,
-                exit_code: None,
-                stdout: "".to_string(),
-                stdout_truncated: false,
-                stderr: "".to_string(),
-                stderr_truncated: false,
-                error: Some(ActionError {
-                    code: ActionErrorCode::ExecFailed,
-                    message: format!("exec failed: {err}"),
-                }),
-            })
+            return failed(resolved_path, ActionErrorCode::ExecFailed, format!("exec failed: {err}"), false, 0);
         }
         Err(_) => {
-            return ActionResult::Exec(ExecResult {
-                ok: false,
-                exit_code: None,
-                stdout: "".to_string(),
-                stdout_truncated: false,
-                stderr: "".to_string(),
-                stderr_truncated: false,
-                error: Some(ActionError {
-                    code: ActionErrorCode::ExecTimedOut,
-                    message: "exec timed out".to_string(),
-                }),
-            })
+            let members = match pgid {
+                Some(pgid) => kill_group(&mut child, pgid).await,
+                None => 0,
+            };
+            let message = format!("exec timed out after {}s; killed {members} processes", exec.timeout_sec);
+            return failed(resolved_path, ActionErrorCode::ExecTimedOut, message, true, members);
         }
     };
 
-    let (stdout, stdout_truncated) = truncate_bytes(&output.stdout);
-    let (stderr, stderr_truncated) = truncate_bytes(&output.stderr);
+    let (stdout, stdout_truncated) = truncate_bytes(&out, max_bytes);
+    let (stderr, stderr_truncated) = truncate_bytes(&err, max_bytes);
 
     ActionResult::Exec(ExecResult {
-        ok: output.status.success(),
-        exit_code: output.status.code(),
+        ok: status.success(),
+        resolved_path,
+        exit_code: status.code(),
         stdout,
         stderr,
         stdout_truncated,
         stderr_truncated,
+        killed: false,
+        killed_processes: 0,
         error: None,
     })
 }
 
-fn truncate_bytes(bytes: &[u8]) -> (String, bool) {
-    if bytes.len() <= MAX_STDIO_BYTES {
+fn failed(
+    resolved_path: Option<String>,
+    code: ActionErrorCode,
+    message: String,
+    killed: bool,
+    killed_processes: u32,
+) -> ActionResult {
+    ActionResult::Exec(ExecResult {
+        ok: false,
+        resolved_path,
+        exit_code: None,
+        stdout: "".to_string(),
+        stdout_truncated: false,
+        stderr: "".to_string(),
+        stderr_truncated: false,
+        killed,
+        killed_processes,
+        error: Some(ActionError {
+            code,
+            message,
+            policy: None,
+        }),
+    })
+}
+
+// SIGTERM to the whole group, SIGKILL to whatever is left after the grace period, then the
+// child is reaped. returns how many processes were in the group when it was signalled.
+// a descendant that moved to its own session is out of reach.
+async fn kill_group(child: &mut Child, pgid: i32) -> u32 {
+    let members = group_size(pgid);
+    signal_group(pgid, libc::SIGTERM);
+    let exited = tokio::time::timeout(KILL_GRACE, child.wait()).await.is_ok();
+    if !exited || group_size(pgid) > 0 {
+        signal_group(pgid, libc::SIGKILL);
+    }
+    let _ = child.wait().await;
+    members
+}
+
+fn signal_group(pgid: i32, signal: libc::c_int) {
+    // the group may already be gone; ESRCH is expected then.
+    unsafe {
+        libc::killpg(pgid, signal);
+    }
+}
+
+// live members of a process group, from the pgrp field of /proc/<pid>/stat.
+fn group_size(pgid: i32) -> u32 {
+    let entries = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().bytes().all(|b| b.is_ascii_digit()))
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("stat")).ok())
+        .filter(|stat| {
+            // the fields after the parenthesised command name are state, ppid, pgrp.
+            let mut fields = stat.rsplit_once(')').map(|(_, rest)| rest.split_whitespace()).into_iter().flatten();
+            let state = fields.next();
+            state != Some("Z") && fields.nth(1).and_then(|pgrp| pgrp.parse().ok()) == Some(pgid)
+        })
+        .count() as u32
+}
+
+// keeps the first `limit` bytes and discards the rest, so a chatty child never blocks on a full pipe.
+async fn read_capped<R: AsyncRead + Unpin>(reader: &mut R, limit: usize) -> std::io::Result<Vec<u8>> {
+    let mut kept = Vec::new();
+    (&mut *reader).take(limit as u64).read_to_end(&mut kept).await?;
+    tokio::io::copy(reader, &mut tokio::io::sink()).await?;
+    Ok(kept)
+}
+
+fn truncate_bytes(bytes: &[u8], max_bytes: usize) -> (String, bool) {
+    if bytes.len() <= max_bytes {
         return (String::from_utf8_lossy(bytes).to_string(), false);
     }
 
-    let mut out = String::from_utf8_lossy(&bytes[..MAX_STDIO_BYTES]).to_string();
+    let mut out = String::from_utf8_lossy(&bytes[..max_bytes]).to_string();
     out.push_str("\n[truncated]\n");
     (out, true)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn timeout_kills_the_whole_process_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("survived");
+        // the background sleep would outlive its shell if only the shell were killed.
+        let script = format!("(sleep 3; touch {}) & sleep 30", marker.display());
+        let exec = ExecAction {
+            argv: vec!["sh".to_string(), "-c".to_string(), script],
+            cwd: None,
+            env: None,
+            timeout_sec: 1,
+            as_root: false,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
 
+        let r = match run(&exec, Path::new("/bin/sh"), &["/usr/bin".to_string(), "/bin".to_string()]).await {
+            ActionResult::Exec(r) => r,
+            other => panic!("unexpected result {other:?}"),
+        };
+        assert!(!r.ok);
+        assert_eq!(r.error.unwrap().code, ActionErrorCode::ExecTimedOut);
+        assert!(r.killed);
+        assert!(r.killed_processes >= 3, "{}", r.killed_processes);
+
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn commands_started_by_name_resolve_against_the_search_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // a directory the daemon's own PATH does not list, holding a program only it has.
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("llm-osd-only-here");
+        std::fs::write(&program, "#!/bin/sh\necho from-search-path\n").unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let search_path = vec![dir.path().to_string_lossy().to_string(), "/usr/bin".to_string(), "/bin".to_string()];
+        assert!(!std::env::var("PATH").unwrap_or_default().split(':').any(|d| d == search_path[0]));
+
+        let exec = ExecAction {
+            argv: vec!["sh".to_string(), "-c".to_string(), "llm-osd-only-here; echo \"$PATH\"".to_string()],
+            cwd: None,
+            env: None,
+            timeout_sec: 5,
+            as_root: false,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        let r = match run(&exec, Path::new("/bin/sh"), &search_path).await {
+            ActionResult::Exec(r) => r,
+            other => panic!("unexpected result {other:?}"),
+        };
+        assert!(r.ok, "{r:?}");
+        assert_eq!(r.stdout, format!("from-search-path\n{}\n", search_path.join(":")));
+    }
+
+    #[tokio::test]
+    async fn output_past_the_cap_is_drained_not_kept() {
+        let exec = ExecAction {
+            argv: vec!["sh".to_string(), "-c".to_string(), "head -c 1000000 /dev/zero | tr '\\0' x".to_string()],
+            cwd: None,
+            env: None,
+            timeout_sec: 10,
+            as_root: false,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+
+        let r = match run_capped(&exec, Path::new("/bin/sh"), &["/usr/bin".to_string(), "/bin".to_string()], 16).await {
+            ActionResult::Exec(r) => r,
+            other => panic!("unexpected result {other:?}"),
+        };
+        assert!(r.ok, "{r:?}");
+        assert!(r.stdout_truncated);
+        assert_eq!(r.stdout, format!("{}\n[truncated]\n", "x".repeat(16)));
+        assert!(!r.killed);
+    }
+}
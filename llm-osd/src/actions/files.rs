@@ -6,19 +6,134 @@ use llm_os_common::{
     ActionError, ActionErrorCode, ActionResult, ReadFileAction, ReadFileResult, WriteFileAction,
     WriteFileResult,
 };
+use std::ffi::CString;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
 
-pub async fn read(read: &ReadFileAction) -> ActionResult {
+// a path resolved to its real location: an open handle on the real parent directory plus the final name.
+// the final component is always opened with O_NOFOLLOW relative to that handle, so swapping in a
+// symlink after policy evaluation cannot redirect the read or write.
+#[derive(Debug)]
+pub struct Target {
+    pub path: PathBuf,
+    dir: OwnedFd,
+    name: CString,
+}
+
+// resolves the path exactly as policy does, then pins the real parent directory with an open handle.
+pub fn resolve(path: &str) -> Result<Target, String> {
+    let resolved = llm_os_policy::target_path(path)?;
+    let (parent, name) = match (resolved.parent(), resolved.file_name()) {
+        (Some(parent), Some(name)) => (parent, name),
+        _ => return Err(format!("{path} does not name a file")),
+    };
+    let dir = open_dir_without_symlinks(parent)
+        .map_err(|err| format!("open {}: {err}", parent.display()))?;
+    let name = CString::new(name.as_bytes()).map_err(|_| format!("{path} contains a nul byte"))?;
+
+    Ok(Target {
+        path: resolved,
+        dir,
+        name,
+    })
+}
+
+impl Target {
+    fn open(&self, flags: libc::c_int, mode: libc::mode_t) -> std::io::Result<std::fs::File> {
+        let fd = unsafe {
+            libc::openat(
+                self.dir.as_raw_fd(),
+                self.name.as_ptr(),
+                flags | libc::O_NOFOLLOW | libc::O_CLOEXEC,
+                mode as libc::c_uint,
+            )
+        };
+        if fd < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::ELOOP) {
+                return Err(std::io::Error::other(format!(
+                    "{} is a symlink; refusing to follow it",
+                    self.path.display()
+                )));
+            }
+            return Err(err);
+        }
+        Ok(unsafe { std::fs::File::from_raw_fd(fd) })
+    }
+
+    fn display(&self) -> Option<String> {
+        Some(self.path.to_string_lossy().to_string())
+    }
+}
+
+// the directory was just canonicalized, so any symlink met while opening it means it changed underneath us.
+fn open_dir_without_symlinks(dir: &Path) -> std::io::Result<OwnedFd> {
+    let path = CString::new(dir.as_os_str().as_bytes())
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+    let mut how: libc::open_how = unsafe { std::mem::zeroed() };
+    how.flags = (libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC) as u64;
+    how.resolve = libc::RESOLVE_NO_SYMLINKS | libc::RESOLVE_NO_MAGICLINKS;
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_openat2,
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            &how as *const libc::open_how,
+            std::mem::size_of::<libc::open_how>(),
+        )
+    };
+    if fd >= 0 {
+        return Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) });
+    }
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ENOSYS) | Some(libc::EPERM) => open_dir_by_components(dir),
+        _ => Err(err),
+    }
+}
+
+// fallback for kernels without openat2: walk the path one directory at a time with O_NOFOLLOW.
+fn open_dir_by_components(dir: &Path) -> std::io::Result<OwnedFd> {
+    let flags = libc::O_PATH | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC;
+    let root = CString::new("/").expect("static path");
+    let fd = unsafe { libc::open(root.as_ptr(), flags) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut current = unsafe { OwnedFd::from_raw_fd(fd) };
+    for component in dir.components() {
+        let Component::Normal(name) = component else {
+            continue;
+        };
+        let name = CString::new(name.as_bytes())
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        let fd = unsafe { libc::openat(current.as_raw_fd(), name.as_ptr(), flags) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        current = unsafe { OwnedFd::from_raw_fd(fd) };
+    }
+    Ok(current)
+}
+
+#[allow(clippy::reserve_after_initialization)]
+pub async fn read(read: &ReadFileAction, target: Target) -> ActionResult {
     let max = read.max_bytes as usize;
-    let mut file = match tokio::fs::File::open(&read.path).await {
-        Ok(f) => f,
+    let resolved_path = target.display();
+    let mut file = match target.open(libc::O_RDONLY, 0) {
+        Ok(f) => tokio::fs::File::from_std(f),
         Err(err) => {
             return ActionResult::ReadFile(ReadFileResult {
                 ok: false,
+                resolved_path,
                 content_base64: None,
                 truncated: false,
                 error: Some(ActionError {
                     code: ActionErrorCode::ReadFailed,
                     message: format!("read failed: {err}"),
+                    policy: None,
                 }),
             })
         }
@@ -36,11 +151,13 @@ pub async fn read(read: &ReadFileAction) -> ActionResult {
             Err(err) => {
                 return ActionResult::ReadFile(ReadFileResult {
                     ok: false,
+                    resolved_path,
                     content_base64: None,
                     truncated: false,
                     error: Some(ActionError {
                         code: ActionErrorCode::ReadFailed,
                         message: format!("read failed: {err}"),
+                        policy: None,
                     }),
                 })
             }
@@ -56,6 +173,7 @@ pub async fn read(read: &ReadFileAction) -> ActionResult {
     let content_base64 = base64::engine::general_purpose::STANDARD.encode(slice);
     ActionResult::ReadFile(ReadFileResult {
         ok: true,
+        resolved_path,
         content_base64: Some(content_base64),
         truncated,
         error: None,
@@ -63,6 +181,7 @@ pub async fn read(read: &ReadFileAction) -> ActionResult {
 }
 
 #[cfg(test)]
+#[allow(clippy::items_after_test_module)]
 mod tests {
     use super::*;
 
@@ -96,7 +215,8 @@ mod tests {
             recovery: None,
         };
 
-        let result = read(&action).await;
+        let target = resolve(&action.path).unwrap();
+        let result = read(&action, target).await;
         let after_hwm = proc_kb_field("VmHWM:");
 
         match result {
@@ -111,52 +231,126 @@ mod tests {
         let delta = after_hwm.saturating_sub(before_hwm);
         assert!(delta < 100 * 1024, "vmhwm increased too much: {delta} kb");
     }
+
+    #[tokio::test]
+    async fn write_refuses_a_symlinked_final_component() {
+        let dir = tempfile::tempdir().unwrap();
+        let victim = dir.path().join("victim.txt");
+        std::fs::write(&victim, "keep").unwrap();
+        let link = dir.path().join("link.txt");
+        std::os::unix::fs::symlink(&victim, &link).unwrap();
+
+        let action = WriteFileAction {
+            path: link.to_string_lossy().to_string(),
+            content: "clobbered".to_string(),
+            mode: "0644".to_string(),
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        let target = resolve(&action.path).unwrap();
+        match write(&action, target).await {
+            ActionResult::WriteFile(w) => {
+                assert!(!w.ok);
+                assert!(w.error.unwrap().message.contains("symlink"));
+            }
+            _ => panic!("unexpected result type"),
+        }
+        assert_eq!(std::fs::read_to_string(&victim).unwrap(), "keep");
+    }
+
+    #[test]
+    fn resolve_reports_the_real_parent_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = std::fs::canonicalize(dir.path()).unwrap().join("real");
+        std::fs::create_dir(&real).unwrap();
+        std::os::unix::fs::symlink(&real, dir.path().join("alias")).unwrap();
+
+        let target = resolve(&dir.path().join("alias/../alias/f.txt").to_string_lossy()).unwrap();
+        assert_eq!(target.path, real.join("f.txt"));
+        assert!(resolve("/").is_err());
+    }
+
+    #[test]
+    fn component_walk_rejects_symlinked_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = std::fs::canonicalize(dir.path()).unwrap();
+        std::fs::create_dir(real.join("sub")).unwrap();
+        std::os::unix::fs::symlink(real.join("sub"), real.join("alias")).unwrap();
+
+        assert!(open_dir_by_components(&real.join("sub")).is_ok());
+        assert!(open_dir_by_components(&real.join("alias")).is_err());
+        assert!(open_dir_without_symlinks(&real.join("alias")).is_err());
+    }
 }
 
-pub async fn write(write: &WriteFileAction) -> ActionResult {
+pub async fn write(write: &WriteFileAction, target: Target) -> ActionResult {
+    let resolved_path = target.display();
     let mode = match parse_mode(&write.mode) {
         Ok(m) => m,
         Err(err) => {
             return ActionResult::WriteFile(WriteFileResult {
                 ok: false,
+                resolved_path,
                 artifacts: vec![],
                 error: Some(ActionError {
                     code: ActionErrorCode::InvalidModeString,
                     message: err,
+                    policy: None,
                 }),
             })
         }
     };
 
-    if let Err(err) = tokio::fs::write(&write.path, write.content.as_bytes()).await {
+    let file = match target.open(libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC, mode as libc::mode_t) {
+        Ok(f) => f,
+        Err(err) => {
+            return ActionResult::WriteFile(WriteFileResult {
+                ok: false,
+                resolved_path,
+                artifacts: vec![],
+                error: Some(ActionError {
+                    code: ActionErrorCode::WriteFailed,
+                    message: format!("write failed: {err}"),
+                    policy: None,
+                }),
+            })
+        }
+    };
+
+    let mut file = tokio::fs::File::from_std(file);
+    use tokio::io::AsyncWriteExt;
+    if let Err(err) = file.write_all(write.content.as_bytes()).await {
         return ActionResult::WriteFile(WriteFileResult {
             ok: false,
+            resolved_path,
             artifacts: vec![],
             error: Some(ActionError {
                 code: ActionErrorCode::WriteFailed,
                 message: format!("write failed: {err}"),
+                policy: None,
             }),
         });
     }
 
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let perms = std::fs::Permissions::from_mode(mode);
-        if let Err(err) = tokio::fs::set_permissions(&write.path, perms).await {
-            return ActionResult::WriteFile(WriteFileResult {
-                ok: false,
-                artifacts: vec![],
-                error: Some(ActionError {
-                    code: ActionErrorCode::WriteFailed,
-                    message: format!("chmod failed: {err}"),
-                }),
-            });
-        }
+    // the create mode is filtered by umask, so set the requested mode on the open handle.
+    let perms = std::fs::Permissions::from_mode(mode);
+    if let Err(err) = file.set_permissions(perms).await {
+        return ActionResult::WriteFile(WriteFileResult {
+            ok: false,
+            resolved_path,
+            artifacts: vec![],
+            error: Some(ActionError {
+                code: ActionErrorCode::WriteFailed,
+                message: format!("chmod failed: {err}"),
+                policy: None,
+            }),
+        });
     }
 
     ActionResult::WriteFile(WriteFileResult {
         ok: true,
+        resolved_path,
         artifacts: vec![write.path.clone()],
         error: None,
     })
@@ -167,5 +361,3 @@ fn parse_mode(mode: &str) -> Result<u32, String> {
     let mode = mode.strip_prefix("0o").unwrap_or(mode);
     u32::from_str_radix(mode, 8).map_err(|_| "mode must be an octal string like 0644".to_string())
 }
-
-
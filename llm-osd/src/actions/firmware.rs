@@ -0,0 +1,184 @@
+// ABOUTME: implements firmware_op inventory and uefi_var_read natively from sysfs and efivarfs.
+// ABOUTME: the sysfs root is configurable so both can run against fixture trees.
+
+use std::io::Read;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+use base64::Engine;
+use llm_os_common::{ActionError, ActionErrorCode, ActionResult, FirmwareInventory, FirmwareOpResult, UefiVariable};
+
+pub const DEFAULT_SYSFS_ROOT: &str = "/sys";
+const DMI_DIR: &str = "class/dmi/id";
+const EFIVARS_DIR: &str = "firmware/efi/efivars";
+// efivarfs entries are bounded by nvram size; anything larger is not a variable worth returning.
+const MAX_UEFI_VAR_BYTES: u64 = 64 * 1024;
+const MAX_DMI_VALUE_BYTES: u64 = 256;
+
+// EFI_VARIABLE_* attribute bits from the uefi specification.
+const ATTRIBUTES: [(u32, &str); 7] = [
+    (0x01, "non_volatile"),
+    (0x02, "bootservice_access"),
+    (0x04, "runtime_access"),
+    (0x08, "hardware_error_record"),
+    (0x10, "authenticated_write_access"),
+    (0x20, "time_based_authenticated_write_access"),
+    (0x40, "append_write"),
+];
+
+fn result(inventory: Option<Box<FirmwareInventory>>, uefi_var: Option<UefiVariable>, error: Option<String>) -> ActionResult {
+    ActionResult::FirmwareOp(FirmwareOpResult {
+        ok: error.is_none(),
+        argv: vec![],
+        inventory,
+        uefi_var,
+        error: error.map(|message| ActionError {
+            code: ActionErrorCode::FirmwareFailed,
+            message,
+            policy: None,
+        }),
+    })
+}
+
+// root-only files such as product_serial are never opened.
+pub fn inventory(sysfs_root: &Path) -> ActionResult {
+    let dir = sysfs_root.join(DMI_DIR);
+    if !dir.is_dir() {
+        return result(None, None, Some(format!("no dmi information under {}", dir.display())));
+    }
+    let field = |name: &str| dmi_value(&dir.join(name));
+    let inventory = FirmwareInventory {
+        vendor: field("sys_vendor"),
+        product: field("product_name"),
+        product_version: field("product_version"),
+        bios_vendor: field("bios_vendor"),
+        bios_version: field("bios_version"),
+        bios_date: field("bios_date"),
+        board_vendor: field("board_vendor"),
+        board_name: field("board_name"),
+        board_version: field("board_version"),
+    };
+    result(Some(Box::new(inventory)), None, None)
+}
+
+// firmware often leaves unused fields blank; those are reported as null.
+fn dmi_value(path: &Path) -> Option<String> {
+    let mut value = String::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(MAX_DMI_VALUE_BYTES)
+        .read_to_string(&mut value)
+        .ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+pub fn uefi_var_read(sysfs_root: &Path, name: &str) -> ActionResult {
+    match read_variable(&sysfs_root.join(EFIVARS_DIR), name) {
+        Ok(var) => result(None, Some(var), None),
+        Err(message) => result(None, None, Some(message)),
+    }
+}
+
+fn read_variable(dir: &Path, name: &str) -> Result<UefiVariable, String> {
+    if !dir.is_dir() {
+        return Err(format!("efivarfs is not mounted at {}", dir.display()));
+    }
+    let path = dir.join(name);
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(&path)
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => format!("uefi variable {name} does not exist"),
+            _ => format!("open {}: {err}", path.display()),
+        })?;
+    let mut bytes = Vec::new();
+    file.take(MAX_UEFI_VAR_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|err| format!("read {}: {err}", path.display()))?;
+    if bytes.len() as u64 > MAX_UEFI_VAR_BYTES {
+        return Err(format!("uefi variable {name} is larger than {MAX_UEFI_VAR_BYTES} bytes"));
+    }
+    parse_variable(name, &bytes)
+}
+
+pub fn parse_variable(name: &str, bytes: &[u8]) -> Result<UefiVariable, String> {
+    let (header, data) = match bytes.split_first_chunk::<4>() {
+        Some(split) => split,
+        None => return Err(format!("uefi variable {name} is shorter than its 4-byte attribute header")),
+    };
+    let attributes = u32::from_le_bytes(*header);
+    Ok(UefiVariable {
+        name: name.to_string(),
+        attributes,
+        attribute_names: ATTRIBUTES
+            .iter()
+            .filter(|(bit, _)| attributes & bit != 0)
+            .map(|(_, name)| name.to_string())
+            .collect(),
+        data_base64: base64::engine::general_purpose::STANDARD.encode(data),
+        size: data.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn firmware(result: ActionResult) -> FirmwareOpResult {
+        match result {
+            ActionResult::FirmwareOp(r) => r,
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn inventory_reads_dmi_fields_and_skips_blanks() {
+        let root = tempfile::tempdir().unwrap();
+        let dmi = root.path().join(DMI_DIR);
+        std::fs::create_dir_all(&dmi).unwrap();
+        std::fs::write(dmi.join("sys_vendor"), "LENOVO\n").unwrap();
+        std::fs::write(dmi.join("product_name"), "20XW0055US\n").unwrap();
+        std::fs::write(dmi.join("bios_version"), "N32ET86W (1.62 )\n").unwrap();
+        std::fs::write(dmi.join("board_name"), "20XW0055US\n").unwrap();
+        std::fs::write(dmi.join("board_version"), " \n").unwrap();
+
+        let r = firmware(inventory(root.path()));
+        assert!(r.ok, "{:?}", r.error);
+        let dmi = r.inventory.unwrap();
+        assert_eq!(dmi.vendor.as_deref(), Some("LENOVO"));
+        assert_eq!(dmi.bios_version.as_deref(), Some("N32ET86W (1.62 )"));
+        assert_eq!(dmi.board_name.as_deref(), Some("20XW0055US"));
+        assert_eq!(dmi.board_version, None);
+        assert_eq!(dmi.bios_date, None);
+
+        let r = firmware(inventory(&root.path().join("missing")));
+        assert_eq!(r.error.unwrap().code, ActionErrorCode::FirmwareFailed);
+    }
+
+    #[test]
+    fn uefi_var_read_splits_the_attribute_header_from_the_data() {
+        let root = tempfile::tempdir().unwrap();
+        let efivars = root.path().join(EFIVARS_DIR);
+        std::fs::create_dir_all(&efivars).unwrap();
+        let name = "BootOrder-8be4df61-93ca-11d2-aa0d-00e098032b8c";
+        std::fs::write(efivars.join(name), [0x07, 0, 0, 0, 0x01, 0x00, 0x00, 0x00]).unwrap();
+        std::fs::write(efivars.join("Short-0000"), [0x07, 0]).unwrap();
+        std::os::unix::fs::symlink(efivars.join(name), efivars.join("Link-0000")).unwrap();
+
+        let r = firmware(uefi_var_read(root.path(), name));
+        assert!(r.ok, "{:?}", r.error);
+        let var = r.uefi_var.unwrap();
+        assert_eq!(var.attributes, 7);
+        assert_eq!(var.attribute_names, vec!["non_volatile", "bootservice_access", "runtime_access"]);
+        assert_eq!(var.data_base64, "AQAAAA==");
+        assert_eq!(var.size, 4);
+
+        let r = firmware(uefi_var_read(root.path(), "Short-0000"));
+        assert!(r.error.unwrap().message.contains("4-byte attribute header"));
+        assert!(!firmware(uefi_var_read(root.path(), "Link-0000")).ok);
+        let r = firmware(uefi_var_read(root.path(), "Missing-0000"));
+        assert_eq!(r.error.unwrap().message, "uefi variable Missing-0000 does not exist");
+    }
+}
@@ -0,0 +1,248 @@
+// ABOUTME: runs log_query actions as `journalctl -o json` with a command line built from typed filters.
+// ABOUTME: each json line is parsed into a structured entry with a bounded message.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use llm_os_common::{ActionError, ActionErrorCode, ActionResult, LogEntry, LogPriority, LogQueryAction, LogQueryResult};
+use tokio::process::Command;
+
+const JOURNALCTL_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_ENTRIES: u32 = 100;
+const MAX_MESSAGE_BYTES: usize = 4096;
+const MAX_STDERR_BYTES: usize = 2048;
+// the fields entries are built from; journalctl always adds the timestamps and cursor.
+const OUTPUT_FIELDS: &str = "MESSAGE,PRIORITY,_PID,_SYSTEMD_UNIT";
+
+pub fn default_program() -> PathBuf {
+    ["/usr/bin/journalctl", "/bin/journalctl"]
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from("/usr/bin/journalctl"))
+}
+
+// every value is passed in `--flag=value` form, so nothing the model sends can be read as another option.
+pub fn argv(q: &LogQueryAction) -> Vec<String> {
+    let mut argv = vec![
+        "journalctl".to_string(),
+        "--no-pager".to_string(),
+        "--output=json".to_string(),
+        format!("--output-fields={OUTPUT_FIELDS}"),
+        format!("--lines={}", q.max_entries.unwrap_or(DEFAULT_MAX_ENTRIES)),
+    ];
+    if let Some(unit) = &q.unit {
+        argv.push(format!("--unit={unit}"));
+    }
+    if let Some(priority) = &q.priority {
+        argv.push(format!("--priority={}..{}", priority_name(priority.from), priority_name(priority.to)));
+    }
+    if let Some(since) = &q.since {
+        argv.push(format!("--since={since}"));
+    }
+    if let Some(until) = &q.until {
+        argv.push(format!("--until={until}"));
+    }
+    if let Some(grep) = &q.grep {
+        argv.push(format!("--grep={grep}"));
+    }
+    argv
+}
+
+fn priority_name(priority: LogPriority) -> &'static str {
+    match priority {
+        LogPriority::Emerg => "emerg",
+        LogPriority::Alert => "alert",
+        LogPriority::Crit => "crit",
+        LogPriority::Err => "err",
+        LogPriority::Warning => "warning",
+        LogPriority::Notice => "notice",
+        LogPriority::Info => "info",
+        LogPriority::Debug => "debug",
+    }
+}
+
+fn priority_from_level(level: &str) -> Option<LogPriority> {
+    let priority = match level {
+        "0" => LogPriority::Emerg,
+        "1" => LogPriority::Alert,
+        "2" => LogPriority::Crit,
+        "3" => LogPriority::Err,
+        "4" => LogPriority::Warning,
+        "5" => LogPriority::Notice,
+        "6" => LogPriority::Info,
+        "7" => LogPriority::Debug,
+        _ => return None,
+    };
+    Some(priority)
+}
+
+pub fn planned(q: &LogQueryAction) -> ActionResult {
+    result(argv(q), vec![], None)
+}
+
+pub async fn run(program: &Path, q: &LogQueryAction) -> ActionResult {
+    let argv = argv(q);
+    match query(program, &argv).await {
+        Ok(entries) => result(argv, entries, None),
+        Err(message) => result(argv, vec![], Some(message)),
+    }
+}
+
+fn result(argv: Vec<String>, entries: Vec<LogEntry>, error: Option<String>) -> ActionResult {
+    ActionResult::LogQuery(LogQueryResult {
+        ok: error.is_none(),
+        argv,
+        entries,
+        error: error.map(|message| ActionError {
+            code: ActionErrorCode::LogQueryFailed,
+            message,
+            policy: None,
+        }),
+    })
+}
+
+async fn query(program: &Path, argv: &[String]) -> Result<Vec<LogEntry>, String> {
+    let mut cmd = Command::new(program);
+    cmd.args(&argv[1..]).kill_on_drop(true);
+    let output = match tokio::time::timeout(JOURNALCTL_TIMEOUT, cmd.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(err)) => return Err(format!("journalctl could not be started: {err}")),
+        Err(_) => return Err(format!("journalctl timed out after {}s", JOURNALCTL_TIMEOUT.as_secs())),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr[..output.stderr.len().min(MAX_STDERR_BYTES)]);
+        let code = output.status.code().map_or("a signal".to_string(), |code| format!("status {code}"));
+        return Err(format!("journalctl exited with {code}: {}", stderr.trim()));
+    }
+    parse_entries(&String::from_utf8_lossy(&output.stdout))
+}
+
+// one json object per line; journalctl's `-- No entries --` style notices are skipped.
+pub fn parse_entries(output: &str) -> Result<Vec<LogEntry>, String> {
+    output
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .enumerate()
+        .map(|(i, line)| {
+            let fields: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(line).map_err(|err| format!("journal entry {i} is not a json object: {err}"))?;
+            parse_entry(&fields).ok_or_else(|| format!("journal entry {i} has no __REALTIME_TIMESTAMP"))
+        })
+        .collect()
+}
+
+fn parse_entry(fields: &serde_json::Map<String, serde_json::Value>) -> Option<LogEntry> {
+    let field = |name: &str| fields.get(name).and_then(field_text);
+    let timestamp_us = field("__REALTIME_TIMESTAMP")?.parse().ok()?;
+    // journalctl prints null for values over its size limit rather than the value itself.
+    let (message, message_truncated) = match field("MESSAGE") {
+        Some(message) => truncate(message),
+        None => (String::new(), fields.get("MESSAGE").is_some_and(|v| v.is_null())),
+    };
+    Some(LogEntry {
+        timestamp_us,
+        unit: field("_SYSTEMD_UNIT"),
+        priority: field("PRIORITY").as_deref().and_then(priority_from_level),
+        pid: field("_PID").and_then(|pid| pid.parse().ok()),
+        message,
+        message_truncated,
+    })
+}
+
+// values are strings, byte arrays for non-utf-8 data, or arrays of either when a field repeats.
+fn field_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Array(items) if items.iter().all(|item| item.is_u64()) => {
+            let bytes: Vec<u8> = items.iter().filter_map(|item| item.as_u64()).map(|b| b as u8).collect();
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        serde_json::Value::Array(items) => items.first().and_then(field_text),
+        _ => None,
+    }
+}
+
+fn truncate(mut message: String) -> (String, bool) {
+    if message.len() <= MAX_MESSAGE_BYTES {
+        return (message, false);
+    }
+    let mut end = MAX_MESSAGE_BYTES;
+    while !message.is_char_boundary(end) {
+        end -= 1;
+    }
+    message.truncate(end);
+    (message, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llm_os_common::LogPriorityRange;
+
+    const FIXTURE: &str = r#"{"__CURSOR":"s=1;i=2","__REALTIME_TIMESTAMP":"1760601600123456","__MONOTONIC_TIMESTAMP":"99","_BOOT_ID":"b","MESSAGE":"Accepted publickey for deploy","PRIORITY":"6","_PID":"812","_SYSTEMD_UNIT":"ssh.service"}
+{"__CURSOR":"s=1;i=3","__REALTIME_TIMESTAMP":"1760601601000000","__MONOTONIC_TIMESTAMP":"100","_BOOT_ID":"b","MESSAGE":[104,105,255],"PRIORITY":"3"}
+{"__CURSOR":"s=1;i=4","__REALTIME_TIMESTAMP":"1760601602000000","__MONOTONIC_TIMESTAMP":"101","_BOOT_ID":"b","MESSAGE":null,"PRIORITY":"4","_SYSTEMD_UNIT":["a.service","b.service"]}
+"#;
+
+    #[test]
+    fn parses_journal_json_into_entries() {
+        let entries = parse_entries(FIXTURE).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[0],
+            LogEntry {
+                timestamp_us: 1760601600123456,
+                unit: Some("ssh.service".to_string()),
+                priority: Some(LogPriority::Info),
+                pid: Some(812),
+                message: "Accepted publickey for deploy".to_string(),
+                message_truncated: false,
+            }
+        );
+        assert_eq!(entries[1].message, "hi\u{fffd}");
+        assert_eq!(entries[1].priority, Some(LogPriority::Err));
+        assert_eq!((entries[1].unit.as_deref(), entries[1].pid), (None, None));
+        assert_eq!(entries[2].unit.as_deref(), Some("a.service"));
+        assert!(entries[2].message.is_empty() && entries[2].message_truncated);
+
+        assert!(parse_entries("-- No entries --\n").unwrap().is_empty());
+        assert!(parse_entries("{\"MESSAGE\":\"x\"}\n").is_err());
+
+        let long = format!(r#"{{"__REALTIME_TIMESTAMP":"1","MESSAGE":"{}"}}"#, "é".repeat(MAX_MESSAGE_BYTES));
+        let entry = &parse_entries(&long).unwrap()[0];
+        assert!(entry.message_truncated && entry.message.len() <= MAX_MESSAGE_BYTES);
+    }
+
+    #[test]
+    fn argv_passes_every_filter_as_a_single_option() {
+        let q = LogQueryAction {
+            unit: Some("ssh.service".to_string()),
+            priority: Some(LogPriorityRange {
+                from: LogPriority::Emerg,
+                to: LogPriority::Warning,
+            }),
+            since: Some("2026-10-16 08:00".to_string()),
+            until: None,
+            grep: Some("-failed password".to_string()),
+            max_entries: None,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        assert_eq!(
+            argv(&q),
+            vec![
+                "journalctl",
+                "--no-pager",
+                "--output=json",
+                "--output-fields=MESSAGE,PRIORITY,_PID,_SYSTEMD_UNIT",
+                "--lines=100",
+                "--unit=ssh.service",
+                "--priority=emerg..warning",
+                "--since=2026-10-16 08:00",
+                "--grep=-failed password",
+            ]
+        );
+    }
+}
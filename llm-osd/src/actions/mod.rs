@@ -1,7 +1,19 @@
 // ABOUTME: implements the allowlisted action execution logic used by the daemon.
 // ABOUTME: keeps each os capability in its own module for clean extension.
 
+use std::future::Future;
+use std::pin::Pin;
+
+pub mod cgroup;
 pub mod exec;
 pub mod files;
+pub mod firmware;
+pub mod journal;
+pub mod packages;
+pub mod procs;
+pub mod service;
+
+// backends are trait objects so tests can swap in fakes; their async methods return boxed futures.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
 
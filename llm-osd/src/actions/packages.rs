@@ -0,0 +1,615 @@
+// ABOUTME: installs, removes and updates packages through one backend per package manager.
+// ABOUTME: reports an outcome per requested package and per update step rather than a bare argv.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use llm_os_common::{
+    ActionError, ActionErrorCode, PackageManager, PackageOutcome, PackageState, UpdateScope, UpdateStep, UpdateStepState,
+};
+use tokio::process::Command;
+
+use super::BoxFuture;
+
+// how long a transaction may wait for another package manager to release its lock.
+const LOCK_WAIT: Duration = Duration::from_secs(300);
+const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_DETAIL_BYTES: usize = 512;
+const SEARCH_PATH: &[&str] = &["/usr/local/sbin", "/usr/local/bin", "/usr/sbin", "/usr/bin", "/sbin", "/bin", "/opt/homebrew/bin"];
+const PACMAN_LOCK: &str = "/var/lib/pacman/db.lck";
+
+// keeps every manager from prompting, paging, or rewriting its output for a terminal.
+const NONINTERACTIVE_ENV: &[(&str, &str)] = &[
+    ("DEBIAN_FRONTEND", "noninteractive"),
+    ("APT_LISTCHANGES_FRONTEND", "none"),
+    ("NEEDRESTART_MODE", "a"),
+    ("HOMEBREW_NO_AUTO_UPDATE", "1"),
+    ("HOMEBREW_NO_INSTALL_CLEANUP", "1"),
+    ("ZYPP_LOCK_TIMEOUT", "300"),
+    ("LC_ALL", "C"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageOp {
+    Install,
+    Remove,
+}
+
+pub trait PackageBackend: Send + Sync {
+    // the transaction command line, also reported for plan_only requests.
+    fn argv(&self, op: PackageOp, packages: &[String]) -> Vec<String>;
+
+    fn is_installed<'a>(&'a self, package: &'a str) -> BoxFuture<'a, Result<bool, String>>;
+
+    // whether the configured repositories know the package at all.
+    fn is_available<'a>(&'a self, package: &'a str) -> BoxFuture<'a, Result<bool, String>>;
+
+    // runs one transaction over all packages; an error carries the manager's own explanation.
+    fn apply<'a>(&'a self, op: PackageOp, packages: &'a [String]) -> BoxFuture<'a, Result<(), String>>;
+
+    // the ordered command lines for update_system; none when the manager cannot do this scope.
+    fn update_steps(&self, scope: UpdateScope) -> Option<Vec<Vec<String>>>;
+
+    fn run_step<'a>(&'a self, argv: &'a [String]) -> BoxFuture<'a, Result<(), StepError>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepError {
+    // none when the step could not be started, timed out, or was killed by a signal.
+    pub exit_code: Option<i32>,
+    pub message: String,
+}
+
+pub struct Backends {
+    backends: Vec<(PackageManager, Arc<dyn PackageBackend>)>,
+}
+
+impl Backends {
+    pub fn native() -> Self {
+        let managers = [
+            PackageManager::Apt,
+            PackageManager::Dnf,
+            PackageManager::Pacman,
+            PackageManager::Zypper,
+            PackageManager::Brew,
+        ];
+        Backends {
+            backends: managers
+                .into_iter()
+                .map(|manager| (manager.clone(), Arc::new(Native { manager }) as Arc<dyn PackageBackend>))
+                .collect(),
+        }
+    }
+
+    // `other` has no backend.
+    pub fn get(&self, manager: &PackageManager) -> Option<&dyn PackageBackend> {
+        self.backends
+            .iter()
+            .find(|(m, _)| m == manager)
+            .map(|(_, backend)| backend.as_ref())
+    }
+
+    #[cfg(test)]
+    pub fn set(&mut self, manager: PackageManager, backend: Arc<dyn PackageBackend>) {
+        self.backends.retain(|(m, _)| *m != manager);
+        self.backends.push((manager, backend));
+    }
+}
+
+pub struct Applied {
+    pub argv: Vec<String>,
+    pub packages: Vec<PackageOutcome>,
+    pub error: Option<ActionError>,
+}
+
+// skips packages that are already in the wanted state, runs one transaction for the rest, and then
+// asks the manager again rather than trusting its exit status.
+pub async fn apply(backend: &dyn PackageBackend, op: PackageOp, packages: &[String]) -> Applied {
+    let argv = backend.argv(op, packages);
+    match classify(backend, op, packages).await {
+        Ok(outcomes) => {
+            let error = failure(op, &outcomes);
+            Applied {
+                argv,
+                packages: outcomes,
+                error,
+            }
+        }
+        Err(message) => Applied {
+            argv,
+            packages: vec![],
+            error: Some(ActionError {
+                code: ActionErrorCode::PackageFailed,
+                message,
+                policy: None,
+            }),
+        },
+    }
+}
+
+async fn classify(backend: &dyn PackageBackend, op: PackageOp, packages: &[String]) -> Result<Vec<PackageOutcome>, String> {
+    let mut outcomes = Vec::with_capacity(packages.len());
+    let mut pending = Vec::new();
+    for name in packages {
+        let installed = backend.is_installed(name).await?;
+        let state = match op {
+            PackageOp::Install if installed => Some(PackageState::AlreadyPresent),
+            PackageOp::Install if !backend.is_available(name).await? => Some(PackageState::NotFound),
+            PackageOp::Remove if !installed => Some(PackageState::NotInstalled),
+            _ => None,
+        };
+        if state.is_none() {
+            pending.push(name.clone());
+        }
+        outcomes.push(PackageOutcome {
+            name: name.clone(),
+            state: state.unwrap_or(PackageState::Failed),
+            detail: None,
+        });
+    }
+    if pending.is_empty() {
+        return Ok(outcomes);
+    }
+
+    let transaction = backend.apply(op, &pending).await;
+    for outcome in outcomes.iter_mut().filter(|o| pending.contains(&o.name)) {
+        let installed = backend.is_installed(&outcome.name).await?;
+        outcome.state = match (op, installed) {
+            (PackageOp::Install, true) => PackageState::Installed,
+            (PackageOp::Remove, false) => PackageState::Removed,
+            _ => {
+                outcome.detail = Some(match &transaction {
+                    Err(message) => message.clone(),
+                    Ok(()) => "the package manager succeeded but the package state did not change".to_string(),
+                });
+                PackageState::Failed
+            }
+        };
+    }
+    Ok(outcomes)
+}
+
+fn failure(op: PackageOp, outcomes: &[PackageOutcome]) -> Option<ActionError> {
+    let done: &[PackageState] = match op {
+        PackageOp::Install => &[PackageState::Installed, PackageState::AlreadyPresent],
+        PackageOp::Remove => &[PackageState::Removed, PackageState::NotInstalled],
+    };
+    let failed: Vec<String> = outcomes
+        .iter()
+        .filter(|o| !done.contains(&o.state))
+        .map(|o| format!("{} ({})", o.name, state_name(o.state)))
+        .collect();
+    if failed.is_empty() {
+        return None;
+    }
+    Some(ActionError {
+        code: ActionErrorCode::PackageFailed,
+        message: format!("{} of {} packages failed: {}", failed.len(), outcomes.len(), failed.join(", ")),
+        policy: None,
+    })
+}
+
+// every step is reported; the first failure stops the run and marks the remaining steps skipped.
+pub async fn update(backend: &dyn PackageBackend, steps: Vec<Vec<String>>) -> (Vec<UpdateStep>, Option<ActionError>) {
+    let total = steps.len();
+    let mut results = Vec::with_capacity(total);
+    let mut error = None;
+    for (index, argv) in steps.into_iter().enumerate() {
+        if error.is_some() {
+            results.push(UpdateStep {
+                argv,
+                state: UpdateStepState::Skipped,
+                exit_code: None,
+                detail: None,
+            });
+            continue;
+        }
+        let step = match backend.run_step(&argv).await {
+            Ok(()) => UpdateStep {
+                argv,
+                state: UpdateStepState::Succeeded,
+                exit_code: Some(0),
+                detail: None,
+            },
+            Err(failure) => {
+                error = Some(ActionError {
+                    code: ActionErrorCode::PackageFailed,
+                    message: format!("update step {} of {total} failed: {}", index + 1, failure.message),
+                    policy: None,
+                });
+                UpdateStep {
+                    argv,
+                    state: UpdateStepState::Failed,
+                    exit_code: failure.exit_code,
+                    detail: Some(failure.message),
+                }
+            }
+        };
+        results.push(step);
+    }
+    (results, error)
+}
+
+pub fn planned(steps: Vec<Vec<String>>) -> Vec<UpdateStep> {
+    steps
+        .into_iter()
+        .map(|argv| UpdateStep {
+            argv,
+            state: UpdateStepState::Planned,
+            exit_code: None,
+            detail: None,
+        })
+        .collect()
+}
+
+pub fn state_name(state: PackageState) -> &'static str {
+    match state {
+        PackageState::Installed => "installed",
+        PackageState::AlreadyPresent => "already_present",
+        PackageState::Removed => "removed",
+        PackageState::NotInstalled => "not_installed",
+        PackageState::NotFound => "not_found",
+        PackageState::Failed => "failed",
+    }
+}
+
+// drives the distribution's own tools; queries go through the low-level database where there is one.
+struct Native {
+    manager: PackageManager,
+}
+
+impl Native {
+    fn installed_query(&self, package: &str) -> (&'static str, Vec<String>) {
+        let (program, args): (&str, &[&str]) = match self.manager {
+            PackageManager::Apt => ("dpkg-query", &["-W", "-f=${db:Status-Status}"]),
+            PackageManager::Dnf | PackageManager::Zypper => ("rpm", &["-q"]),
+            PackageManager::Pacman => ("pacman", &["-Q"]),
+            PackageManager::Brew | PackageManager::Other => ("brew", &["list", "--versions"]),
+        };
+        (program, args.iter().map(|a| a.to_string()).chain([package.to_string()]).collect())
+    }
+
+    fn available_query(&self, package: &str) -> (&'static str, Vec<String>) {
+        let (program, args): (&str, &[&str]) = match self.manager {
+            PackageManager::Apt => ("apt-cache", &["show"]),
+            PackageManager::Dnf => ("dnf", &["-q", "info"]),
+            PackageManager::Pacman => ("pacman", &["-Si"]),
+            PackageManager::Zypper => ("zypper", &["--non-interactive", "search", "--match-exact"]),
+            PackageManager::Brew | PackageManager::Other => ("brew", &["info"]),
+        };
+        (program, args.iter().map(|a| a.to_string()).chain([package.to_string()]).collect())
+    }
+}
+
+impl PackageBackend for Native {
+    fn argv(&self, op: PackageOp, packages: &[String]) -> Vec<String> {
+        let lock_wait = format!("DPkg::Lock::Timeout={}", LOCK_WAIT.as_secs());
+        let prefix: Vec<&str> = match (&self.manager, op) {
+            (PackageManager::Apt, PackageOp::Install) => vec!["apt-get", "install", "-y", "-o", &lock_wait, "--"],
+            (PackageManager::Apt, PackageOp::Remove) => vec!["apt-get", "remove", "-y", "-o", &lock_wait, "--"],
+            (PackageManager::Dnf, PackageOp::Install) => vec!["dnf", "install", "-y", "--"],
+            (PackageManager::Dnf, PackageOp::Remove) => vec!["dnf", "remove", "-y", "--"],
+            (PackageManager::Pacman, PackageOp::Install) => vec!["pacman", "-S", "--noconfirm", "--needed", "--"],
+            (PackageManager::Pacman, PackageOp::Remove) => vec!["pacman", "-R", "--noconfirm", "--"],
+            (PackageManager::Zypper, PackageOp::Install) => vec!["zypper", "--non-interactive", "install", "--"],
+            (PackageManager::Zypper, PackageOp::Remove) => vec!["zypper", "--non-interactive", "remove", "--"],
+            (PackageManager::Brew | PackageManager::Other, PackageOp::Install) => vec!["brew", "install"],
+            (PackageManager::Brew | PackageManager::Other, PackageOp::Remove) => vec!["brew", "uninstall"],
+        };
+        prefix.into_iter().map(|a| a.to_string()).chain(packages.iter().cloned()).collect()
+    }
+
+    fn is_installed<'a>(&'a self, package: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+        Box::pin(async move {
+            let (program, args) = self.installed_query(package);
+            let output = run(program, &args, QUERY_TIMEOUT).await?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Ok(match self.manager {
+                // dpkg keeps removed-but-configured packages around in state `config-files`.
+                PackageManager::Apt => output.status.success() && stdout.trim() == "installed",
+                PackageManager::Brew | PackageManager::Other => output.status.success() && !stdout.trim().is_empty(),
+                _ => output.status.success(),
+            })
+        })
+    }
+
+    fn is_available<'a>(&'a self, package: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+        Box::pin(async move {
+            let (program, args) = self.available_query(package);
+            Ok(run(program, &args, QUERY_TIMEOUT).await?.status.success())
+        })
+    }
+
+    fn apply<'a>(&'a self, op: PackageOp, packages: &'a [String]) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let argv = self.argv(op, packages);
+            self.transact(&argv).await.map_err(|failure| failure.message)
+        })
+    }
+
+    // pacman has no refresh-only mode that is safe to leave behind (`-Sy` invites partial upgrades)
+    // and no security metadata, so it only supports a full `-Syu`.
+    fn update_steps(&self, scope: UpdateScope) -> Option<Vec<Vec<String>>> {
+        let lock_wait = format!("DPkg::Lock::Timeout={}", LOCK_WAIT.as_secs());
+        let steps: Vec<Vec<&str>> = match (&self.manager, scope) {
+            (PackageManager::Apt, UpdateScope::RefreshOnly) => vec![vec!["apt-get", "update", "-o", &lock_wait]],
+            (PackageManager::Apt, UpdateScope::Full) => vec![
+                vec!["apt-get", "update", "-o", &lock_wait],
+                vec!["apt-get", "upgrade", "-y", "-o", &lock_wait],
+            ],
+            // applies what unattended-upgrades is configured to take, which is the security pocket by default.
+            (PackageManager::Apt, UpdateScope::SecurityOnly) => {
+                vec![vec!["apt-get", "update", "-o", &lock_wait], vec!["unattended-upgrade"]]
+            }
+            (PackageManager::Dnf, UpdateScope::RefreshOnly) => vec![vec!["dnf", "makecache"]],
+            (PackageManager::Dnf, UpdateScope::Full) => vec![vec!["dnf", "makecache"], vec!["dnf", "upgrade", "-y"]],
+            (PackageManager::Dnf, UpdateScope::SecurityOnly) => {
+                vec![vec!["dnf", "makecache"], vec!["dnf", "upgrade", "-y", "--security"]]
+            }
+            (PackageManager::Pacman, UpdateScope::Full) => vec![vec!["pacman", "-Syu", "--noconfirm"]],
+            (PackageManager::Pacman, _) => return None,
+            (PackageManager::Zypper, UpdateScope::RefreshOnly) => vec![vec!["zypper", "--non-interactive", "refresh"]],
+            (PackageManager::Zypper, UpdateScope::Full) => vec![
+                vec!["zypper", "--non-interactive", "refresh"],
+                vec!["zypper", "--non-interactive", "update"],
+            ],
+            (PackageManager::Zypper, UpdateScope::SecurityOnly) => vec![
+                vec!["zypper", "--non-interactive", "refresh"],
+                vec!["zypper", "--non-interactive", "patch", "--category", "security"],
+            ],
+            (PackageManager::Brew | PackageManager::Other, UpdateScope::RefreshOnly) => vec![vec!["brew", "update"]],
+            (PackageManager::Brew | PackageManager::Other, UpdateScope::Full) => {
+                vec![vec!["brew", "update"], vec!["brew", "upgrade"]]
+            }
+            (PackageManager::Brew | PackageManager::Other, UpdateScope::SecurityOnly) => return None,
+        };
+        Some(
+            steps
+                .into_iter()
+                .map(|step| step.into_iter().map(|a| a.to_string()).collect())
+                .collect(),
+        )
+    }
+
+    fn run_step<'a>(&'a self, argv: &'a [String]) -> BoxFuture<'a, Result<(), StepError>> {
+        Box::pin(self.transact(argv))
+    }
+}
+
+impl Native {
+    async fn transact(&self, argv: &[String]) -> Result<(), StepError> {
+        let failed = |message: String| StepError { exit_code: None, message };
+        // pacman fails at once on a held lock instead of waiting for it.
+        if self.manager == PackageManager::Pacman {
+            let deadline = tokio::time::Instant::now() + LOCK_WAIT;
+            while Path::new(PACMAN_LOCK).exists() {
+                if tokio::time::Instant::now() >= deadline {
+                    return Err(failed(format!("{PACMAN_LOCK} is still held after {}s", LOCK_WAIT.as_secs())));
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+        let output = run(&argv[0], &argv[1..], TRANSACTION_TIMEOUT).await.map_err(failed)?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let code = output.status.code().map_or("a signal".to_string(), |code| format!("status {code}"));
+        Err(StepError {
+            exit_code: output.status.code(),
+            message: format!("{} exited with {code}: {}", argv[0], tail(&output.stderr)),
+        })
+    }
+}
+
+async fn run(program: &str, args: &[String], timeout: Duration) -> Result<std::process::Output, String> {
+    let path = locate(program).ok_or_else(|| format!("{program} is not installed"))?;
+    let mut cmd = Command::new(path);
+    cmd.args(args)
+        .envs(NONINTERACTIVE_ENV.iter().copied())
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true);
+    match tokio::time::timeout(timeout, cmd.output()).await {
+        Ok(Ok(output)) => Ok(output),
+        Ok(Err(err)) => Err(format!("{program} could not be started: {err}")),
+        Err(_) => Err(format!("{program} timed out after {}s", timeout.as_secs())),
+    }
+}
+
+fn locate(program: &str) -> Option<PathBuf> {
+    SEARCH_PATH
+        .iter()
+        .map(|dir| Path::new(dir).join(program))
+        .find(|path| path.is_file())
+}
+
+// the end of stderr is where managers put the reason a transaction failed.
+fn tail(stderr: &[u8]) -> String {
+    let text = String::from_utf8_lossy(stderr);
+    let text = text.trim();
+    let mut start = text.len().saturating_sub(MAX_DETAIL_BYTES);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    text[start..].to_string()
+}
+
+// an in-memory package database; packages named `broken-*` are available but never install.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct FakeBackend {
+    pub installed: std::sync::Mutex<std::collections::BTreeSet<String>>,
+    pub available: std::collections::BTreeSet<String>,
+    pub transactions: std::sync::Mutex<Vec<Vec<String>>>,
+}
+
+#[cfg(test)]
+impl FakeBackend {
+    pub fn new(installed: &[&str], available: &[&str]) -> Self {
+        FakeBackend {
+            installed: std::sync::Mutex::new(installed.iter().map(|p| p.to_string()).collect()),
+            available: available.iter().map(|p| p.to_string()).collect(),
+            transactions: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl PackageBackend for FakeBackend {
+    fn argv(&self, op: PackageOp, packages: &[String]) -> Vec<String> {
+        let verb = if op == PackageOp::Install { "install" } else { "remove" };
+        ["fake".to_string(), verb.to_string()].into_iter().chain(packages.iter().cloned()).collect()
+    }
+
+    fn is_installed<'a>(&'a self, package: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+        Box::pin(async move { Ok(self.installed.lock().unwrap().contains(package)) })
+    }
+
+    fn is_available<'a>(&'a self, package: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+        Box::pin(async move { Ok(self.available.contains(package)) })
+    }
+
+    fn apply<'a>(&'a self, op: PackageOp, packages: &'a [String]) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            self.transactions.lock().unwrap().push(self.argv(op, packages));
+            let mut installed = self.installed.lock().unwrap();
+            for package in packages.iter().filter(|p| !p.starts_with("broken-")) {
+                match op {
+                    PackageOp::Install => installed.insert(package.clone()),
+                    PackageOp::Remove => installed.remove(package),
+                };
+            }
+            match packages.iter().find(|p| p.starts_with("broken-")) {
+                Some(package) => Err(format!("E: {package} has unmet dependencies")),
+                None => Ok(()),
+            }
+        })
+    }
+
+    // `upgrade` fails while a package named `broken-*` is installed.
+    fn update_steps(&self, scope: UpdateScope) -> Option<Vec<Vec<String>>> {
+        let steps: &[&[&str]] = match scope {
+            UpdateScope::RefreshOnly => &[&["fake", "refresh"]],
+            UpdateScope::Full => &[&["fake", "refresh"], &["fake", "upgrade"]],
+            UpdateScope::SecurityOnly => return None,
+        };
+        Some(steps.iter().map(|step| step.iter().map(|a| a.to_string()).collect()).collect())
+    }
+
+    fn run_step<'a>(&'a self, argv: &'a [String]) -> BoxFuture<'a, Result<(), StepError>> {
+        Box::pin(async move {
+            self.transactions.lock().unwrap().push(argv.to_vec());
+            let held = self.installed.lock().unwrap().iter().any(|p| p.starts_with("broken-"));
+            if argv[1] == "upgrade" && held {
+                return Err(StepError {
+                    exit_code: Some(100),
+                    message: "fake exited with status 100: broken packages".to_string(),
+                });
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(packages: &[&str]) -> Vec<String> {
+        packages.iter().map(|p| p.to_string()).collect()
+    }
+
+    fn states(applied: &Applied) -> Vec<(&str, PackageState)> {
+        applied.packages.iter().map(|o| (o.name.as_str(), o.state)).collect()
+    }
+
+    #[tokio::test]
+    async fn install_reports_each_package_and_only_transacts_the_missing_ones() {
+        let fake = FakeBackend::new(&["curl"], &["curl", "git", "broken-lib"]);
+        let applied = apply(&fake, PackageOp::Install, &names(&["curl", "git", "nope", "broken-lib"])).await;
+        assert_eq!(
+            states(&applied),
+            vec![
+                ("curl", PackageState::AlreadyPresent),
+                ("git", PackageState::Installed),
+                ("nope", PackageState::NotFound),
+                ("broken-lib", PackageState::Failed),
+            ]
+        );
+        assert_eq!(applied.packages[3].detail.as_deref(), Some("E: broken-lib has unmet dependencies"));
+        assert_eq!(*fake.transactions.lock().unwrap(), vec![names(&["fake", "install", "git", "broken-lib"])]);
+        let error = applied.error.unwrap();
+        assert_eq!(error.code, ActionErrorCode::PackageFailed);
+        assert_eq!(error.message, "2 of 4 packages failed: nope (not_found), broken-lib (failed)");
+
+        let again = apply(&fake, PackageOp::Install, &names(&["curl", "git"])).await;
+        assert!(again.error.is_none());
+        assert_eq!(fake.transactions.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn remove_skips_packages_that_are_not_installed() {
+        let fake = FakeBackend::new(&["curl", "git"], &["curl", "git"]);
+        let applied = apply(&fake, PackageOp::Remove, &names(&["git", "vim"])).await;
+        assert_eq!(
+            states(&applied),
+            vec![("git", PackageState::Removed), ("vim", PackageState::NotInstalled)]
+        );
+        assert!(applied.error.is_none());
+        assert_eq!(*fake.transactions.lock().unwrap(), vec![names(&["fake", "remove", "git"])]);
+    }
+
+    #[tokio::test]
+    async fn update_stops_at_the_first_failed_step() {
+        let fake = FakeBackend::new(&["curl"], &[]);
+        let steps = vec![names(&["fake", "refresh"]), names(&["fake", "upgrade"]), names(&["fake", "cleanup"])];
+        let (results, error) = update(&fake, steps.clone()).await;
+        assert!(error.is_none());
+        assert!(results.iter().all(|step| step.state == UpdateStepState::Succeeded));
+
+        fake.installed.lock().unwrap().insert("broken-lib".to_string());
+        let (results, error) = update(&fake, steps).await;
+        let states: Vec<_> = results.iter().map(|step| step.state).collect();
+        assert_eq!(states, vec![UpdateStepState::Succeeded, UpdateStepState::Failed, UpdateStepState::Skipped]);
+        assert_eq!(results[1].exit_code, Some(100));
+        assert_eq!(
+            error.unwrap().message,
+            "update step 2 of 3 failed: fake exited with status 100: broken packages"
+        );
+        assert_eq!(fake.transactions.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn native_update_steps_are_runnable_argv() {
+        let apt = Native { manager: PackageManager::Apt };
+        let steps = apt.update_steps(UpdateScope::Full).unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                names(&["apt-get", "update", "-o", "DPkg::Lock::Timeout=300"]),
+                names(&["apt-get", "upgrade", "-y", "-o", "DPkg::Lock::Timeout=300"]),
+            ]
+        );
+        assert!(steps.iter().flatten().all(|arg| arg != "&&"));
+        let dnf = Native { manager: PackageManager::Dnf };
+        assert_eq!(dnf.update_steps(UpdateScope::SecurityOnly).unwrap()[1], names(&["dnf", "upgrade", "-y", "--security"]));
+        let pacman = Native { manager: PackageManager::Pacman };
+        assert_eq!(pacman.update_steps(UpdateScope::Full).unwrap(), vec![names(&["pacman", "-Syu", "--noconfirm"])]);
+        assert!(pacman.update_steps(UpdateScope::RefreshOnly).is_none());
+    }
+
+    #[test]
+    fn native_transactions_never_prompt() {
+        let apt = Native { manager: PackageManager::Apt };
+        assert_eq!(
+            apt.argv(PackageOp::Install, &names(&["curl"])),
+            names(&["apt-get", "install", "-y", "-o", "DPkg::Lock::Timeout=300", "--", "curl"])
+        );
+        let pacman = Native { manager: PackageManager::Pacman };
+        assert_eq!(
+            pacman.argv(PackageOp::Remove, &names(&["vim"])),
+            names(&["pacman", "-R", "--noconfirm", "--", "vim"])
+        );
+    }
+}
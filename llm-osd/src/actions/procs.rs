@@ -0,0 +1,333 @@
+// ABOUTME: answers observe ps and top natively by walking /proc into structured process records.
+// ABOUTME: filtering, sorting, the top-n limit and field selection all happen in the daemon.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use llm_os_common::{
+    ActionError, ActionErrorCode, ActionResult, ObserveAction, ObserveResult, ObserveTool, ProcessField, ProcessInfo,
+    ProcessQuery, ProcessSnapshot,
+};
+
+pub const DEFAULT_PROC_ROOT: &str = "/proc";
+pub const PASSWD_PATH: &str = "/etc/passwd";
+const DEFAULT_TOP_LIMIT: u32 = 20;
+const MAX_PROCESSES: u32 = 1000;
+const MAX_CMDLINE_BYTES: usize = 1024;
+
+// kernel constants needed to turn /proc/<pid>/stat into bytes and milliseconds.
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    pub ticks_per_sec: u64,
+    pub page_size: u64,
+    pub boot_time_sec: u64,
+}
+
+impl Clock {
+    pub fn read(proc_root: &Path) -> Result<Clock, String> {
+        let stat = std::fs::read_to_string(proc_root.join("stat"))
+            .map_err(|err| format!("read {}: {err}", proc_root.join("stat").display()))?;
+        let boot_time_sec = stat
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))
+            .and_then(|value| value.trim().parse().ok())
+            .ok_or_else(|| "no btime in /proc/stat".to_string())?;
+        let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        Ok(Clock {
+            ticks_per_sec: u64::try_from(ticks_per_sec).unwrap_or(100).max(1),
+            page_size: u64::try_from(page_size).unwrap_or(4096),
+            boot_time_sec,
+        })
+    }
+}
+
+// uid to account name from a passwd file; unreadable files just leave numeric uids.
+pub fn read_users(path: &Path) -> HashMap<u32, String> {
+    let text = std::fs::read_to_string(path).unwrap_or_default();
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+pub fn observe(proc_root: &Path, obs: &ObserveAction) -> ActionResult {
+    let users = read_users(Path::new(PASSWD_PATH));
+    // validation already rejects these; refuse rather than answer a different question than was asked.
+    let queried = if obs.args.is_empty() {
+        snapshot(proc_root, &users, &obs.tool, obs.processes.as_ref())
+    } else {
+        Err("observe.args must be empty for ps and top; use observe.processes".to_string())
+    };
+    let (processes, error) = match queried {
+        Ok(snapshot) => (Some(snapshot), None),
+        Err(message) => (
+            None,
+            Some(ActionError {
+                code: ActionErrorCode::ObserveFailed,
+                message,
+                policy: None,
+            }),
+        ),
+    };
+    ActionResult::Observe(ObserveResult {
+        ok: error.is_none(),
+        argv: vec![],
+        exit_code: None,
+        stdout: String::new(),
+        stdout_truncated: false,
+        stderr: String::new(),
+        stderr_truncated: false,
+        arg_classes: vec![],
+        processes,
+        timeout_sec: None,
+        error,
+    })
+}
+
+pub fn snapshot(
+    proc_root: &Path,
+    users: &HashMap<u32, String>,
+    tool: &ObserveTool,
+    query: Option<&ProcessQuery>,
+) -> Result<ProcessSnapshot, String> {
+    let clock = Clock::read(proc_root)?;
+    let entries = std::fs::read_dir(proc_root).map_err(|err| format!("read {}: {err}", proc_root.display()))?;
+    let default = ProcessQuery::default();
+    let query = query.unwrap_or(&default);
+
+    // processes can exit between listing and reading; those are skipped.
+    let mut processes: Vec<ProcessInfo> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| read_process(proc_root, pid, &clock, users))
+        .filter(|process| matches(process, query))
+        .collect();
+
+    let top = matches!(tool, ObserveTool::Top);
+    let sort_by = query.sort_by.unwrap_or(if top { ProcessField::CpuTimeMs } else { ProcessField::Pid });
+    let descending = query.descending.unwrap_or(top && query.sort_by.is_none());
+    processes.sort_by(|a, b| {
+        let order = compare(a, b, sort_by).then(a.pid.cmp(&b.pid));
+        if descending {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+
+    let matched = processes.len() as u32;
+    let limit = query.limit.unwrap_or(if top { DEFAULT_TOP_LIMIT } else { MAX_PROCESSES });
+    processes.truncate(limit.min(MAX_PROCESSES) as usize);
+    if let Some(fields) = &query.fields {
+        processes.iter_mut().for_each(|process| select(process, fields));
+    }
+    Ok(ProcessSnapshot { matched, processes })
+}
+
+fn read_process(proc_root: &Path, pid: u32, clock: &Clock, users: &HashMap<u32, String>) -> Option<ProcessInfo> {
+    let dir = proc_root.join(pid.to_string());
+    let stat = std::fs::read_to_string(dir.join("stat")).ok()?;
+    let status = std::fs::read_to_string(dir.join("status")).ok()?;
+    let cmdline = std::fs::read(dir.join("cmdline")).unwrap_or_default();
+    let mut process = parse_stat(&stat, clock)?;
+    let uid = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))
+        .and_then(|ids| ids.split_whitespace().next())
+        .and_then(|uid| uid.parse::<u32>().ok());
+    process.user = uid.map(|uid| users.get(&uid).cloned().unwrap_or_else(|| uid.to_string()));
+    process.cmdline = Some(join_cmdline(&cmdline));
+    Some(process)
+}
+
+// `pid (name) state ppid ...`; the name may itself contain spaces and parentheses, so the
+// remaining fields are split after the last `)`. field numbers follow proc(5).
+pub fn parse_stat(stat: &str, clock: &Clock) -> Option<ProcessInfo> {
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let pid = stat[..open].trim().parse().ok()?;
+    let name = stat.get(open + 1..close)?.to_string();
+    let fields: Vec<&str> = stat.get(close + 1..)?.split_whitespace().collect();
+    let field = |n: usize| -> Option<u64> { fields.get(n - 3)?.parse().ok() };
+    let ticks_to_ms = |ticks: u64| ticks * 1000 / clock.ticks_per_sec;
+    Some(ProcessInfo {
+        pid,
+        ppid: field(4).map(|ppid| ppid as u32),
+        user: None,
+        state: fields.first().map(|state| state.to_string()),
+        name: Some(name),
+        cmdline: None,
+        rss_bytes: field(24).map(|pages| pages * clock.page_size),
+        cpu_time_ms: Some(ticks_to_ms(field(14)? + field(15)?)),
+        start_time_ms: field(22).map(|ticks| clock.boot_time_sec * 1000 + ticks_to_ms(ticks)),
+    })
+}
+
+fn join_cmdline(raw: &[u8]) -> String {
+    let raw = &raw[..raw.len().min(MAX_CMDLINE_BYTES)];
+    let args: Vec<String> = raw
+        .split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    args.join(" ")
+}
+
+fn matches(process: &ProcessInfo, query: &ProcessQuery) -> bool {
+    let equals = |want: &Option<String>, have: &Option<String>| want.is_none() || want == have;
+    let name = query.name.as_deref().is_none_or(|needle| {
+        [&process.name, &process.cmdline]
+            .iter()
+            .any(|field| field.as_deref().is_some_and(|value| value.contains(needle)))
+    });
+    equals(&query.user, &process.user)
+        && equals(&query.state, &process.state)
+        && query.ppid.is_none_or(|ppid| process.ppid == Some(ppid))
+        && name
+}
+
+fn compare(a: &ProcessInfo, b: &ProcessInfo, field: ProcessField) -> std::cmp::Ordering {
+    match field {
+        ProcessField::Pid => a.pid.cmp(&b.pid),
+        ProcessField::Ppid => a.ppid.cmp(&b.ppid),
+        ProcessField::User => a.user.cmp(&b.user),
+        ProcessField::State => a.state.cmp(&b.state),
+        ProcessField::Name => a.name.cmp(&b.name),
+        ProcessField::Cmdline => a.cmdline.cmp(&b.cmdline),
+        ProcessField::RssBytes => a.rss_bytes.cmp(&b.rss_bytes),
+        ProcessField::CpuTimeMs => a.cpu_time_ms.cmp(&b.cpu_time_ms),
+        ProcessField::StartTimeMs => a.start_time_ms.cmp(&b.start_time_ms),
+    }
+}
+
+fn select(process: &mut ProcessInfo, fields: &[ProcessField]) {
+    let keep = |field: ProcessField| fields.contains(&field);
+    let full = std::mem::take(process);
+    *process = ProcessInfo {
+        pid: full.pid,
+        ppid: full.ppid.filter(|_| keep(ProcessField::Ppid)),
+        user: full.user.filter(|_| keep(ProcessField::User)),
+        state: full.state.filter(|_| keep(ProcessField::State)),
+        name: full.name.filter(|_| keep(ProcessField::Name)),
+        cmdline: full.cmdline.filter(|_| keep(ProcessField::Cmdline)),
+        rss_bytes: full.rss_bytes.filter(|_| keep(ProcessField::RssBytes)),
+        cpu_time_ms: full.cpu_time_ms.filter(|_| keep(ProcessField::CpuTimeMs)),
+        start_time_ms: full.start_time_ms.filter(|_| keep(ProcessField::StartTimeMs)),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLOCK: Clock = Clock {
+        ticks_per_sec: 100,
+        page_size: 4096,
+        boot_time_sec: 1_700_000_000,
+    };
+
+    // a proc fixture: /proc/stat plus one directory per (pid, uid, stat tail, cmdline).
+    fn fixture(processes: &[(u32, u32, &str, &str)]) -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("stat"), "cpu  1 2 3\nbtime 1700000000\n").unwrap();
+        std::fs::create_dir(root.path().join("self")).unwrap();
+        for (pid, uid, stat, cmdline) in processes {
+            let dir = root.path().join(pid.to_string());
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("stat"), format!("{pid} {stat}")).unwrap();
+            std::fs::write(dir.join("status"), format!("Name:\tx\nUid:\t{uid}\t{uid}\t{uid}\t{uid}\n")).unwrap();
+            std::fs::write(dir.join("cmdline"), cmdline.replace(' ', "\0")).unwrap();
+        }
+        root
+    }
+
+    fn stat_tail(name: &str, state: &str, ppid: u32, utime: u64, rss_pages: u64) -> String {
+        format!("({name}) {state} {ppid} 1 1 0 -1 4194560 100 0 0 0 {utime} 50 0 0 20 0 1 0 500 1000000 {rss_pages} 0")
+    }
+
+    #[test]
+    fn parses_stat_with_spaces_and_parens_in_the_name() {
+        let stat = format!("42 {}", stat_tail("tmux: server (1)", "S", 1, 150, 10));
+        let process = parse_stat(&stat, &CLOCK).unwrap();
+        assert_eq!(process.pid, 42);
+        assert_eq!(process.name.as_deref(), Some("tmux: server (1)"));
+        assert_eq!(process.state.as_deref(), Some("S"));
+        assert_eq!(process.ppid, Some(1));
+        assert_eq!(process.cpu_time_ms, Some(2000));
+        assert_eq!(process.rss_bytes, Some(40960));
+        assert_eq!(process.start_time_ms, Some(1_700_000_005_000));
+    }
+
+    #[test]
+    fn observe_refuses_args_instead_of_ignoring_them() {
+        let root = fixture(&[]);
+        let obs = ObserveAction {
+            tool: ObserveTool::Ps,
+            name: None,
+            args: vec!["aux".to_string()],
+            processes: None,
+            timeout_sec: None,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        };
+        let r = match observe(root.path(), &obs) {
+            ActionResult::Observe(r) => r,
+            other => panic!("unexpected result {other:?}"),
+        };
+        assert!(!r.ok);
+        assert!(r.processes.is_none());
+        assert_eq!(r.error.unwrap().code, ActionErrorCode::ObserveFailed);
+    }
+
+    #[test]
+    fn snapshot_filters_sorts_limits_and_selects_fields() {
+        let init = stat_tail("systemd", "S", 0, 300, 100);
+        let nginx = stat_tail("nginx", "S", 1, 900, 50);
+        let worker = stat_tail("nginx", "R", 20, 100, 30);
+        let root = fixture(&[
+            (1, 0, &init, "/sbin/init splash"),
+            (20, 33, &nginx, "nginx: master process"),
+            (21, 33, &worker, "nginx: worker process"),
+        ]);
+        let users = HashMap::from([(0, "root".to_string()), (33, "www-data".to_string())]);
+
+        let top = snapshot(root.path(), &users, &ObserveTool::Top, None).unwrap();
+        let pids: Vec<u32> = top.processes.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, vec![20, 1, 21]);
+        assert_eq!(top.processes[0].user.as_deref(), Some("www-data"));
+        assert_eq!(top.processes[1].cmdline.as_deref(), Some("/sbin/init splash"));
+
+        let query = ProcessQuery {
+            user: Some("www-data".to_string()),
+            sort_by: Some(ProcessField::RssBytes),
+            limit: Some(1),
+            fields: Some(vec![ProcessField::Cmdline]),
+            ..ProcessQuery::default()
+        };
+        let ps = snapshot(root.path(), &users, &ObserveTool::Ps, Some(&query)).unwrap();
+        assert_eq!(ps.matched, 2);
+        assert_eq!(
+            ps.processes,
+            vec![ProcessInfo {
+                pid: 21,
+                cmdline: Some("nginx: worker process".to_string()),
+                ..ProcessInfo::default()
+            }]
+        );
+        let json = serde_json::to_string(&ps.processes[0]).unwrap();
+        assert_eq!(json, r#"{"pid":21,"cmdline":"nginx: worker process"}"#);
+
+        let query = ProcessQuery {
+            name: Some("worker".to_string()),
+            ..ProcessQuery::default()
+        };
+        let ps = snapshot(root.path(), &users, &ObserveTool::Ps, Some(&query)).unwrap();
+        assert_eq!(ps.processes.len(), 1);
+        assert_eq!(ps.processes[0].state.as_deref(), Some("R"));
+    }
+}
@@ -0,0 +1,213 @@
+// ABOUTME: runs service_control actions through a pluggable init backend, systemctl by default.
+// ABOUTME: status is read from `systemctl show` key=value output into a structured result.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use llm_os_common::{ActionError, ActionErrorCode, ActionResult, ServiceControlResult, ServiceControlVerb, ServiceStatus};
+use tokio::process::Command;
+
+use super::BoxFuture;
+
+// starting a unit waits for its job; systemd's own default start timeout is 90s.
+const SYSTEMCTL_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_STDERR_BYTES: usize = 2048;
+const SHOW_PROPERTIES: &str = "LoadState,ActiveState,SubState,MainPID";
+
+pub trait ServiceBackend: Send + Sync {
+    // the command line `control` runs, also reported for plan_only requests.
+    fn argv(&self, verb: &ServiceControlVerb, unit: &str) -> Vec<String>;
+
+    // performs the verb; `status` returns the unit's state and every other verb returns none.
+    fn control<'a>(&'a self, verb: &'a ServiceControlVerb, unit: &'a str) -> BoxFuture<'a, Result<Option<ServiceStatus>, String>>;
+}
+
+pub struct Systemctl {
+    program: PathBuf,
+}
+
+impl Systemctl {
+    pub fn new() -> Self {
+        let program = ["/usr/bin/systemctl", "/bin/systemctl"]
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file())
+            .unwrap_or_else(|| PathBuf::from("/usr/bin/systemctl"));
+        Systemctl { program }
+    }
+}
+
+impl ServiceBackend for Systemctl {
+    // `--` keeps a unit name that starts with `-` from being read as an option.
+    fn argv(&self, verb: &ServiceControlVerb, unit: &str) -> Vec<String> {
+        let mut argv = vec!["systemctl".to_string(), "--no-ask-password".to_string()];
+        match verb {
+            ServiceControlVerb::Status => {
+                argv.push("show".to_string());
+                argv.push(format!("--property={SHOW_PROPERTIES}"));
+            }
+            other => argv.push(verb_name(other).to_string()),
+        }
+        argv.push("--".to_string());
+        argv.push(unit.to_string());
+        argv
+    }
+
+    fn control<'a>(&'a self, verb: &'a ServiceControlVerb, unit: &'a str) -> BoxFuture<'a, Result<Option<ServiceStatus>, String>> {
+        Box::pin(async move {
+            let argv = self.argv(verb, unit);
+            let mut cmd = Command::new(&self.program);
+            cmd.args(&argv[1..]).kill_on_drop(true);
+            let output = match tokio::time::timeout(SYSTEMCTL_TIMEOUT, cmd.output()).await {
+                Ok(Ok(output)) => output,
+                Ok(Err(err)) => return Err(format!("systemctl could not be started: {err}")),
+                Err(_) => return Err(format!("systemctl timed out after {}s", SYSTEMCTL_TIMEOUT.as_secs())),
+            };
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr[..output.stderr.len().min(MAX_STDERR_BYTES)]);
+                let code = output.status.code().map_or("a signal".to_string(), |code| format!("status {code}"));
+                return Err(format!("systemctl {} exited with {code}: {}", verb_name(verb), stderr.trim()));
+            }
+            match verb {
+                ServiceControlVerb::Status => Ok(Some(parse_show(&String::from_utf8_lossy(&output.stdout)))),
+                _ => Ok(None),
+            }
+        })
+    }
+}
+
+pub fn verb_name(verb: &ServiceControlVerb) -> &'static str {
+    match verb {
+        ServiceControlVerb::Start => "start",
+        ServiceControlVerb::Stop => "stop",
+        ServiceControlVerb::Restart => "restart",
+        ServiceControlVerb::Enable => "enable",
+        ServiceControlVerb::Disable => "disable",
+        ServiceControlVerb::Status => "status",
+    }
+}
+
+// unknown keys are ignored; a main pid of 0 means the unit has no running main process.
+pub fn parse_show(output: &str) -> ServiceStatus {
+    let mut status = ServiceStatus {
+        load_state: String::new(),
+        active_state: String::new(),
+        sub_state: String::new(),
+        main_pid: None,
+    };
+    for line in output.lines() {
+        let (key, value) = match line.split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        match key {
+            "LoadState" => status.load_state = value.to_string(),
+            "ActiveState" => status.active_state = value.to_string(),
+            "SubState" => status.sub_state = value.to_string(),
+            "MainPID" => status.main_pid = value.parse().ok().filter(|pid| *pid != 0),
+            _ => {}
+        }
+    }
+    status
+}
+
+pub async fn run(backend: &dyn ServiceBackend, verb: &ServiceControlVerb, unit: &str) -> ActionResult {
+    let argv = backend.argv(verb, unit);
+    let (status, error) = match backend.control(verb, unit).await {
+        Ok(status) => (status, None),
+        Err(message) => (
+            None,
+            Some(ActionError {
+                code: ActionErrorCode::ServiceFailed,
+                message,
+                policy: None,
+            }),
+        ),
+    };
+    ActionResult::ServiceControl(ServiceControlResult {
+        ok: error.is_none(),
+        argv,
+        status,
+        error,
+    })
+}
+
+// an in-memory init system: units exist once something starts or enables them.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct FakeBackend {
+    pub calls: std::sync::Mutex<Vec<String>>,
+    units: std::sync::Mutex<std::collections::BTreeMap<String, (bool, bool)>>,
+}
+
+#[cfg(test)]
+impl ServiceBackend for FakeBackend {
+    fn argv(&self, verb: &ServiceControlVerb, unit: &str) -> Vec<String> {
+        vec!["fake".to_string(), verb_name(verb).to_string(), unit.to_string()]
+    }
+
+    fn control<'a>(&'a self, verb: &'a ServiceControlVerb, unit: &'a str) -> BoxFuture<'a, Result<Option<ServiceStatus>, String>> {
+        Box::pin(async move {
+            self.calls.lock().unwrap().push(format!("{} {unit}", verb_name(verb)));
+            let mut units = self.units.lock().unwrap();
+            if unit.starts_with("missing") {
+                return Err(format!("Unit {unit} not found."));
+            }
+            let (active, enabled) = units.entry(unit.to_string()).or_default();
+            match verb {
+                ServiceControlVerb::Start | ServiceControlVerb::Restart => *active = true,
+                ServiceControlVerb::Stop => *active = false,
+                ServiceControlVerb::Enable => *enabled = true,
+                ServiceControlVerb::Disable => *enabled = false,
+                ServiceControlVerb::Status => {
+                    let show = format!(
+                        "LoadState=loaded\nActiveState={}\nSubState={}\nMainPID={}\n",
+                        if *active { "active" } else { "inactive" },
+                        if *active { "running" } else { "dead" },
+                        if *active { 4242 } else { 0 },
+                    );
+                    return Ok(Some(parse_show(&show)));
+                }
+            }
+            Ok(None)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_systemctl_show_output() {
+        let status = parse_show("LoadState=loaded\nActiveState=active\nSubState=running\nMainPID=812\nId=ssh.service\n");
+        assert_eq!(status.load_state, "loaded");
+        assert_eq!(status.active_state, "active");
+        assert_eq!(status.sub_state, "running");
+        assert_eq!(status.main_pid, Some(812));
+
+        let status = parse_show("LoadState=not-found\nActiveState=inactive\nSubState=dead\nMainPID=0\n");
+        assert_eq!(status.load_state, "not-found");
+        assert_eq!(status.main_pid, None);
+    }
+
+    #[test]
+    fn systemctl_argv_ends_options_before_the_unit() {
+        let systemctl = Systemctl::new();
+        assert_eq!(
+            systemctl.argv(&ServiceControlVerb::Restart, "-H.service"),
+            vec!["systemctl", "--no-ask-password", "restart", "--", "-H.service"]
+        );
+        assert_eq!(
+            systemctl.argv(&ServiceControlVerb::Status, "ssh.service"),
+            vec![
+                "systemctl",
+                "--no-ask-password",
+                "show",
+                "--property=LoadState,ActiveState,SubState,MainPID",
+                "--",
+                "ssh.service"
+            ]
+        );
+    }
+}
@@ -0,0 +1,254 @@
+// ABOUTME: parks plans that policy marks for two-person approval until a second peer uid decides.
+// ABOUTME: approved plans run under the requester's profile; entries stay pollable by the requester and by approvers.
+
+use std::sync::Mutex;
+
+use llm_os_common::{ActionPlan, ActionPlanResult, ApprovalState, ApprovalStatus};
+use llm_os_policy as policy;
+
+use crate::audit;
+
+pub const DEFAULT_TTL_MS: u64 = 60 * 60 * 1000;
+const MAX_PENDING: usize = 64;
+// decided entries are kept for polling until the queue holds this many entries in total.
+const MAX_ENTRIES: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub status: ApprovalStatus,
+    pub plan: ActionPlan,
+    pub peer: Option<audit::PeerCredentials>,
+    pub identity: Option<policy::Peer>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParkError {
+    Duplicate,
+    Full,
+}
+
+#[derive(Debug)]
+pub struct Approvals {
+    ttl_ms: u64,
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl Approvals {
+    pub fn new(ttl_ms: u64) -> Self {
+        Approvals {
+            ttl_ms,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    // resending a plan that is already pending from the same uid returns the existing entry.
+    pub fn park(
+        &self,
+        plan: &ActionPlan,
+        peer: Option<audit::PeerCredentials>,
+        identity: Option<policy::Peer>,
+        now_ms: u64,
+    ) -> Result<ApprovalStatus, ParkError> {
+        let uid = peer.map(|creds| creds.uid);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(existing) = entries.iter().find(|e| e.status.request_id == plan.request_id) {
+            let same = existing.status.state == ApprovalState::Pending
+                && existing.status.requested_by == uid
+                && unconfirmed(&existing.plan) == unconfirmed(plan);
+            return if same { Ok(existing.status.clone()) } else { Err(ParkError::Duplicate) };
+        }
+        let pending = entries.iter().filter(|e| e.status.state == ApprovalState::Pending).count();
+        if pending >= MAX_PENDING {
+            return Err(ParkError::Full);
+        }
+        while entries.len() >= MAX_ENTRIES {
+            match entries.iter().position(is_settled) {
+                Some(index) => entries.remove(index),
+                None => break,
+            };
+        }
+
+        let status = ApprovalStatus {
+            request_id: plan.request_id.clone(),
+            state: ApprovalState::Pending,
+            requested_by: uid,
+            requested_at_ms: now_ms,
+            expires_at_ms: now_ms + self.ttl_ms,
+            decided_by: None,
+            decided_at_ms: None,
+            note: None,
+            plan: None,
+            result: None,
+        };
+        entries.push(Entry {
+            status: status.clone(),
+            plan: plan.clone(),
+            peer,
+            identity,
+        });
+        Ok(status)
+    }
+
+    // marks overdue pending entries expired and returns them so the caller can audit each one.
+    pub fn expire(&self, now_ms: u64) -> Vec<Entry> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let mut expired = Vec::new();
+        for entry in entries.iter_mut() {
+            if entry.status.state == ApprovalState::Pending && entry.status.expires_at_ms <= now_ms {
+                entry.status.state = ApprovalState::Expired;
+                entry.status.decided_at_ms = Some(now_ms);
+                expired.push(entry.clone());
+            }
+        }
+        expired
+    }
+
+    // the entries `uid` may see; peers the policy lets decide see every entry.
+    pub fn list(&self, uid: Option<u32>, decider: bool) -> Vec<ApprovalStatus> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .filter(|e| visible(e, uid, decider))
+            .map(|e| e.status.clone())
+            .collect()
+    }
+
+    // the full entry, including the parked plan and the execution result once there is one.
+    // an entry `uid` may not see is reported as missing.
+    pub fn show(&self, request_id: &str, uid: Option<u32>, decider: bool) -> Option<ApprovalStatus> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .find(|e| e.status.request_id == request_id && visible(e, uid, decider))
+            .map(|e| {
+                let mut status = e.status.clone();
+                status.plan = Some(e.plan.clone());
+                status
+            })
+    }
+
+    // moves a pending entry to approved or rejected; the requester's own uid can do neither.
+    pub fn decide(
+        &self,
+        request_id: &str,
+        approve: bool,
+        uid: Option<u32>,
+        note: Option<&str>,
+        now_ms: u64,
+    ) -> Result<Entry, String> {
+        let uid = uid.ok_or_else(|| "peer uid is unavailable".to_string())?;
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = entries
+            .iter_mut()
+            .find(|e| e.status.request_id == request_id)
+            .ok_or_else(|| format!("no parked plan with request_id {request_id}"))?;
+        if entry.status.state != ApprovalState::Pending {
+            return Err(format!("{request_id} is already {}", state_name(entry.status.state)));
+        }
+        if entry.status.requested_by == Some(uid) {
+            return Err(format!("uid {uid} submitted {request_id} and cannot also decide it"));
+        }
+        entry.status.state = if approve { ApprovalState::Approved } else { ApprovalState::Rejected };
+        entry.status.decided_by = Some(uid);
+        entry.status.decided_at_ms = Some(now_ms);
+        entry.status.note = note.map(|n| n.to_string());
+        Ok(entry.clone())
+    }
+
+    pub fn record_result(&self, request_id: &str, result: &ActionPlanResult) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(entry) = entries.iter_mut().find(|e| e.status.request_id == request_id) {
+            entry.status.result = Some(Box::new(result.clone()));
+        }
+    }
+}
+
+fn visible(entry: &Entry, uid: Option<u32>, decider: bool) -> bool {
+    decider || (uid.is_some() && entry.status.requested_by == uid)
+}
+
+// an approved entry is still running until its result has been recorded.
+fn is_settled(entry: &Entry) -> bool {
+    match entry.status.state {
+        ApprovalState::Pending => false,
+        ApprovalState::Approved => entry.status.result.is_some(),
+        ApprovalState::Rejected | ApprovalState::Expired => true,
+    }
+}
+
+fn unconfirmed(plan: &ActionPlan) -> ActionPlan {
+    let mut plan = plan.clone();
+    plan.confirmation = None;
+    plan
+}
+
+pub fn state_name(state: ApprovalState) -> &'static str {
+    match state {
+        ApprovalState::Pending => "pending",
+        ApprovalState::Approved => "approved",
+        ApprovalState::Rejected => "rejected",
+        ApprovalState::Expired => "expired",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(request_id: &str) -> ActionPlan {
+        llm_os_common::parse_action_plan(&format!(
+            r#"{{"request_id":"{request_id}","version":"0.1","mode":"execute","actions":[{{"type":"ping"}}]}}"#
+        ))
+        .unwrap()
+    }
+
+    fn creds(uid: u32) -> Option<audit::PeerCredentials> {
+        Some(audit::PeerCredentials { pid: 1, uid, gid: uid })
+    }
+
+    #[test]
+    fn only_another_uid_decides_and_only_once() {
+        let approvals = Approvals::new(1000);
+        let status = approvals.park(&plan("req-1"), creds(1000), None, 0).unwrap();
+        assert_eq!(status.state, ApprovalState::Pending);
+        assert_eq!(status.expires_at_ms, 1000);
+        assert_eq!(approvals.park(&plan("req-1"), creds(1000), None, 5).unwrap(), status);
+        assert_eq!(approvals.park(&plan("req-1"), creds(1001), None, 5), Err(ParkError::Duplicate));
+
+        let err = approvals.decide("req-1", true, Some(1000), None, 10).unwrap_err();
+        assert!(err.contains("cannot also decide"));
+        let entry = approvals.decide("req-1", true, Some(1001), Some("ok"), 10).unwrap();
+        assert_eq!(entry.status.state, ApprovalState::Approved);
+        assert_eq!(entry.status.decided_by, Some(1001));
+        let err = approvals.decide("req-1", false, Some(1002), None, 11).unwrap_err();
+        assert_eq!(err, "req-1 is already approved");
+        assert!(approvals.decide("req-missing", true, Some(1001), None, 11).is_err());
+
+        assert!(approvals.show("req-1", Some(1002), true).unwrap().plan.is_some());
+        assert!(approvals.list(Some(1002), true)[0].plan.is_none());
+    }
+
+    #[test]
+    fn only_the_requester_and_deciders_see_an_entry() {
+        let approvals = Approvals::new(1000);
+        approvals.park(&plan("req-1"), creds(1000), None, 0).unwrap();
+        assert!(approvals.show("req-1", Some(1000), false).is_some());
+        assert_eq!(approvals.list(Some(1000), false).len(), 1);
+        assert!(approvals.show("req-1", Some(1001), false).is_none());
+        assert!(approvals.list(Some(1001), false).is_empty());
+        assert!(approvals.show("req-1", None, false).is_none());
+        assert!(approvals.show("req-1", Some(1001), true).is_some());
+    }
+
+    #[test]
+    fn pending_entries_expire_once() {
+        let approvals = Approvals::new(1000);
+        approvals.park(&plan("req-1"), creds(1000), None, 0).unwrap();
+        assert!(approvals.expire(999).is_empty());
+        let expired = approvals.expire(1000);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].status.state, ApprovalState::Expired);
+        assert!(approvals.expire(2000).is_empty());
+        assert!(approvals.decide("req-1", true, Some(1001), None, 2000).is_err());
+    }
+}
@@ -2,7 +2,7 @@
 // ABOUTME: keeps auditing deterministic by logging structured json lines.
 
 use anyhow::Context;
-use llm_os_common::{ActionPlan, ActionPlanResult};
+use llm_os_common::{Action, ActionPlan, ActionPlanResult, ActionResult, ApprovalStatus};
 
 #[derive(Debug, Clone, Copy, serde::Serialize)]
 #[serde(deny_unknown_fields)]
@@ -17,8 +17,10 @@ pub struct PeerCredentials {
 struct AuditRecord<'a> {
     ts_unix_ms: u64,
     peer: Option<PeerCredentials>,
+    profile: Option<&'a str>,
     request_id: &'a str,
     session_id: Option<&'a str>,
+    confirmation: Option<&'a str>,
     plan: serde_json::Value,
     result: serde_json::Value,
 }
@@ -27,6 +29,8 @@ pub async fn append_record(
     audit_path: &str,
     ts_unix_ms: u64,
     peer: Option<PeerCredentials>,
+    profile: Option<&str>,
+    confirmation: Option<&str>,
     plan: &ActionPlan,
     result: &ActionPlanResult,
 ) -> anyhow::Result<()> {
@@ -36,8 +40,10 @@ pub async fn append_record(
     let record = AuditRecord {
         ts_unix_ms,
         peer,
+        profile,
         request_id: plan.request_id.as_str(),
         session_id: plan.session_id.as_deref(),
+        confirmation,
         plan: redacted_plan,
         result: redacted_result,
     };
@@ -60,7 +66,13 @@ pub async fn append_record(
 
 fn redact_plan(plan: &ActionPlan) -> anyhow::Result<serde_json::Value> {
     let mut v = serde_json::to_value(plan)?;
+    redact_plan_value(&mut v);
+    Ok(v)
+}
 
+// each arm keeps its own presence check so new redactions slot in without reshaping the match.
+#[allow(clippy::collapsible_match)]
+fn redact_plan_value(v: &mut serde_json::Value) {
     if let Some(obj) = v.as_object_mut() {
         if let Some(conf) = obj.get_mut("confirmation") {
             if let Some(conf_obj) = conf.as_object_mut() {
@@ -98,12 +110,16 @@ fn redact_plan(plan: &ActionPlan) -> anyhow::Result<serde_json::Value> {
             }
         }
     }
-
-    Ok(v)
 }
 
 fn redact_result(result: &ActionPlanResult) -> anyhow::Result<serde_json::Value> {
     let mut v = serde_json::to_value(result)?;
+    redact_result_value(&mut v);
+    Ok(v)
+}
+
+#[allow(clippy::collapsible_match)]
+fn redact_result_value(v: &mut serde_json::Value) {
     if let Some(obj) = v.as_object_mut() {
         if let Some(results) = obj.get_mut("results") {
             if let Some(arr) = results.as_array_mut() {
@@ -123,6 +139,20 @@ fn redact_result(result: &ActionPlanResult) -> anyhow::Result<serde_json::Value>
                                         serde_json::Value::String("[redacted]".to_string()),
                                     );
                                 }
+                                // process snapshots carry command lines, which can hold secrets.
+                                if action_obj.contains_key("processes") {
+                                    action_obj.insert(
+                                        "processes".to_string(),
+                                        serde_json::Value::String("[redacted]".to_string()),
+                                    );
+                                }
+                            }
+                            // journal messages can carry the same secrets as command output.
+                            Some("log_query") if action_obj.contains_key("entries") => {
+                                action_obj.insert(
+                                    "entries".to_string(),
+                                    serde_json::Value::String("[redacted]".to_string()),
+                                );
                             }
                             Some("read_file") => {
                                 if action_obj.contains_key("content_base64") {
@@ -132,14 +162,178 @@ fn redact_result(result: &ActionPlanResult) -> anyhow::Result<serde_json::Value>
                                     );
                                 }
                             }
+                            // approval entries carry whole parked plans and their results.
+                            Some("approval") => {
+                                if let Some(entries) = action_obj.get_mut("entries").and_then(|e| e.as_array_mut()) {
+                                    for entry in entries {
+                                        redact_approval_value(entry);
+                                    }
+                                }
+                            }
                             _ => {}
                         }
                     }
                 }
             }
         }
+        if let Some(approval) = obj.get_mut("approval") {
+            redact_approval_value(approval);
+        }
     }
-    Ok(v)
 }
 
+fn redact_approval_value(v: &mut serde_json::Value) {
+    if let Some(obj) = v.as_object_mut() {
+        if let Some(plan) = obj.get_mut("plan") {
+            redact_plan_value(plan);
+        }
+        if let Some(result) = obj.get_mut("result") {
+            redact_result_value(result);
+        }
+    }
+}
 
+// the same redaction as the audit records, applied to an approval entry shown to another peer.
+// typed lists have no room for a placeholder, so process snapshots and journal entries are dropped.
+pub fn redact_approval(status: &mut ApprovalStatus) {
+    const REDACTED: &str = "[redacted]";
+    if let Some(plan) = status.plan.as_mut() {
+        if let Some(conf) = plan.confirmation.as_mut() {
+            conf.token = REDACTED.to_string();
+        }
+        for action in plan.actions.iter_mut() {
+            match action {
+                Action::WriteFile(w) => w.content = REDACTED.to_string(),
+                Action::Exec(e) => {
+                    for v in e.env.iter_mut().flat_map(|env| env.values_mut()) {
+                        *v = REDACTED.to_string();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    if let Some(result) = status.result.as_mut() {
+        for action in result.results.iter_mut() {
+            match action {
+                ActionResult::Exec(r) => {
+                    r.stdout = REDACTED.to_string();
+                    r.stderr = REDACTED.to_string();
+                }
+                ActionResult::Observe(r) => {
+                    r.stdout = REDACTED.to_string();
+                    r.stderr = REDACTED.to_string();
+                    r.processes = None;
+                }
+                ActionResult::LogQuery(r) => r.entries.clear(),
+                ActionResult::ReadFile(r) if r.content_base64.is_some() => {
+                    r.content_base64 = Some(REDACTED.to_string());
+                }
+                ActionResult::Approval(r) => r.entries.iter_mut().for_each(redact_approval),
+                _ => {}
+            }
+        }
+        if let Some(approval) = result.approval.as_mut() {
+            redact_approval(approval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_plans_and_results_nested_in_approval_entries() {
+        let plan: ActionPlan = llm_os_common::parse_action_plan(
+            r#"{"request_id":"req-1","version":"0.1","mode":"execute","actions":[
+              {"type":"write_file","path":"/etc/motd","content":"secret","mode":"0644","reason":"test","danger":null,"recovery":null}
+            ]}"#,
+        )
+        .unwrap();
+        let executed: ActionPlanResult = serde_json::from_str(
+            r#"{"request_id":"req-1","executed":true,"results":[
+              {"type":"exec","ok":true,"resolved_path":"/bin/echo","exit_code":0,"stdout":"secret","stdout_truncated":false,"stderr":"","stderr_truncated":false,"error":null}
+            ],"explanations":null,"challenge":null,"approval":null,"error":null}"#,
+        )
+        .unwrap();
+        let status = llm_os_common::ApprovalStatus {
+            request_id: "req-1".to_string(),
+            state: llm_os_common::ApprovalState::Approved,
+            requested_by: Some(1000),
+            requested_at_ms: 0,
+            expires_at_ms: 1,
+            decided_by: Some(1001),
+            decided_at_ms: Some(1),
+            note: None,
+            plan: Some(plan),
+            result: Some(Box::new(executed)),
+        };
+        let response = ActionPlanResult {
+            request_id: "req-approvals-show".to_string(),
+            executed: true,
+            results: vec![llm_os_common::ActionResult::Approval(llm_os_common::ApprovalResult {
+                ok: true,
+                entries: vec![status],
+                error: None,
+            })],
+            explanations: None,
+            challenge: None,
+            approval: None,
+            grants_applied: None,
+            error: None,
+        };
+
+        let redacted = redact_result(&response).unwrap().to_string();
+        assert!(!redacted.contains("secret"), "{redacted}");
+    }
+
+    #[test]
+    fn redacts_log_query_entries() {
+        let response: ActionPlanResult = serde_json::from_str(
+            r#"{"request_id":"req-1","executed":true,"results":[
+              {"type":"log_query","ok":true,"argv":["journalctl"],"entries":[{"timestamp_us":1,"unit":null,"priority":null,"pid":null,"message":"password=secret","message_truncated":false}],"error":null}
+            ],"explanations":null,"challenge":null,"approval":null,"error":null}"#,
+        )
+        .unwrap();
+        let redacted = redact_result(&response).unwrap();
+        assert_eq!(redacted["results"][0]["entries"], "[redacted]");
+        assert_eq!(redacted["results"][0]["argv"][0], "journalctl");
+    }
+
+    #[test]
+    fn redacts_approval_entries_shown_to_other_peers() {
+        let plan = llm_os_common::parse_action_plan(
+            r#"{"request_id":"req-1","version":"0.1","mode":"execute","actions":[
+              {"type":"write_file","path":"/etc/motd","content":"secret","mode":"0644","reason":"test","danger":null,"recovery":null},
+              {"type":"exec","argv":["/bin/echo"],"cwd":null,"env":{"TOKEN":"secret"},"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}
+            ],"confirmation":{"token":"secret","nonce":null}}"#,
+        )
+        .unwrap();
+        let executed: ActionPlanResult = serde_json::from_str(
+            r#"{"request_id":"req-1","executed":true,"results":[
+              {"type":"exec","ok":true,"resolved_path":"/bin/echo","exit_code":0,"stdout":"secret","stdout_truncated":false,"stderr":"secret","stderr_truncated":false,"error":null},
+              {"type":"log_query","ok":true,"argv":["journalctl"],"entries":[{"timestamp_us":1,"unit":null,"priority":null,"pid":null,"message":"secret","message_truncated":false}],"error":null}
+            ],"explanations":null,"challenge":null,"approval":null,"error":null}"#,
+        )
+        .unwrap();
+        let mut status = llm_os_common::ApprovalStatus {
+            request_id: "req-1".to_string(),
+            state: llm_os_common::ApprovalState::Approved,
+            requested_by: Some(1000),
+            requested_at_ms: 0,
+            expires_at_ms: 1,
+            decided_by: Some(1001),
+            decided_at_ms: Some(1),
+            note: None,
+            plan: Some(plan),
+            result: Some(Box::new(executed)),
+        };
+
+        redact_approval(&mut status);
+        let redacted = serde_json::to_string(&status).unwrap();
+        assert!(!redacted.contains("secret"), "{redacted}");
+        assert!(redacted.contains("/etc/motd"));
+    }
+
+}
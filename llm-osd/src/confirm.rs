@@ -0,0 +1,235 @@
+// ABOUTME: issues and redeems single-use confirmation challenges bound to one plan and one peer.
+// ABOUTME: a remembered token alone no longer confirms anything; each confirmation needs a fresh nonce.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use llm_os_common::{ActionPlan, ConfirmationChallenge};
+use sha2::{Digest, Sha256};
+
+pub const DEFAULT_TTL_MS: u64 = 5 * 60 * 1000;
+const MAX_PENDING: usize = 1024;
+// one peer flooding unconfirmed plans only ever evicts its own challenges.
+const MAX_PENDING_PER_UID: usize = 16;
+// failures allowed before the first lockout; each later failure doubles the lockout up to the cap.
+const FREE_FAILURES: u32 = 3;
+const FIRST_LOCKOUT_MS: u64 = 1000;
+const MAX_LOCKOUT_MS: u64 = 60 * 60 * 1000;
+
+#[derive(Debug, Clone)]
+struct Pending {
+    plan_hash: String,
+    uid: Option<u32>,
+    expires_at_ms: u64,
+}
+
+#[derive(Debug)]
+pub struct Challenges {
+    ttl_ms: u64,
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+impl Challenges {
+    pub fn new(ttl_ms: u64) -> Self {
+        Challenges {
+            ttl_ms,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn issue(&self, plan_hash: &str, uid: Option<u32>, now_ms: u64) -> std::io::Result<ConfirmationChallenge> {
+        let nonce = random_nonce()?;
+        let expires_at_ms = now_ms + self.ttl_ms;
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.retain(|_, p| p.expires_at_ms > now_ms);
+        // a flood of unconfirmed plans must not grow memory without bound; drop the uid's oldest
+        // first, and only fall back to the oldest overall once every uid together fills the table.
+        let oldest = |pending: &HashMap<String, Pending>, same_uid: bool| {
+            pending
+                .iter()
+                .filter(|(_, p)| !same_uid || p.uid == uid)
+                .min_by_key(|(_, p)| p.expires_at_ms)
+                .map(|(nonce, _)| nonce.clone())
+        };
+        while pending.values().filter(|p| p.uid == uid).count() >= MAX_PENDING_PER_UID {
+            match oldest(&pending, true) {
+                Some(nonce) => pending.remove(&nonce),
+                None => break,
+            };
+        }
+        while pending.len() >= MAX_PENDING {
+            match oldest(&pending, false) {
+                Some(nonce) => pending.remove(&nonce),
+                None => break,
+            };
+        }
+        pending.insert(
+            nonce.clone(),
+            Pending {
+                plan_hash: plan_hash.to_string(),
+                uid,
+                expires_at_ms,
+            },
+        );
+        Ok(ConfirmationChallenge {
+            nonce,
+            plan_hash: plan_hash.to_string(),
+            expires_at_ms,
+        })
+    }
+
+    // the nonce is consumed by any attempt to use it, so a mismatched retry cannot probe it twice.
+    pub fn redeem(&self, nonce: &str, plan_hash: &str, uid: Option<u32>, now_ms: u64) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        match pending.remove(nonce) {
+            Some(p) => p.plan_hash == plan_hash && p.uid == uid && p.expires_at_ms > now_ms,
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Failures {
+    count: u32,
+    locked_until_ms: u64,
+}
+
+// counts failed confirmation attempts per peer uid and locks the uid out for a growing interval.
+#[derive(Debug, Default)]
+pub struct Lockout {
+    failures: Mutex<HashMap<Option<u32>, Failures>>,
+}
+
+impl Lockout {
+    pub fn new() -> Self {
+        Lockout::default()
+    }
+
+    pub fn locked_until(&self, uid: Option<u32>, now_ms: u64) -> Option<u64> {
+        let failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        failures
+            .get(&uid)
+            .map(|f| f.locked_until_ms)
+            .filter(|until| *until > now_ms)
+    }
+
+    // records one failure and returns the lockout deadline it triggered, if any.
+    pub fn fail(&self, uid: Option<u32>, now_ms: u64) -> Option<u64> {
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = failures.entry(uid).or_default();
+        entry.count = entry.count.saturating_add(1);
+        if entry.count <= FREE_FAILURES {
+            return None;
+        }
+        let doublings = (entry.count - FREE_FAILURES - 1).min(32);
+        let lockout_ms = FIRST_LOCKOUT_MS.saturating_mul(1u64 << doublings).min(MAX_LOCKOUT_MS);
+        entry.locked_until_ms = now_ms + lockout_ms;
+        Some(entry.locked_until_ms)
+    }
+
+    pub fn succeed(&self, uid: Option<u32>) {
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        failures.remove(&uid);
+    }
+}
+
+// sha-256 over the canonical json of the plan without its confirmation block.
+pub fn plan_hash(plan: &ActionPlan) -> String {
+    let mut unconfirmed = plan.clone();
+    unconfirmed.confirmation = None;
+    let canonical = serde_json::to_vec(&unconfirmed).unwrap_or_default();
+    hex(&Sha256::digest(&canonical))
+}
+
+fn random_nonce() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    let n = unsafe { libc::getrandom(bytes.as_mut_ptr().cast(), bytes.len(), 0) };
+    if n != bytes.len() as isize {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(hex(&bytes))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(argv0: &str) -> ActionPlan {
+        llm_os_common::parse_action_plan(&format!(
+            r#"{{"request_id":"req-1","version":"0.1","mode":"execute","actions":[{{"type":"exec","argv":["{argv0}"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}}],"confirmation":{{"token":"t","nonce":null}}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn plan_hash_ignores_confirmation_but_not_actions() {
+        let mut confirmed = plan("/usr/bin/true");
+        confirmed.confirmation.as_mut().unwrap().nonce = Some("abc".to_string());
+        assert_eq!(plan_hash(&plan("/usr/bin/true")), plan_hash(&confirmed));
+        assert_ne!(plan_hash(&plan("/usr/bin/true")), plan_hash(&plan("/usr/bin/false")));
+    }
+
+    #[test]
+    fn nonces_are_single_use_and_bound_to_plan_uid_and_expiry() {
+        let challenges = Challenges::new(1000);
+        let hash = plan_hash(&plan("/usr/bin/true"));
+
+        let c = challenges.issue(&hash, Some(1000), 0).unwrap();
+        assert_eq!(c.expires_at_ms, 1000);
+        assert!(challenges.redeem(&c.nonce, &hash, Some(1000), 10));
+        assert!(!challenges.redeem(&c.nonce, &hash, Some(1000), 10));
+
+        let c = challenges.issue(&hash, Some(1000), 0).unwrap();
+        assert!(!challenges.redeem(&c.nonce, &hash, Some(1001), 10));
+        assert!(!challenges.redeem(&c.nonce, &hash, Some(1000), 10));
+
+        let c = challenges.issue(&hash, Some(1000), 0).unwrap();
+        assert!(!challenges.redeem(&c.nonce, "other", Some(1000), 10));
+
+        let c = challenges.issue(&hash, Some(1000), 0).unwrap();
+        assert!(!challenges.redeem(&c.nonce, &hash, Some(1000), 1000));
+    }
+
+    #[test]
+    fn a_flooding_uid_only_evicts_its_own_challenges() {
+        let challenges = Challenges::new(1_000_000);
+        let hash = plan_hash(&plan("/usr/bin/true"));
+        let now = 2 * MAX_PENDING as u64;
+
+        let other = challenges.issue(&hash, Some(1001), 0).unwrap();
+        let first = challenges.issue(&hash, Some(1000), 0).unwrap();
+        let flood: Vec<_> = (1..now).map(|t| challenges.issue(&hash, Some(1000), t).unwrap()).collect();
+
+        assert!(!challenges.redeem(&first.nonce, &hash, Some(1000), now));
+        for c in &flood[flood.len() - MAX_PENDING_PER_UID..] {
+            assert!(challenges.redeem(&c.nonce, &hash, Some(1000), now));
+        }
+        assert!(challenges.redeem(&other.nonce, &hash, Some(1001), now));
+    }
+
+    #[test]
+    fn lockout_escalates_per_uid_and_resets_on_success() {
+        let lockout = Lockout::new();
+        for _ in 0..FREE_FAILURES {
+            assert_eq!(lockout.fail(Some(1000), 0), None);
+        }
+        assert_eq!(lockout.fail(Some(1000), 0), Some(1000));
+        assert_eq!(lockout.fail(Some(1000), 0), Some(2000));
+        assert_eq!(lockout.fail(Some(1000), 0), Some(4000));
+        assert_eq!(lockout.locked_until(Some(1000), 3999), Some(4000));
+        assert_eq!(lockout.locked_until(Some(1000), 4000), None);
+        assert_eq!(lockout.locked_until(Some(1001), 0), None);
+
+        for _ in 0..64 {
+            lockout.fail(Some(1000), 0);
+        }
+        assert_eq!(lockout.locked_until(Some(1000), 0), Some(MAX_LOCKOUT_MS));
+
+        lockout.succeed(Some(1000));
+        assert_eq!(lockout.locked_until(Some(1000), 0), None);
+    }
+}
@@ -0,0 +1,287 @@
+// ABOUTME: keeps time-bounded confirmation grants per session_id and granting uid in memory.
+// ABOUTME: a matching grant stands in for confirmation of one action; expired grants are dropped on access.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use llm_os_common::{Action, GrantScope, SessionGrant};
+use llm_os_policy as policy;
+
+const MAX_GRANTS_PER_SESSION: usize = 32;
+// spread across sessions, one uid still cannot fill the daemon-wide table for everyone else.
+const MAX_GRANTS_PER_UID: usize = 64;
+const MAX_GRANTS: usize = 1024;
+
+#[derive(Debug, Default)]
+pub struct Grants {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    next_id: u64,
+    grants: Vec<SessionGrant>,
+}
+
+impl Grants {
+    pub fn new() -> Self {
+        Grants::default()
+    }
+
+    pub fn grant(
+        &self,
+        session_id: &str,
+        uid: Option<u32>,
+        scope: &GrantScope,
+        ttl_ms: u64,
+        note: Option<&str>,
+        now_ms: u64,
+    ) -> Result<SessionGrant, String> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.grants.retain(|g| g.expires_at_ms > now_ms);
+        let in_session = state
+            .grants
+            .iter()
+            .filter(|g| g.session_id == session_id && g.uid == uid)
+            .count();
+        if in_session >= MAX_GRANTS_PER_SESSION {
+            return Err(format!("session {session_id} already holds {MAX_GRANTS_PER_SESSION} grants"));
+        }
+        if state.grants.iter().filter(|g| g.uid == uid).count() >= MAX_GRANTS_PER_UID {
+            return Err(format!("uid already holds {MAX_GRANTS_PER_UID} grants across its sessions"));
+        }
+        if state.grants.len() >= MAX_GRANTS {
+            return Err("too many active grants".to_string());
+        }
+        state.next_id += 1;
+        let grant = SessionGrant {
+            grant_id: format!("grant-{}", state.next_id),
+            session_id: session_id.to_string(),
+            uid,
+            scope: scope.clone(),
+            granted_at_ms: now_ms,
+            expires_at_ms: now_ms + ttl_ms,
+            note: note.map(|n| n.to_string()),
+        };
+        state.grants.push(grant.clone());
+        Ok(grant)
+    }
+
+    pub fn list(&self, session_id: &str, uid: Option<u32>, now_ms: u64) -> Vec<SessionGrant> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.grants.retain(|g| g.expires_at_ms > now_ms);
+        state
+            .grants
+            .iter()
+            .filter(|g| g.session_id == session_id && g.uid == uid)
+            .cloned()
+            .collect()
+    }
+
+    pub fn revoke(&self, session_id: &str, uid: Option<u32>, grant_id: &str, now_ms: u64) -> Result<SessionGrant, String> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.grants.retain(|g| g.expires_at_ms > now_ms);
+        let index = state
+            .grants
+            .iter()
+            .position(|g| g.grant_id == grant_id && g.session_id == session_id && g.uid == uid)
+            .ok_or_else(|| format!("no active grant {grant_id} in session {session_id}"))?;
+        Ok(state.grants.remove(index))
+    }
+
+    // the first live grant from this uid in this session that covers the action, if any.
+    pub fn covering(
+        &self,
+        session_id: &str,
+        uid: Option<u32>,
+        action: &Action,
+        policy: &policy::Policy,
+        now_ms: u64,
+    ) -> Option<String> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .grants
+            .iter()
+            .filter(|g| g.session_id == session_id && g.uid == uid && g.expires_at_ms > now_ms)
+            .find(|g| covers(&g.scope, action, policy))
+            .map(|g| g.grant_id.clone())
+    }
+}
+
+// matches the same resolved program and target paths the policy decided on. an exec grant vouches
+// for the program's own rule only: env, cwd, and anything else in the verdict that asks for
+// confirmation (wrapped commands, unparsed input) are outside what the grant describes.
+fn covers(scope: &GrantScope, action: &Action, policy: &policy::Policy) -> bool {
+    match (scope, action) {
+        (GrantScope::Exec(grant), Action::Exec(exec)) => {
+            if exec.env.as_ref().is_some_and(|env| !env.is_empty()) || exec.cwd.is_some() {
+                return false;
+            }
+            let program = match policy.resolve_exec(exec) {
+                Ok(program) => program,
+                Err(_) => return false,
+            };
+            let others = policy.exec_verdict(exec, &program).steps.split_off(1);
+            if others.iter().any(|step| step.decision >= policy::Decision::Confirm) {
+                return false;
+            }
+            let program_matches = policy::glob_match(&grant.program, &program.path.to_string_lossy())
+                || std::fs::canonicalize(&grant.program).is_ok_and(|granted| granted == program.path);
+            let args = exec.argv.get(1..).unwrap_or_default();
+            program_matches && grant.args.as_deref().is_none_or(|patterns| args_match(patterns, args))
+        }
+        (GrantScope::ReadFile(grant), Action::ReadFile(read)) => under_root(&grant.root, &read.path),
+        (GrantScope::WriteFile(grant), Action::WriteFile(write)) => under_root(&grant.root, &write.path),
+        _ => false,
+    }
+}
+
+// one glob per argument; a final `**` takes whatever arguments remain, including none.
+fn args_match(patterns: &[String], args: &[String]) -> bool {
+    let (fixed, rest) = match patterns.split_last() {
+        Some((last, fixed)) if last == "**" => (fixed, true),
+        _ => (patterns, false),
+    };
+    let count_ok = if rest { args.len() >= fixed.len() } else { args.len() == fixed.len() };
+    count_ok && fixed.iter().zip(args).all(|(pattern, arg)| policy::glob_match(pattern, arg))
+}
+
+fn under_root(root: &str, path: &str) -> bool {
+    let target = match policy::target_path(path) {
+        Ok(target) => target,
+        Err(_) => return false,
+    };
+    let root = std::fs::canonicalize(root).unwrap_or_else(|_| Path::new(root).to_path_buf());
+    target.starts_with(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llm_os_common::{ExecGrant, PathGrant};
+
+    fn exec(argv: &[&str]) -> Action {
+        exec_with(argv, None, None)
+    }
+
+    fn exec_with(argv: &[&str], cwd: Option<&str>, env: Option<(&str, &str)>) -> Action {
+        llm_os_common::Action::Exec(llm_os_common::ExecAction {
+            argv: argv.iter().map(|a| a.to_string()).collect(),
+            cwd: cwd.map(str::to_string),
+            env: env.map(|(key, value)| [(key.to_string(), value.to_string())].into_iter().collect()),
+            timeout_sec: 5,
+            as_root: false,
+            reason: "test".to_string(),
+            danger: None,
+            recovery: None,
+        })
+    }
+
+    #[test]
+    fn grants_match_program_args_and_session_until_expiry() {
+        let grants = Grants::new();
+        let policy = policy::Policy::default();
+        let scope = GrantScope::Exec(ExecGrant {
+            program: "/bin/true".to_string(),
+            args: Some(vec!["status".to_string(), "*".to_string()]),
+        });
+        let grant = grants.grant("sess-1", Some(1000), &scope, 1000, None, 0).unwrap();
+
+        let covering = |session: &str, uid: u32, action: &Action, now: u64| {
+            grants.covering(session, Some(uid), action, &policy, now)
+        };
+        assert_eq!(covering("sess-1", 1000, &exec(&["true", "status", "nginx"]), 10), Some(grant.grant_id.clone()));
+        assert_eq!(covering("sess-1", 1000, &exec(&["true", "restart", "nginx"]), 10), None);
+        assert_eq!(covering("sess-1", 1000, &exec(&["true", "status", "nginx", "--now"]), 10), None);
+        // an argument with a space cannot pose as two granted arguments, or the other way round.
+        assert_eq!(covering("sess-1", 1000, &exec(&["true", "status nginx"]), 10), None);
+        assert_eq!(covering("sess-1", 1000, &exec(&["true", "status", "two words"]), 10), Some(grant.grant_id.clone()));
+        assert_eq!(covering("sess-2", 1000, &exec(&["true", "status", "nginx"]), 10), None);
+        assert_eq!(covering("sess-1", 1001, &exec(&["true", "status", "nginx"]), 10), None);
+        assert_eq!(covering("sess-1", 1000, &exec(&["true", "status", "nginx"]), 1000), None);
+
+        assert!(grants.revoke("sess-1", Some(1001), &grant.grant_id, 10).is_err());
+        assert!(grants.revoke("sess-1", Some(1000), &grant.grant_id, 10).is_ok());
+        assert!(grants.list("sess-1", Some(1000), 10).is_empty());
+    }
+
+    #[test]
+    fn grants_are_capped_per_uid_across_sessions() {
+        let grants = Grants::new();
+        let scope = GrantScope::Exec(ExecGrant {
+            program: "/bin/true".to_string(),
+            args: None,
+        });
+        for i in 0..MAX_GRANTS_PER_UID {
+            let session = format!("sess-{}", i / MAX_GRANTS_PER_SESSION);
+            grants.grant(&session, Some(1000), &scope, 1000, None, 0).unwrap();
+        }
+        let err = grants.grant("sess-new", Some(1000), &scope, 1000, None, 0).unwrap_err();
+        assert_eq!(err, format!("uid already holds {MAX_GRANTS_PER_UID} grants across its sessions"));
+        assert!(grants.grant("sess-new", Some(1001), &scope, 1000, None, 0).is_ok());
+        // expired grants no longer count.
+        assert!(grants.grant("sess-new", Some(1000), &scope, 1000, None, 1000).is_ok());
+    }
+
+    #[test]
+    fn exec_grants_do_not_cover_env_cwd_or_other_confirm_steps() {
+        let grants = Grants::new();
+        let policy = policy::Policy::default();
+        let scope = |program: &str| {
+            GrantScope::Exec(ExecGrant {
+                program: program.to_string(),
+                args: Some(vec!["**".to_string()]),
+            })
+        };
+        grants.grant("sess-1", Some(1000), &scope("/bin/true"), 1000, None, 0).unwrap();
+        grants.grant("sess-1", Some(1000), &scope("/bin/sh"), 1000, None, 0).unwrap();
+        let covered = |action: &Action| grants.covering("sess-1", Some(1000), action, &policy, 10).is_some();
+
+        assert!(covered(&exec(&["true", "status"])));
+        assert!(!covered(&exec_with(&["true", "status"], None, Some(("LD_PRELOAD", "/tmp/evil.so")))));
+        assert!(!covered(&exec_with(&["true", "status"], None, Some(("LANG", "C")))));
+        assert!(!covered(&exec_with(&["true", "status"], Some("/tmp"), None)));
+        // the shell is granted, but the script it runs is not.
+        assert!(!covered(&exec(&["sh", "-c", "rm -rf /tmp/x"])));
+        assert!(!covered(&exec(&["sh", "-c", "echo $(id)"])));
+    }
+
+    #[test]
+    fn trailing_double_star_takes_the_remaining_args() {
+        let patterns = |p: &[&str]| p.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let args = |a: &[&str]| a.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let rest = patterns(&["status", "**"]);
+        assert!(args_match(&rest, &args(&["status"])));
+        assert!(args_match(&rest, &args(&["status", "nginx", "--no-pager"])));
+        assert!(!args_match(&rest, &args(&["restart", "nginx"])));
+        assert!(!args_match(&rest, &args(&["status nginx"])));
+        assert!(args_match(&patterns(&[]), &args(&[])));
+        assert!(!args_match(&patterns(&[]), &args(&["status"])));
+    }
+
+    #[test]
+    fn path_grants_cover_targets_under_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let grants = Grants::new();
+        let policy = policy::Policy::default();
+        let scope = GrantScope::WriteFile(PathGrant {
+            root: dir.path().to_string_lossy().to_string(),
+        });
+        grants.grant("sess-1", None, &scope, 1000, None, 0).unwrap();
+
+        let write = |path: String| {
+            Action::WriteFile(llm_os_common::WriteFileAction {
+                path,
+                content: String::new(),
+                mode: "0644".to_string(),
+                reason: "test".to_string(),
+                danger: None,
+                recovery: None,
+            })
+        };
+        let inside = dir.path().join("app.conf").to_string_lossy().to_string();
+        let outside = format!("{}/../elsewhere", dir.path().display());
+        assert!(grants.covering("sess-1", None, &write(inside), &policy, 1).is_some());
+        assert!(grants.covering("sess-1", None, &write(outside), &policy, 1).is_none());
+    }
+}
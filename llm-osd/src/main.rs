@@ -2,10 +2,16 @@
 // ABOUTME: exposes a local unix socket and writes an audit log for each request.
 
 mod actions;
+mod approvals;
 mod audit;
-mod policy;
+mod confirm;
+mod grants;
+mod peer;
+mod secret;
 mod server;
 
+use llm_os_policy as policy;
+
 use clap::Parser;
 
 #[derive(Debug, Parser)]
@@ -16,12 +22,50 @@ struct Args {
     #[arg(long, default_value = "./llm-osd-audit.jsonl")]
     audit_path: String,
 
-    #[arg(long, default_value = "i-understand")]
-    confirm_token: String,
+    // prefer --confirm-token-file or a systemd credential; this value shows up in ps.
+    #[arg(long)]
+    confirm_token: Option<String>,
+
+    #[arg(long)]
+    confirm_token_file: Option<String>,
+
+    #[arg(long)]
+    policy_path: Option<String>,
+
+    // bounded so the millisecond conversion below cannot overflow.
+    #[arg(long, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..=86_400))]
+    challenge_ttl_sec: u64,
+
+    #[arg(long, default_value_t = 3600, value_parser = clap::value_parser!(u64).range(1..=604_800))]
+    approval_ttl_sec: u64,
+
+    // cgroup_apply writes under <cgroup_root>/llm-osd, which must be delegated to the daemon.
+    #[arg(long, default_value = "/sys/fs/cgroup")]
+    cgroup_root: String,
+
+    // firmware_op reads dmi identity and efivarfs under this sysfs mount.
+    #[arg(long, default_value = "/sys")]
+    sysfs_root: String,
+
+    // a toml file of [[tools]] that observe can run by name with tool "other", and per-tool [timeouts].
+    #[arg(long)]
+    observe_tools_path: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
-    server::run(&args.socket_path, &args.audit_path, &args.confirm_token).await
+    let confirm_token = secret::load_confirm_token(args.confirm_token_file.as_deref(), args.confirm_token.as_deref())?;
+    let mut config = server::Config::new(&args.socket_path, &args.audit_path, &confirm_token);
+    config.challenges = confirm::Challenges::new(args.challenge_ttl_sec * 1000);
+    config.approvals = approvals::Approvals::new(args.approval_ttl_sec * 1000);
+    config.cgroup_root = args.cgroup_root.into();
+    config.sysfs_root = args.sysfs_root.into();
+    if let Some(policy_path) = &args.policy_path {
+        config.policy = policy::Policy::load(policy_path)?;
+    }
+    if let Some(observe_tools_path) = &args.observe_tools_path {
+        config.policy.observe_tools = policy::ObserveCatalog::load(observe_tools_path)?;
+    }
+    server::run(config).await
 }
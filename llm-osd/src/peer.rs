@@ -0,0 +1,87 @@
+// ABOUTME: reads the identity of the process on the other end of a unix socket connection.
+// ABOUTME: combines SO_PEERCRED, supplementary groups and the passwd entry for policy profile selection.
+
+use std::os::unix::io::AsRawFd;
+use tokio::net::UnixStream;
+
+use crate::audit;
+use llm_os_policy as policy;
+
+pub fn credentials(stream: &UnixStream) -> Option<audit::PeerCredentials> {
+    let fd = stream.as_raw_fd();
+
+    let mut ucred: libc::ucred = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let rc = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut ucred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if rc != 0 {
+        return None;
+    }
+    if len as usize != std::mem::size_of::<libc::ucred>() {
+        return None;
+    }
+
+    Some(audit::PeerCredentials {
+        pid: ucred.pid,
+        uid: ucred.uid,
+        gid: ucred.gid,
+    })
+}
+
+pub fn identity(stream: &UnixStream, creds: &audit::PeerCredentials) -> policy::Peer {
+    let groups = socket_groups(stream).unwrap_or_else(|| proc_groups(creds.pid));
+    policy::Peer {
+        uid: creds.uid,
+        gid: creds.gid,
+        groups,
+        home: policy::home_dir(creds.uid),
+    }
+}
+
+// SO_PEERGROUPS reports the groups captured at connect time, so it cannot race with the peer exiting.
+fn socket_groups(stream: &UnixStream) -> Option<Vec<u32>> {
+    let fd = stream.as_raw_fd();
+    let mut groups: Vec<libc::gid_t> = vec![0; 64];
+    for _ in 0..2 {
+        let mut len = (groups.len() * std::mem::size_of::<libc::gid_t>()) as libc::socklen_t;
+        let rc = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_PEERGROUPS,
+                groups.as_mut_ptr().cast(),
+                &mut len,
+            )
+        };
+        let count = len as usize / std::mem::size_of::<libc::gid_t>();
+        if rc == 0 {
+            groups.truncate(count);
+            return Some(groups);
+        }
+        if std::io::Error::last_os_error().raw_os_error() != Some(libc::ERANGE) {
+            return None;
+        }
+        groups.resize(count.max(groups.len() * 2), 0);
+    }
+    None
+}
+
+// fallback for kernels without SO_PEERGROUPS.
+fn proc_groups(pid: i32) -> Vec<u32> {
+    let status = match std::fs::read_to_string(format!("/proc/{pid}/status")) {
+        Ok(status) => status,
+        Err(_) => return vec![],
+    };
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Groups:"))
+        .map(|groups| groups.split_whitespace().filter_map(|g| g.parse().ok()).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,104 @@
+// ABOUTME: loads the confirmation token from a root-only file or a systemd credential.
+// ABOUTME: keeps the token off the command line, where any local user could read it from ps.
+
+use std::io::Read;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+// the credential name to pass to `LoadCredential=` / `SetCredentialEncrypted=` in the unit file.
+pub const CREDENTIAL_NAME: &str = "confirm-token";
+
+// an explicit file wins over a systemd credential; the command line value is a last resort.
+pub fn load_confirm_token(file: Option<&str>, inline: Option<&str>) -> anyhow::Result<String> {
+    if let Some(path) = file {
+        return read_token_file(Path::new(path));
+    }
+    if let Some(path) = credential_path() {
+        if path.exists() {
+            return read_token_file(&path);
+        }
+    }
+    if let Some(token) = inline {
+        return Ok(token.to_string());
+    }
+    Err(anyhow::anyhow!(
+        "no confirmation token configured: pass --confirm-token-file or provide the `{CREDENTIAL_NAME}` systemd credential"
+    ))
+}
+
+fn credential_path() -> Option<PathBuf> {
+    std::env::var_os("CREDENTIALS_DIRECTORY").map(|dir| Path::new(&dir).join(CREDENTIAL_NAME))
+}
+
+// the file must be a regular file owned by the daemon's user with no group or other access.
+pub fn read_token_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC)
+        .open(path)
+        .with_context(|| format!("open confirmation token at {}", path.display()))?;
+    let meta = file.metadata()?;
+    if !meta.is_file() {
+        return Err(anyhow::anyhow!("{} is not a regular file", path.display()));
+    }
+    let euid = unsafe { libc::geteuid() };
+    if meta.uid() != euid {
+        return Err(anyhow::anyhow!("{} must be owned by uid {euid}", path.display()));
+    }
+    if meta.mode() & 0o077 != 0 {
+        return Err(anyhow::anyhow!(
+            "{} is accessible by group or others (mode {:o}); chmod 600 it",
+            path.display(),
+            meta.mode() & 0o777
+        ));
+    }
+
+    let mut token = String::new();
+    file.read_to_string(&mut token)?;
+    let token = token.trim().to_string();
+    if token.is_empty() {
+        return Err(anyhow::anyhow!("{} is empty", path.display()));
+    }
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn token_files_must_be_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "s3cret\n").unwrap();
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        let err = read_token_file(&path).unwrap_err();
+        assert!(err.to_string().contains("accessible by group or others"));
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(read_token_file(&path).unwrap(), "s3cret");
+
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+        assert!(read_token_file(&link).is_err());
+    }
+
+    #[test]
+    fn explicit_file_wins_and_missing_sources_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "from-file").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o400)).unwrap();
+
+        let token = load_confirm_token(Some(&path.to_string_lossy()), Some("inline")).unwrap();
+        assert_eq!(token, "from-file");
+        if std::env::var_os("CREDENTIALS_DIRECTORY").is_none() {
+            assert!(load_confirm_token(None, None).is_err());
+            assert_eq!(load_confirm_token(None, Some("inline")).unwrap(), "inline");
+        }
+    }
+}
@@ -1,7 +1,8 @@
 // ABOUTME: hosts the unix socket server for receiving action plans and returning structured results.
 // ABOUTME: enforces strict parsing, validation, policy checks, and audit logging.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
@@ -9,13 +10,16 @@ use llm_os_common::{
     parse_action_plan, validate_action_plan, Action, ActionPlanResult, ActionResult, ErrorCode,
     Mode, RequestError,
 };
-use std::os::unix::io::AsRawFd;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{UnixListener, UnixStream};
 
 use crate::actions;
+use crate::approvals;
 use crate::audit;
-use crate::policy;
+use crate::confirm;
+use crate::grants;
+use crate::peer;
+use llm_os_policy as policy;
 
 const MAX_REQUEST_BYTES: usize = 256 * 1024;
 #[cfg(test)]
@@ -23,7 +27,46 @@ const READ_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(
 #[cfg(not(test))]
 const READ_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
 
-pub async fn run(socket_path: &str, audit_path: &str, confirm_token: &str) -> anyhow::Result<()> {
+pub struct Config {
+    pub socket_path: String,
+    pub audit_path: String,
+    pub confirm_token: String,
+    pub policy: policy::Policy,
+    pub challenges: confirm::Challenges,
+    pub lockout: confirm::Lockout,
+    pub approvals: approvals::Approvals,
+    pub grants: grants::Grants,
+    pub services: Arc<dyn actions::service::ServiceBackend>,
+    pub packages: actions::packages::Backends,
+    pub cgroup_root: PathBuf,
+    pub sysfs_root: PathBuf,
+    pub proc_root: PathBuf,
+    pub journalctl: PathBuf,
+}
+
+impl Config {
+    pub fn new(socket_path: &str, audit_path: &str, confirm_token: &str) -> Self {
+        Config {
+            socket_path: socket_path.to_string(),
+            audit_path: audit_path.to_string(),
+            confirm_token: confirm_token.to_string(),
+            policy: policy::Policy::default(),
+            challenges: confirm::Challenges::new(confirm::DEFAULT_TTL_MS),
+            lockout: confirm::Lockout::new(),
+            approvals: approvals::Approvals::new(approvals::DEFAULT_TTL_MS),
+            grants: grants::Grants::new(),
+            services: Arc::new(actions::service::Systemctl::new()),
+            packages: actions::packages::Backends::native(),
+            cgroup_root: PathBuf::from(actions::cgroup::DEFAULT_ROOT),
+            sysfs_root: PathBuf::from(actions::firmware::DEFAULT_SYSFS_ROOT),
+            proc_root: PathBuf::from(actions::procs::DEFAULT_PROC_ROOT),
+            journalctl: actions::journal::default_program(),
+        }
+    }
+}
+
+pub async fn run(config: Config) -> anyhow::Result<()> {
+    let socket_path = config.socket_path.as_str();
     if Path::new(socket_path).exists() {
         tokio::fs::remove_file(socket_path)
             .await
@@ -31,21 +74,22 @@ pub async fn run(socket_path: &str, audit_path: &str, confirm_token: &str) -> an
     }
 
     let listener = UnixListener::bind(socket_path).with_context(|| format!("bind {socket_path}"))?;
+    let config = Arc::new(config);
 
     loop {
         let (stream, _addr) = listener.accept().await?;
-        let audit_path = audit_path.to_string();
-        let confirm_token = confirm_token.to_string();
+        let config = Arc::clone(&config);
         tokio::spawn(async move {
-            if let Err(err) = handle_client(stream, &audit_path, &confirm_token).await {
+            if let Err(err) = handle_client(stream, &config).await {
                 let _ = err;
             }
         });
     }
 }
 
-async fn handle_client(mut stream: UnixStream, audit_path: &str, confirm_token: &str) -> anyhow::Result<()> {
-    let peer = peer_credentials(&stream);
+async fn handle_client(mut stream: UnixStream, config: &Config) -> anyhow::Result<()> {
+    let peer = peer::credentials(&stream);
+    let identity = peer.as_ref().map(|creds| peer::identity(&stream, creds));
 
     let mut input = Vec::new();
     let mut buf = [0u8; 4096];
@@ -120,64 +164,120 @@ async fn handle_client(mut stream: UnixStream, audit_path: &str, confirm_token:
         return Ok(());
     }
 
-    let confirmation_token = plan.confirmation.as_ref().map(|c| c.token.as_str());
+    let policy = match config.policy.for_peer(identity.as_ref()) {
+        Ok(policy) => policy,
+        Err(err) => {
+            let _ = write_request_error(
+                &mut stream,
+                &plan.request_id,
+                ErrorCode::NoMatchingProfile,
+                &format!("policy profile: {err}"),
+            )
+            .await;
+            return Ok(());
+        }
+    };
+
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let uid = peer.as_ref().map(|creds| creds.uid);
+    audit_expired_approvals(config, now_ms).await?;
+
+    if plan.mode == Mode::Execute && needs_approval(&plan, &policy) {
+        return park_plan(&mut stream, &plan, peer, identity, &policy, now_ms, config).await;
+    }
+
+    let plan_hash = confirm::plan_hash(&plan);
+    let (confirmed, attempt) = check_confirmation(&plan, &plan_hash, uid, now_ms, config);
+
+    let mut states = Vec::with_capacity(plan.actions.len());
+    let mut grants_applied = Vec::new();
+    for (index, action) in plan.actions.iter().enumerate() {
+        states.push(match granted(&plan, action, uid, confirmed, &policy, now_ms, config) {
+            Some(grant_id) => {
+                grants_applied.push(llm_os_common::GrantUse { index, grant_id });
+                ConfirmState::Granted
+            }
+            None => confirmed,
+        });
+    }
+    // nothing in an execute-mode plan runs while any of its actions still waits for confirmation:
+    // the plan is answered as plan_only would answer it, so the confirmed resend runs each action once.
+    let held = plan.mode == Mode::Execute
+        && plan.actions.iter().zip(&states).any(|(action, confirmed)| {
+            matches!(confirmed, ConfirmState::Unconfirmed | ConfirmState::LockedOut { .. })
+                && policy.action_verdict(action).decision() == policy::Decision::Confirm
+        });
 
     let mut results = Vec::with_capacity(plan.actions.len());
-    for action in &plan.actions {
+    for (action, &confirmed) in plan.actions.iter().zip(&states) {
         let result = match plan.mode {
-            Mode::Execute => execute_action(action, confirmation_token, confirm_token).await,
-            Mode::PlanOnly => plan_action(action, confirmation_token, confirm_token).await,
+            Mode::Execute if held => plan_action(action, confirmed, &policy, config).await,
+            Mode::Execute => match action {
+                Action::Approval(approval) => run_approval(approval, uid, confirmed, &policy, now_ms, config).await?,
+                Action::SessionGrant(grant) => {
+                    let session_id = plan.session_id.as_deref().unwrap_or_default();
+                    run_session_grant(grant, session_id, uid, confirmed, &policy, now_ms, config)
+                }
+                _ => execute_action(action, confirmed, &policy, config).await,
+            },
+            Mode::PlanOnly => plan_action(action, confirmed, &policy, config).await,
+            Mode::Explain => continue,
         };
         results.push(result);
     }
+    let explanations = (plan.mode == Mode::Explain)
+        .then(|| plan.actions.iter().map(|action| explain_action(action, &policy)).collect());
+
+    let needs_confirmation = results.iter().any(|result| {
+        result.error().map(|e| &e.code) == Some(&llm_os_common::ActionErrorCode::ConfirmationRequired)
+    });
+    let locked_out = matches!(confirmed, ConfirmState::LockedOut { .. });
+    let (challenge, error) = if plan.mode == Mode::Execute && needs_confirmation && !locked_out {
+        match config.challenges.issue(&plan_hash, uid, now_ms) {
+            Ok(challenge) => (Some(challenge), None),
+            Err(err) => (
+                None,
+                Some(RequestError {
+                    code: ErrorCode::ChallengeFailed,
+                    message: format!("could not issue a confirmation challenge: {err}"),
+                }),
+            ),
+        }
+    } else {
+        (None, None)
+    };
 
     let response = ActionPlanResult {
         request_id: plan.request_id.clone(),
-        executed: plan.mode == Mode::Execute,
+        executed: plan.mode == Mode::Execute && !held,
         results,
-        error: None,
+        error,
+        explanations,
+        challenge,
+        approval: None,
+        grants_applied: (!grants_applied.is_empty()).then_some(grants_applied),
     };
     let response_json = serde_json::to_vec(&response)?;
     stream.write_all(&response_json).await?;
     stream.shutdown().await?;
 
-    let now_ms = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis() as u64;
-    audit::append_record(audit_path, now_ms, peer, &plan, &response).await?;
+    audit::append_record(
+        &config.audit_path,
+        now_ms,
+        peer,
+        policy.active_profile.as_deref(),
+        attempt,
+        &plan,
+        &response,
+    )
+    .await?;
 
     Ok(())
 }
 
-fn peer_credentials(stream: &UnixStream) -> Option<audit::PeerCredentials> {
-    let fd = stream.as_raw_fd();
-
-    let mut ucred: libc::ucred = unsafe { std::mem::zeroed() };
-    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
-    let rc = unsafe {
-        libc::getsockopt(
-            fd,
-            libc::SOL_SOCKET,
-            libc::SO_PEERCRED,
-            (&mut ucred as *mut libc::ucred).cast(),
-            &mut len,
-        )
-    };
-    if rc != 0 {
-        return None;
-    }
-    if len as usize != std::mem::size_of::<libc::ucred>() {
-        return None;
-    }
-
-    Some(audit::PeerCredentials {
-        pid: ucred.pid,
-        uid: ucred.uid,
-        gid: ucred.gid,
-    })
-}
-
 async fn write_request_error(
     stream: &mut UnixStream,
     request_id: &str,
@@ -192,6 +292,10 @@ async fn write_request_error(
             code,
             message: message.to_string(),
         }),
+        explanations: None,
+        challenge: None,
+        approval: None,
+        grants_applied: None,
     };
     let response_json = serde_json::to_vec(&response)?;
     stream.write_all(&response_json).await?;
@@ -199,422 +303,1040 @@ async fn write_request_error(
     Ok(())
 }
 
+// approval actions are sent alone and never parked themselves.
+fn needs_approval(plan: &llm_os_common::ActionPlan, policy: &policy::Policy) -> bool {
+    plan.actions.iter().any(|action| {
+        !matches!(action, Action::Approval(_)) && policy.action_verdict(action).decision() == policy::Decision::Approve
+    })
+}
+
+// parks the whole plan instead of running any of it; the response explains which rules asked for approval.
+// only plans in which every action asks for approval are parked, so an approval decides all of it and
+// nothing rides along that the approver did not have to look at.
+async fn park_plan(
+    stream: &mut UnixStream,
+    plan: &llm_os_common::ActionPlan,
+    peer: Option<audit::PeerCredentials>,
+    identity: Option<policy::Peer>,
+    policy: &policy::Policy,
+    now_ms: u64,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let ungated: Vec<String> = plan
+        .actions
+        .iter()
+        .enumerate()
+        .filter(|(_, action)| policy.action_verdict(action).decision() != policy::Decision::Approve)
+        .map(|(index, _)| index.to_string())
+        .collect();
+    if !ungated.is_empty() {
+        let message = format!(
+            "actions {} do not need approval; send them in a plan of their own",
+            ungated.join(", ")
+        );
+        let _ = write_request_error(stream, &plan.request_id, ErrorCode::MixedApprovalPlan, &message).await;
+        return Ok(());
+    }
+
+    let status = match config.approvals.park(plan, peer, identity, now_ms) {
+        Ok(status) => status,
+        Err(err) => {
+            let (code, message) = match err {
+                approvals::ParkError::Duplicate => (
+                    ErrorCode::DuplicateRequestId,
+                    "request_id is already in the approval queue; use a new request_id",
+                ),
+                approvals::ParkError::Full => (ErrorCode::ApprovalQueueFull, "approval queue is full"),
+            };
+            let _ = write_request_error(stream, &plan.request_id, code, message).await;
+            return Ok(());
+        }
+    };
+
+    let response = ActionPlanResult {
+        request_id: plan.request_id.clone(),
+        executed: false,
+        results: vec![],
+        error: None,
+        explanations: Some(plan.actions.iter().map(|action| explain_action(action, policy)).collect()),
+        challenge: None,
+        approval: Some(status),
+        grants_applied: None,
+    };
+    let response_json = serde_json::to_vec(&response)?;
+    stream.write_all(&response_json).await?;
+    stream.shutdown().await?;
+
+    audit::append_record(
+        &config.audit_path,
+        now_ms,
+        peer,
+        policy.active_profile.as_deref(),
+        None,
+        plan,
+        &response,
+    )
+    .await
+}
+
+async fn audit_expired_approvals(config: &Config, now_ms: u64) -> anyhow::Result<()> {
+    for entry in config.approvals.expire(now_ms) {
+        let response = ActionPlanResult {
+            request_id: entry.plan.request_id.clone(),
+            executed: false,
+            results: vec![],
+            error: None,
+            explanations: None,
+            challenge: None,
+            approval: Some(entry.status),
+            grants_applied: None,
+        };
+        audit::append_record(&config.audit_path, now_ms, entry.peer, None, None, &entry.plan, &response).await?;
+    }
+    Ok(())
+}
+
+fn approval_failed(message: String) -> ActionResult {
+    ActionResult::Approval(llm_os_common::ApprovalResult {
+        ok: false,
+        entries: vec![],
+        error: Some(llm_os_common::ActionError {
+            code: llm_os_common::ActionErrorCode::ApprovalFailed,
+            message,
+            policy: None,
+        }),
+    })
+}
+
+async fn run_approval(
+    approval: &llm_os_common::ApprovalAction,
+    uid: Option<u32>,
+    confirmed: ConfirmState,
+    policy: &policy::Policy,
+    now_ms: u64,
+    config: &Config,
+) -> anyhow::Result<ActionResult> {
+    if let Some(error) = policy_error(&policy.approval_verdict(approval.op), policy, "approval", confirmed) {
+        return Ok(ActionResult::Approval(llm_os_common::ApprovalResult {
+            ok: false,
+            entries: vec![],
+            error: Some(error),
+        }));
+    }
+    let request_id = approval.request_id.as_deref().unwrap_or_default();
+    let note = approval.note.as_deref();
+    // a peer the policy would let approve reviews every entry; anyone else only follows their own.
+    let decider = policy.approval_verdict(llm_os_common::ApprovalOp::Approve).decision() != policy::Decision::Deny;
+    let mut entries = match approval.op {
+        llm_os_common::ApprovalOp::List => config.approvals.list(uid, decider),
+        llm_os_common::ApprovalOp::Show => match config.approvals.show(request_id, uid, decider) {
+            Some(status) => vec![status],
+            None => return Ok(approval_failed(format!("no parked plan with request_id {request_id}"))),
+        },
+        llm_os_common::ApprovalOp::Reject => match config.approvals.decide(request_id, false, uid, note, now_ms) {
+            Ok(entry) => vec![entry.status],
+            Err(err) => return Ok(approval_failed(err)),
+        },
+        llm_os_common::ApprovalOp::Approve => match config.approvals.decide(request_id, true, uid, note, now_ms) {
+            Ok(entry) => vec![run_approved(entry, now_ms, config).await?],
+            Err(err) => return Ok(approval_failed(err)),
+        },
+    };
+    // other peers see entries with the same redaction the audit log applies.
+    for entry in entries.iter_mut().filter(|e| uid.is_none() || e.requested_by != uid) {
+        audit::redact_approval(entry);
+    }
+    Ok(ActionResult::Approval(llm_os_common::ApprovalResult {
+        ok: true,
+        entries,
+        error: None,
+    }))
+}
+
+// runs an approved plan under the requester's profile, as the requester would have, and audits it
+// under the requester's credentials. every action asked for approval when the plan was parked;
+// approval still covers only actions that ask for it now.
+async fn run_approved(entry: approvals::Entry, now_ms: u64, config: &Config) -> anyhow::Result<llm_os_common::ApprovalStatus> {
+    let mut status = entry.status.clone();
+    let (profile, result) = match config.policy.for_peer(entry.identity.as_ref()) {
+        Ok(policy) => {
+            let mut results = Vec::with_capacity(entry.plan.actions.len());
+            for action in &entry.plan.actions {
+                let confirmed = match policy.action_verdict(action).decision() {
+                    policy::Decision::Approve => ConfirmState::Approved,
+                    _ => ConfirmState::Unconfirmed,
+                };
+                results.push(execute_action(action, confirmed, &policy, config).await);
+            }
+            let result = ActionPlanResult {
+                request_id: entry.plan.request_id.clone(),
+                executed: true,
+                results,
+                error: None,
+                explanations: None,
+                challenge: None,
+                approval: Some(entry.status.clone()),
+                grants_applied: None,
+            };
+            (policy.active_profile, result)
+        }
+        Err(err) => {
+            let result = ActionPlanResult {
+                request_id: entry.plan.request_id.clone(),
+                executed: false,
+                results: vec![],
+                error: Some(RequestError {
+                    code: ErrorCode::NoMatchingProfile,
+                    message: format!("policy profile: {err}"),
+                }),
+                explanations: None,
+                challenge: None,
+                approval: Some(entry.status.clone()),
+                grants_applied: None,
+            };
+            (None, result)
+        }
+    };
+    config.approvals.record_result(&status.request_id, &result);
+    audit::append_record(
+        &config.audit_path,
+        now_ms,
+        entry.peer,
+        profile.as_deref(),
+        None,
+        &entry.plan,
+        &result,
+    )
+    .await?;
+    status.result = Some(Box::new(result));
+    Ok(status)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfirmState {
+    Confirmed,
+    Unconfirmed,
+    LockedOut { until_ms: u64 },
+    // a second person approved the parked plan, and this action is one that asked for approval.
+    Approved,
+    // a session grant covers this one action in place of confirmation.
+    Granted,
+}
+
+// a session grant applies only where the action would otherwise wait for confirmation.
+fn granted(
+    plan: &llm_os_common::ActionPlan,
+    action: &Action,
+    uid: Option<u32>,
+    confirmed: ConfirmState,
+    policy: &policy::Policy,
+    now_ms: u64,
+    config: &Config,
+) -> Option<String> {
+    if !matches!(confirmed, ConfirmState::Unconfirmed | ConfirmState::LockedOut { .. }) || plan.mode == Mode::Explain {
+        return None;
+    }
+    let session_id = plan.session_id.as_deref()?;
+    if policy.action_verdict(action).decision() != policy::Decision::Confirm {
+        return None;
+    }
+    config.grants.covering(session_id, uid, action, policy, now_ms)
+}
+
+fn session_grant_failed(message: String) -> ActionResult {
+    ActionResult::SessionGrant(llm_os_common::SessionGrantResult {
+        ok: false,
+        grants: vec![],
+        error: Some(llm_os_common::ActionError {
+            code: llm_os_common::ActionErrorCode::GrantFailed,
+            message,
+            policy: None,
+        }),
+    })
+}
+
+fn run_session_grant(
+    grant: &llm_os_common::SessionGrantAction,
+    session_id: &str,
+    uid: Option<u32>,
+    confirmed: ConfirmState,
+    policy: &policy::Policy,
+    now_ms: u64,
+    config: &Config,
+) -> ActionResult {
+    if let Some(error) = policy_error(&policy.session_grant_verdict(grant.op), policy, "session_grant", confirmed) {
+        return ActionResult::SessionGrant(llm_os_common::SessionGrantResult {
+            ok: false,
+            grants: vec![],
+            error: Some(error),
+        });
+    }
+    let grants = match grant.op {
+        llm_os_common::SessionGrantOp::List => config.grants.list(session_id, uid, now_ms),
+        llm_os_common::SessionGrantOp::Grant => {
+            let (scope, ttl_sec) = match (&grant.scope, grant.ttl_sec) {
+                (Some(scope), Some(ttl_sec)) => (scope, ttl_sec),
+                _ => return session_grant_failed("grant needs scope and ttl_sec".to_string()),
+            };
+            match config.grants.grant(session_id, uid, scope, ttl_sec * 1000, grant.note.as_deref(), now_ms) {
+                Ok(created) => vec![created],
+                Err(err) => return session_grant_failed(err),
+            }
+        }
+        llm_os_common::SessionGrantOp::Revoke => {
+            let grant_id = grant.grant_id.as_deref().unwrap_or_default();
+            match config.grants.revoke(session_id, uid, grant_id, now_ms) {
+                Ok(revoked) => vec![revoked],
+                Err(err) => return session_grant_failed(err),
+            }
+        }
+    };
+    ActionResult::SessionGrant(llm_os_common::SessionGrantResult {
+        ok: true,
+        grants,
+        error: None,
+    })
+}
+
+// a plan is confirmed only by the operator token together with an unused challenge nonce issued
+// for this exact plan and peer; presenting a nonce consumes it whether or not the rest matches.
+// every attempt that presents a nonce is returned as an audit outcome, and failures count toward
+// the peer uid's lockout, during which nothing is checked at all.
+fn check_confirmation(
+    plan: &llm_os_common::ActionPlan,
+    plan_hash: &str,
+    uid: Option<u32>,
+    now_ms: u64,
+    config: &Config,
+) -> (ConfirmState, Option<&'static str>) {
+    let locked = config.lockout.locked_until(uid, now_ms);
+    let unconfirmed = |locked: Option<u64>| match locked {
+        Some(until_ms) => ConfirmState::LockedOut { until_ms },
+        None => ConfirmState::Unconfirmed,
+    };
+    let (token, nonce) = match &plan.confirmation {
+        Some(llm_os_common::Confirmation {
+            token,
+            nonce: Some(nonce),
+        }) => (token, nonce),
+        _ => return (unconfirmed(locked), None),
+    };
+    let redeemed = config.challenges.redeem(nonce, plan_hash, uid, now_ms);
+    if locked.is_some() {
+        return (unconfirmed(locked), Some("locked_out"));
+    }
+    let failure = if !redeemed {
+        "bad_nonce"
+    } else if !policy::confirmation_is_valid(Some(token), &config.confirm_token) {
+        "bad_token"
+    } else {
+        config.lockout.succeed(uid);
+        return (ConfirmState::Confirmed, Some("confirmed"));
+    };
+    (unconfirmed(config.lockout.fail(uid, now_ms)), Some(failure))
+}
+
+fn policy_error(
+    verdict: &policy::Verdict,
+    policy: &policy::Policy,
+    subject: &str,
+    confirmed: ConfirmState,
+) -> Option<llm_os_common::ActionError> {
+    let (code, message, guidance) = match verdict.decision() {
+        policy::Decision::Allow => return None,
+        policy::Decision::Confirm => match confirmed {
+            ConfirmState::Confirmed | ConfirmState::Approved | ConfirmState::Granted => return None,
+            ConfirmState::Unconfirmed => (
+                llm_os_common::ActionErrorCode::ConfirmationRequired,
+                "confirmation required".to_string(),
+                "resend the same plan with the confirmation token and the challenge nonce to proceed",
+            ),
+            ConfirmState::LockedOut { until_ms } => (
+                llm_os_common::ActionErrorCode::ConfirmationRequired,
+                format!("confirmation locked out until {until_ms} after repeated failures"),
+                "wait for the lockout to expire, then confirm with the correct token",
+            ),
+        },
+        policy::Decision::Approve => match confirmed {
+            ConfirmState::Approved => return None,
+            _ => (
+                llm_os_common::ActionErrorCode::ApprovalRequired,
+                "approval required".to_string(),
+                "send the plan in execute mode to park it until a second person approves it",
+            ),
+        },
+        policy::Decision::Deny => (
+            llm_os_common::ActionErrorCode::PolicyDenied,
+            format!("{subject} denied by policy"),
+            "change the plan or ask an operator to adjust the policy",
+        ),
+    };
+    let detail = verdict.decisive().map(|step| {
+        let mut detail = policy_detail(step, policy);
+        detail.hint = format!("{}; {guidance}", detail.hint);
+        detail
+    });
+    Some(llm_os_common::ActionError {
+        code,
+        message,
+        policy: detail,
+    })
+}
+
+fn policy_detail(step: &policy::Step, policy: &policy::Policy) -> llm_os_common::PolicyDetail {
+    llm_os_common::PolicyDetail {
+        rule_id: step.rule_id.clone(),
+        source: policy.source_label(),
+        decision: step.decision.into(),
+        hint: step.hint.clone(),
+    }
+}
+
+// the policy check and the manager's steps for this scope, shared by execute and plan_only.
+fn update_steps<'a>(
+    upd: &llm_os_common::UpdateSystemAction,
+    confirmed: ConfirmState,
+    policy: &policy::Policy,
+    config: &'a Config,
+) -> Result<(&'a dyn actions::packages::PackageBackend, Vec<Vec<String>>), llm_os_common::ActionError> {
+    let scope = upd.scope.unwrap_or_default();
+    if let Some(error) = policy_error(&policy.update_system_verdict(scope), policy, "update_system", confirmed) {
+        return Err(error);
+    }
+    let backend = config.packages.get(&upd.manager).ok_or_else(|| unsupported_manager("update_system"))?;
+    let steps = backend.update_steps(scope).ok_or_else(|| llm_os_common::ActionError {
+        code: llm_os_common::ActionErrorCode::PolicyDenied,
+        message: format!(
+            "update_system {} is not supported for this manager",
+            policy::update_scope_name(scope)
+        ),
+        policy: None,
+    })?;
+    Ok((backend, steps))
+}
+
+fn update_failed(error: llm_os_common::ActionError) -> ActionResult {
+    ActionResult::UpdateSystem(llm_os_common::UpdateSystemResult {
+        ok: false,
+        steps: vec![],
+        error: Some(error),
+    })
+}
+
+// how an observe action runs once policy allows it.
+enum ObserveProgram {
+    // ps and top, answered from /proc inside the daemon.
+    Native,
+    // resolved through the exec search path like an exec argv[0].
+    Builtin(&'static str),
+    Registered(policy::RegisteredTool),
+}
+
+impl ObserveProgram {
+    // the command line that runs; empty for the native tools.
+    fn argv(&self, obs: &llm_os_common::ObserveAction) -> Vec<String> {
+        let mut argv = match self {
+            ObserveProgram::Native => return vec![],
+            ObserveProgram::Builtin(base) => vec![base.to_string()],
+            ObserveProgram::Registered(tool) => {
+                let mut argv = vec![tool.path.to_string_lossy().to_string()];
+                argv.extend(tool.args.iter().cloned());
+                argv
+            }
+        };
+        argv.extend(obs.args.iter().cloned());
+        argv
+    }
+
+    // the action's timeout within the tool's configured maximum, or the tool's default.
+    fn timeout_sec(
+        &self,
+        obs: &llm_os_common::ObserveAction,
+        policy: &policy::Policy,
+    ) -> Result<Option<u64>, llm_os_common::ActionError> {
+        if let ObserveProgram::Native = self {
+            return Ok(None);
+        }
+        match policy.observe_tools.timeout(policy::observe_name(obs), obs.timeout_sec) {
+            Ok(timeout_sec) => Ok(Some(timeout_sec)),
+            Err(message) => Err(llm_os_common::ActionError {
+                code: llm_os_common::ActionErrorCode::ObserveFailed,
+                message,
+                policy: None,
+            }),
+        }
+    }
+}
+
+fn observe_program(
+    obs: &llm_os_common::ObserveAction,
+    policy: &policy::Policy,
+    confirmed: ConfirmState,
+) -> Result<ObserveProgram, llm_os_common::ActionError> {
+    if let Some(error) = policy_error(&policy.observe_verdict(obs), policy, "observe", confirmed) {
+        return Err(error);
+    }
+    let program = match obs.tool {
+        llm_os_common::ObserveTool::Ps | llm_os_common::ObserveTool::Top => ObserveProgram::Native,
+        llm_os_common::ObserveTool::Journalctl => ObserveProgram::Builtin("journalctl"),
+        llm_os_common::ObserveTool::Perf => ObserveProgram::Builtin("perf"),
+        llm_os_common::ObserveTool::Bpftrace => ObserveProgram::Builtin("bpftrace"),
+        // the verdict already denies names that are not registered.
+        llm_os_common::ObserveTool::Other => match policy.observe_tools.get(policy::observe_name(obs)) {
+            Some(tool) => ObserveProgram::Registered(tool.clone()),
+            None => {
+                return Err(llm_os_common::ActionError {
+                    code: llm_os_common::ActionErrorCode::PolicyDenied,
+                    message: "observe tool is not registered".to_string(),
+                    policy: None,
+                });
+            }
+        },
+    };
+    Ok(program)
+}
+
+fn observe_arg_classes(obs: &llm_os_common::ObserveAction, policy: &policy::Policy) -> Vec<llm_os_common::ObserveArgClass> {
+    obs.args
+        .iter()
+        .zip(policy.observe_arg_steps(obs))
+        .map(|(arg, step)| llm_os_common::ObserveArgClass {
+            arg: arg.clone(),
+            decision: step.decision.into(),
+            rule_id: step.rule_id,
+        })
+        .collect()
+}
+
+fn observe_failed(
+    obs: &llm_os_common::ObserveAction,
+    policy: &policy::Policy,
+    argv: Vec<String>,
+    error: llm_os_common::ActionError,
+) -> ActionResult {
+    ActionResult::Observe(llm_os_common::ObserveResult {
+        ok: false,
+        argv,
+        exit_code: None,
+        stdout: "".to_string(),
+        stdout_truncated: false,
+        stderr: "".to_string(),
+        stderr_truncated: false,
+        arg_classes: observe_arg_classes(obs, policy),
+        processes: None,
+        timeout_sec: None,
+        error: Some(error),
+    })
+}
+
+fn log_query_denied(q: &llm_os_common::LogQueryAction, error: llm_os_common::ActionError) -> ActionResult {
+    ActionResult::LogQuery(llm_os_common::LogQueryResult {
+        ok: false,
+        argv: actions::journal::argv(q),
+        entries: vec![],
+        error: Some(error),
+    })
+}
+
+fn cgroup_denied(cg: &llm_os_common::CgroupApplyAction, error: llm_os_common::ActionError) -> ActionResult {
+    match actions::cgroup::planned(cg) {
+        ActionResult::CgroupApply(mut result) => {
+            result.ok = false;
+            result.files.clear();
+            result.error = Some(error);
+            ActionResult::CgroupApply(result)
+        }
+        other => other,
+    }
+}
+
+fn cgroup_inspect_denied(error: llm_os_common::ActionError) -> ActionResult {
+    ActionResult::CgroupInspect(llm_os_common::CgroupInspectResult {
+        ok: false,
+        cgroup: String::new(),
+        memory_current: None,
+        cpu_stat: Default::default(),
+        pressure: vec![],
+        error: Some(error),
+    })
+}
+
+fn firmware_failed(error: llm_os_common::ActionError) -> ActionResult {
+    ActionResult::FirmwareOp(llm_os_common::FirmwareOpResult {
+        ok: false,
+        argv: vec![],
+        inventory: None,
+        uefi_var: None,
+        error: Some(error),
+    })
+}
+
+fn unsupported_manager(subject: &str) -> llm_os_common::ActionError {
+    llm_os_common::ActionError {
+        code: llm_os_common::ActionErrorCode::PolicyDenied,
+        message: format!("{subject} manager not supported"),
+        policy: None,
+    }
+}
+
+fn unresolved_exec(err: &str) -> ActionResult {
+    ActionResult::Exec(llm_os_common::ExecResult {
+        ok: false,
+        resolved_path: None,
+        exit_code: None,
+        stdout: "".to_string(),
+        stdout_truncated: false,
+        stderr: "".to_string(),
+        stderr_truncated: false,
+        killed: false,
+        killed_processes: 0,
+        error: Some(llm_os_common::ActionError {
+            code: llm_os_common::ActionErrorCode::ExecFailed,
+            message: format!("argv[0] could not be resolved: {err}"),
+            policy: None,
+        }),
+    })
+}
+
+fn unresolved_read(err: &str) -> ActionResult {
+    ActionResult::ReadFile(llm_os_common::ReadFileResult {
+        ok: false,
+        resolved_path: None,
+        content_base64: None,
+        truncated: false,
+        error: Some(llm_os_common::ActionError {
+            code: llm_os_common::ActionErrorCode::ReadFailed,
+            message: format!("path could not be resolved: {err}"),
+            policy: None,
+        }),
+    })
+}
+
+fn unresolved_write(err: &str) -> ActionResult {
+    ActionResult::WriteFile(llm_os_common::WriteFileResult {
+        ok: false,
+        resolved_path: None,
+        artifacts: vec![],
+        error: Some(llm_os_common::ActionError {
+            code: llm_os_common::ActionErrorCode::WriteFailed,
+            message: format!("path could not be resolved: {err}"),
+            policy: None,
+        }),
+    })
+}
+
+// the full policy trace for one action without executing it or consulting the confirmation token.
+fn explain_action(action: &Action, policy: &policy::Policy) -> llm_os_common::ActionExplanation {
+    let verdict = policy.action_verdict(action);
+    llm_os_common::ActionExplanation {
+        action: policy::action_name(action).to_string(),
+        decision: verdict.decision().into(),
+        trace: verdict.steps.iter().map(|step| policy_detail(step, policy)).collect(),
+    }
+}
+
 async fn execute_action(
     action: &Action,
-    confirmation_token: Option<&str>,
-    confirm_token: &str,
+    confirmed: ConfirmState,
+    policy: &policy::Policy,
+    config: &Config,
 ) -> ActionResult {
     match action {
         Action::Exec(exec) => {
-            if policy::is_exec_denied(exec) {
-                return ActionResult::Exec(llm_os_common::ExecResult {
-                    ok: false,
-                    exit_code: None,
-                    stdout: "".to_string(),
-                    stdout_truncated: false,
-                    stderr: "".to_string(),
-                    stderr_truncated: false,
-                    error: Some(llm_os_common::ActionError {
-                        code: llm_os_common::ActionErrorCode::PolicyDenied,
-                        message: "exec denied by policy".to_string(),
-                    }),
-                });
-            }
-            if policy::exec_requires_confirmation(exec)
-                && !policy::confirmation_is_valid(confirmation_token, confirm_token)
-            {
+            let program = match policy.resolve_exec(exec) {
+                Ok(program) => program,
+                Err(err) => return unresolved_exec(&err),
+            };
+            let resolved_path = Some(program.path.to_string_lossy().to_string());
+            let verdict = policy.exec_verdict(exec, &program);
+            if let Some(error) = policy_error(&verdict, policy, "exec", confirmed) {
                 return ActionResult::Exec(llm_os_common::ExecResult {
                     ok: false,
+                    resolved_path,
                     exit_code: None,
                     stdout: "".to_string(),
                     stdout_truncated: false,
                     stderr: "".to_string(),
                     stderr_truncated: false,
-                    error: Some(llm_os_common::ActionError {
-                        code: llm_os_common::ActionErrorCode::ConfirmationRequired,
-                        message: "confirmation required".to_string(),
-                    }),
+                    killed: false,
+                    killed_processes: 0,
+                    error: Some(error),
                 });
             }
-            actions::exec::run(exec).await
+            actions::exec::run(exec, &program.path, &policy.exec.search_path).await
         }
         Action::ReadFile(read) => {
-            if policy::path_requires_confirmation(&read.path)
-                && !policy::confirmation_is_valid(confirmation_token, confirm_token)
-            {
+            let target = match actions::files::resolve(&read.path) {
+                Ok(target) => target,
+                Err(err) => return unresolved_read(&err),
+            };
+            let resolved_path = Some(target.path.to_string_lossy().to_string());
+            let verdict = policy.read_file_verdict(&read.path, &target.path);
+            if let Some(error) = policy_error(&verdict, policy, "read_file", confirmed) {
                 return ActionResult::ReadFile(llm_os_common::ReadFileResult {
                     ok: false,
+                    resolved_path,
                     content_base64: None,
                     truncated: false,
-                    error: Some(llm_os_common::ActionError {
-                        code: llm_os_common::ActionErrorCode::ConfirmationRequired,
-                        message: "confirmation required".to_string(),
-                    }),
+                    error: Some(error),
                 });
             }
-            actions::files::read(read).await
+            actions::files::read(read, target).await
         }
         Action::WriteFile(write) => {
-            if policy::path_requires_confirmation(&write.path)
-                && !policy::confirmation_is_valid(confirmation_token, confirm_token)
-            {
+            let target = match actions::files::resolve(&write.path) {
+                Ok(target) => target,
+                Err(err) => return unresolved_write(&err),
+            };
+            let resolved_path = Some(target.path.to_string_lossy().to_string());
+            let verdict = policy.write_file_verdict(&write.path, &target.path);
+            if let Some(error) = policy_error(&verdict, policy, "write_file", confirmed) {
                 return ActionResult::WriteFile(llm_os_common::WriteFileResult {
                     ok: false,
+                    resolved_path,
                     artifacts: vec![],
-                    error: Some(llm_os_common::ActionError {
-                        code: llm_os_common::ActionErrorCode::ConfirmationRequired,
-                        message: "confirmation required".to_string(),
-                    }),
+                    error: Some(error),
                 });
             }
-            actions::files::write(write).await
+            actions::files::write(write, target).await
         }
-        Action::ServiceControl(_svc) => ActionResult::ServiceControl(llm_os_common::ServiceControlResult {
-            ok: false,
-            argv: vec![],
-            error: Some(llm_os_common::ActionError {
-                code: llm_os_common::ActionErrorCode::PolicyDenied,
-                message: "service_control is not supported in execute mode".to_string(),
-            }),
-        }),
-        Action::InstallPackages(_pkgs) => ActionResult::InstallPackages(llm_os_common::InstallPackagesResult {
-            ok: false,
-            argv: vec![],
-            error: Some(llm_os_common::ActionError {
-                code: llm_os_common::ActionErrorCode::PolicyDenied,
-                message: "install_packages is not supported in execute mode".to_string(),
-            }),
-        }),
-        Action::RemovePackages(_pkgs) => ActionResult::RemovePackages(llm_os_common::RemovePackagesResult {
-            ok: false,
-            argv: vec![],
-            error: Some(llm_os_common::ActionError {
-                code: llm_os_common::ActionErrorCode::PolicyDenied,
-                message: "remove_packages is not supported in execute mode".to_string(),
-            }),
-        }),
-        Action::UpdateSystem(_upd) => ActionResult::UpdateSystem(llm_os_common::UpdateSystemResult {
-            ok: false,
-            argv: vec![],
-            error: Some(llm_os_common::ActionError {
-                code: llm_os_common::ActionErrorCode::PolicyDenied,
-                message: "update_system is not supported in execute mode".to_string(),
-            }),
-        }),
-        Action::Observe(obs) => {
-            let base = match obs.tool {
-                llm_os_common::ObserveTool::Ps => "ps",
-                llm_os_common::ObserveTool::Top => "top",
-                llm_os_common::ObserveTool::Journalctl => "journalctl",
-                llm_os_common::ObserveTool::Perf => "perf",
-                llm_os_common::ObserveTool::Bpftrace => "bpftrace",
-                llm_os_common::ObserveTool::Other => {
-                    return ActionResult::Observe(llm_os_common::ObserveResult {
+        Action::ServiceControl(svc) => {
+            let verdict = policy.service_control_verdict(&svc.action, &svc.unit);
+            if let Some(error) = policy_error(&verdict, policy, "service_control", confirmed) {
+                return ActionResult::ServiceControl(llm_os_common::ServiceControlResult {
+                    ok: false,
+                    argv: vec![],
+                    status: None,
+                    error: Some(error),
+                });
+            }
+            actions::service::run(config.services.as_ref(), &svc.action, &svc.unit).await
+        }
+        Action::InstallPackages(pkgs) => {
+            let verdict = policy.install_packages_verdict(&pkgs.packages);
+            if let Some(error) = policy_error(&verdict, policy, "install_packages", confirmed) {
+                return ActionResult::InstallPackages(llm_os_common::InstallPackagesResult {
+                    ok: false,
+                    argv: vec![],
+                    packages: vec![],
+                    error: Some(error),
+                });
+            }
+            let backend = match config.packages.get(&pkgs.manager) {
+                Some(backend) => backend,
+                None => {
+                    return ActionResult::InstallPackages(llm_os_common::InstallPackagesResult {
                         ok: false,
                         argv: vec![],
-                        exit_code: None,
-                        stdout: "".to_string(),
-                        stdout_truncated: false,
-                        stderr: "".to_string(),
-                        stderr_truncated: false,
-                        error: Some(llm_os_common::ActionError {
-                            code: llm_os_common::ActionErrorCode::PolicyDenied,
-                            message: "observe tool not supported".to_string(),
-                        }),
+                        packages: vec![],
+                        error: Some(unsupported_manager("install_packages")),
                     });
                 }
             };
-
-            let mut argv = Vec::new();
-            argv.push(base.to_string());
-            argv.extend(obs.args.iter().cloned());
-
-            let exec = llm_os_common::ExecAction {
-                argv: argv.clone(),
-                cwd: None,
-                env: None,
-                timeout_sec: 5,
-                as_root: false,
-                reason: obs.reason.clone(),
-                danger: obs.danger.clone(),
-                recovery: obs.recovery.clone(),
-            };
-
-            match actions::exec::run(&exec).await {
-                ActionResult::Exec(r) => ActionResult::Observe(llm_os_common::ObserveResult {
-                    ok: r.ok,
-                    argv,
-                    exit_code: r.exit_code,
+            let applied = actions::packages::apply(backend, actions::packages::PackageOp::Install, &pkgs.packages).await;
+            ActionResult::InstallPackages(llm_os_common::InstallPackagesResult {
+                ok: applied.error.is_none(),
+                argv: applied.argv,
+                packages: applied.packages,
+                error: applied.error,
+            })
+        }
+        Action::RemovePackages(pkgs) => {
+            let verdict = policy.remove_packages_verdict(&pkgs.packages);
+            if let Some(error) = policy_error(&verdict, policy, "remove_packages", confirmed) {
+                return ActionResult::RemovePackages(llm_os_common::RemovePackagesResult {
+                    ok: false,
+                    argv: vec![],
+                    packages: vec![],
+                    error: Some(error),
+                });
+            }
+            let backend = match config.packages.get(&pkgs.manager) {
+                Some(backend) => backend,
+                None => {
+                    return ActionResult::RemovePackages(llm_os_common::RemovePackagesResult {
+                        ok: false,
+                        argv: vec![],
+                        packages: vec![],
+                        error: Some(unsupported_manager("remove_packages")),
+                    });
+                }
+            };
+            let applied = actions::packages::apply(backend, actions::packages::PackageOp::Remove, &pkgs.packages).await;
+            ActionResult::RemovePackages(llm_os_common::RemovePackagesResult {
+                ok: applied.error.is_none(),
+                argv: applied.argv,
+                packages: applied.packages,
+                error: applied.error,
+            })
+        }
+        Action::UpdateSystem(upd) => {
+            let (backend, steps) = match update_steps(upd, confirmed, policy, config) {
+                Ok(planned) => planned,
+                Err(error) => return update_failed(error),
+            };
+            let (steps, error) = actions::packages::update(backend, steps).await;
+            ActionResult::UpdateSystem(llm_os_common::UpdateSystemResult {
+                ok: error.is_none(),
+                steps,
+                error,
+            })
+        }
+        Action::Observe(obs) => {
+            let program = match observe_program(obs, policy, confirmed) {
+                Ok(program) => program,
+                Err(error) => return observe_failed(obs, policy, vec![], error),
+            };
+            let argv = program.argv(obs);
+            let timeout_sec = match program.timeout_sec(obs, policy) {
+                Ok(timeout_sec) => timeout_sec,
+                Err(error) => return observe_failed(obs, policy, argv, error),
+            };
+            let exec = llm_os_common::ExecAction {
+                argv: argv.clone(),
+                cwd: None,
+                env: None,
+                timeout_sec: timeout_sec.unwrap_or_default(),
+                as_root: false,
+                reason: obs.reason.clone(),
+                danger: obs.danger.clone(),
+                recovery: obs.recovery.clone(),
+            };
+
+            let result = match &program {
+                ObserveProgram::Native => return actions::procs::observe(&config.proc_root, obs),
+                ObserveProgram::Builtin(base) => match policy.exec.resolve(base, None) {
+                    Ok(resolved) => actions::exec::run(&exec, &resolved.path, &policy.exec.search_path).await,
+                    Err(err) => {
+                        return observe_failed(
+                            obs,
+                            policy,
+                            argv,
+                            llm_os_common::ActionError {
+                                code: llm_os_common::ActionErrorCode::ExecFailed,
+                                message: format!("observe tool could not be resolved: {err}"),
+                                policy: None,
+                            },
+                        );
+                    }
+                },
+                ObserveProgram::Registered(tool) => {
+                    actions::exec::run_capped(&exec, &tool.path, &policy.exec.search_path, tool.max_output_bytes).await
+                }
+            };
+
+            match result {
+                ActionResult::Exec(r) => ActionResult::Observe(llm_os_common::ObserveResult {
+                    ok: r.ok,
+                    argv,
+                    exit_code: r.exit_code,
                     stdout: r.stdout,
                     stdout_truncated: r.stdout_truncated,
                     stderr: r.stderr,
                     stderr_truncated: r.stderr_truncated,
+                    arg_classes: observe_arg_classes(obs, policy),
+                    processes: None,
+                    timeout_sec,
                     error: r.error,
                 }),
                 other => other,
             }
         }
-        Action::CgroupApply(_cg) => ActionResult::CgroupApply(llm_os_common::CgroupApplyResult {
-            ok: false,
-            argv: vec![],
-            error: Some(llm_os_common::ActionError {
-                code: llm_os_common::ActionErrorCode::PolicyDenied,
-                message: "cgroup_apply is not supported in execute mode".to_string(),
-            }),
-        }),
-        Action::FirmwareOp(_fw) => ActionResult::FirmwareOp(llm_os_common::FirmwareOpResult {
-            ok: false,
-            argv: vec![],
-            error: Some(llm_os_common::ActionError {
-                code: llm_os_common::ActionErrorCode::PolicyDenied,
-                message: "firmware_op is not supported in execute mode".to_string(),
-            }),
+        Action::LogQuery(q) => {
+            if let Some(error) = policy_error(&policy.log_query_verdict(q), policy, "log_query", confirmed) {
+                return log_query_denied(q, error);
+            }
+            actions::journal::run(&config.journalctl, q).await
+        }
+        Action::CgroupApply(cg) => {
+            if let Some(error) = policy_error(&policy.cgroup_apply_verdict(cg), policy, "cgroup_apply", confirmed) {
+                return cgroup_denied(cg, error);
+            }
+            actions::cgroup::apply(&config.cgroup_root, cg)
+        }
+        Action::CgroupInspect(cg) => {
+            if let Some(error) = policy_error(&policy.cgroup_inspect_verdict(cg), policy, "cgroup_inspect", confirmed) {
+                return cgroup_inspect_denied(error);
+            }
+            actions::cgroup::inspect(&config.cgroup_root, cg)
+        }
+        Action::FirmwareOp(fw) => {
+            if let Some(error) = policy_error(&policy.action_verdict(action), policy, "firmware_op", confirmed) {
+                return firmware_failed(error);
+            }
+            match fw.op {
+                llm_os_common::FirmwareOp::Inventory => actions::firmware::inventory(&config.sysfs_root),
+                llm_os_common::FirmwareOp::UefiVarRead => {
+                    actions::firmware::uefi_var_read(&config.sysfs_root, fw.uefi_var_name.as_deref().unwrap_or(""))
+                }
+                llm_os_common::FirmwareOp::FwupdUpdate => firmware_failed(llm_os_common::ActionError {
+                    code: llm_os_common::ActionErrorCode::PolicyDenied,
+                    message: "firmware_op fwupd_update is not supported in execute mode".to_string(),
+                    policy: None,
+                }),
+            }
+        }
+        Action::Approval(_) => approval_failed("approval actions must be sent alone".to_string()),
+        Action::SessionGrant(_) => session_grant_failed("session_grant actions must be sent alone".to_string()),
+        Action::Ping => ActionResult::Pong(llm_os_common::PongResult {
+            ok: true,
+            observe_tools: policy.observe_tool_listing(),
         }),
-        Action::Ping => ActionResult::Pong(llm_os_common::PongResult { ok: true }),
     }
 }
 
-async fn plan_action(action: &Action, confirmation_token: Option<&str>, confirm_token: &str) -> ActionResult {
+async fn plan_action(
+    action: &Action,
+    confirmed: ConfirmState,
+    policy: &policy::Policy,
+    config: &Config,
+) -> ActionResult {
     match action {
         Action::Exec(exec) => {
-            if policy::is_exec_denied(exec) {
-                return ActionResult::Exec(llm_os_common::ExecResult {
-                    ok: false,
-                    exit_code: None,
-                    stdout: "".to_string(),
-                    stdout_truncated: false,
-                    stderr: "".to_string(),
-                    stderr_truncated: false,
-                    error: Some(llm_os_common::ActionError {
-                        code: llm_os_common::ActionErrorCode::PolicyDenied,
-                        message: "exec denied by policy".to_string(),
-                    }),
-                });
-            }
-            if policy::exec_requires_confirmation(exec)
-                && !policy::confirmation_is_valid(confirmation_token, confirm_token)
-            {
+            let program = match policy.resolve_exec(exec) {
+                Ok(program) => program,
+                Err(err) => return unresolved_exec(&err),
+            };
+            let resolved_path = Some(program.path.to_string_lossy().to_string());
+            let verdict = policy.exec_verdict(exec, &program);
+            if let Some(error) = policy_error(&verdict, policy, "exec", confirmed) {
                 return ActionResult::Exec(llm_os_common::ExecResult {
                     ok: false,
+                    resolved_path,
                     exit_code: None,
                     stdout: "".to_string(),
                     stdout_truncated: false,
                     stderr: "".to_string(),
                     stderr_truncated: false,
-                    error: Some(llm_os_common::ActionError {
-                        code: llm_os_common::ActionErrorCode::ConfirmationRequired,
-                        message: "confirmation required".to_string(),
-                    }),
+                    killed: false,
+                    killed_processes: 0,
+                    error: Some(error),
                 });
             }
             ActionResult::Exec(llm_os_common::ExecResult {
                 ok: true,
+                resolved_path,
                 exit_code: None,
                 stdout: "".to_string(),
                 stdout_truncated: false,
                 stderr: "".to_string(),
                 stderr_truncated: false,
+                killed: false,
+                killed_processes: 0,
                 error: None,
             })
         }
         Action::ReadFile(read) => {
-            if policy::path_requires_confirmation(&read.path)
-                && !policy::confirmation_is_valid(confirmation_token, confirm_token)
-            {
+            let target = match actions::files::resolve(&read.path) {
+                Ok(target) => target,
+                Err(err) => return unresolved_read(&err),
+            };
+            let resolved_path = Some(target.path.to_string_lossy().to_string());
+            let verdict = policy.read_file_verdict(&read.path, &target.path);
+            if let Some(error) = policy_error(&verdict, policy, "read_file", confirmed) {
                 return ActionResult::ReadFile(llm_os_common::ReadFileResult {
                     ok: false,
+                    resolved_path,
                     content_base64: None,
                     truncated: false,
-                    error: Some(llm_os_common::ActionError {
-                        code: llm_os_common::ActionErrorCode::ConfirmationRequired,
-                        message: "confirmation required".to_string(),
-                    }),
+                    error: Some(error),
                 });
             }
             ActionResult::ReadFile(llm_os_common::ReadFileResult {
                 ok: true,
+                resolved_path,
                 content_base64: None,
                 truncated: false,
                 error: None,
             })
         }
         Action::WriteFile(write) => {
-            if policy::path_requires_confirmation(&write.path)
-                && !policy::confirmation_is_valid(confirmation_token, confirm_token)
-            {
+            let target = match actions::files::resolve(&write.path) {
+                Ok(target) => target,
+                Err(err) => return unresolved_write(&err),
+            };
+            let resolved_path = Some(target.path.to_string_lossy().to_string());
+            let verdict = policy.write_file_verdict(&write.path, &target.path);
+            if let Some(error) = policy_error(&verdict, policy, "write_file", confirmed) {
                 return ActionResult::WriteFile(llm_os_common::WriteFileResult {
                     ok: false,
+                    resolved_path,
                     artifacts: vec![],
-                    error: Some(llm_os_common::ActionError {
-                        code: llm_os_common::ActionErrorCode::ConfirmationRequired,
-                        message: "confirmation required".to_string(),
-                    }),
+                    error: Some(error),
                 });
             }
             ActionResult::WriteFile(llm_os_common::WriteFileResult {
                 ok: true,
+                resolved_path,
                 artifacts: vec![],
                 error: None,
             })
         }
         Action::ServiceControl(svc) => {
-            let verb = match svc.action {
-                llm_os_common::ServiceControlVerb::Start => "start",
-                llm_os_common::ServiceControlVerb::Stop => "stop",
-                llm_os_common::ServiceControlVerb::Restart => "restart",
-                llm_os_common::ServiceControlVerb::Enable => "enable",
-                llm_os_common::ServiceControlVerb::Disable => "disable",
-                llm_os_common::ServiceControlVerb::Status => "status",
-            };
+            let verdict = policy.service_control_verdict(&svc.action, &svc.unit);
+            if let Some(error) = policy_error(&verdict, policy, "service_control", confirmed) {
+                return ActionResult::ServiceControl(llm_os_common::ServiceControlResult {
+                    ok: false,
+                    argv: vec![],
+                    status: None,
+                    error: Some(error),
+                });
+            }
             ActionResult::ServiceControl(llm_os_common::ServiceControlResult {
                 ok: true,
-                argv: vec!["systemctl".to_string(), verb.to_string(), svc.unit.clone()],
+                argv: config.services.argv(&svc.action, &svc.unit),
+                status: None,
                 error: None,
             })
         }
         Action::InstallPackages(pkgs) => {
-            let mut argv = Vec::new();
-            match pkgs.manager {
-                llm_os_common::PackageManager::Apt => {
-                    argv.push("apt-get".to_string());
-                    argv.push("install".to_string());
-                    argv.push("-y".to_string());
-                }
-                llm_os_common::PackageManager::Dnf => {
-                    argv.push("dnf".to_string());
-                    argv.push("install".to_string());
-                    argv.push("-y".to_string());
-                }
-                llm_os_common::PackageManager::Pacman => {
-                    argv.push("pacman".to_string());
-                    argv.push("-S".to_string());
-                    argv.push("--noconfirm".to_string());
-                }
-                llm_os_common::PackageManager::Zypper => {
-                    argv.push("zypper".to_string());
-                    argv.push("install".to_string());
-                    argv.push("-y".to_string());
-                }
-                llm_os_common::PackageManager::Brew => {
-                    argv.push("brew".to_string());
-                    argv.push("install".to_string());
-                }
-                llm_os_common::PackageManager::Other => {
-                    return ActionResult::InstallPackages(llm_os_common::InstallPackagesResult {
-                        ok: false,
-                        argv: vec![],
-                        error: Some(llm_os_common::ActionError {
-                            code: llm_os_common::ActionErrorCode::PolicyDenied,
-                            message: "install_packages manager not supported".to_string(),
-                        }),
-                    });
-                }
-            }
-            argv.extend(pkgs.packages.iter().cloned());
-
+            let verdict = policy.install_packages_verdict(&pkgs.packages);
+            let error = policy_error(&verdict, policy, "install_packages", confirmed);
+            let backend = config.packages.get(&pkgs.manager);
+            let error = error.or_else(|| backend.is_none().then(|| unsupported_manager("install_packages")));
             ActionResult::InstallPackages(llm_os_common::InstallPackagesResult {
-                ok: true,
-                argv,
-                error: None,
+                ok: error.is_none(),
+                argv: match (&error, backend) {
+                    (None, Some(backend)) => backend.argv(actions::packages::PackageOp::Install, &pkgs.packages),
+                    _ => vec![],
+                },
+                packages: vec![],
+                error,
             })
         }
         Action::RemovePackages(pkgs) => {
-            let mut argv = Vec::new();
-            match pkgs.manager {
-                llm_os_common::PackageManager::Apt => {
-                    argv.push("apt-get".to_string());
-                    argv.push("remove".to_string());
-                    argv.push("-y".to_string());
-                }
-                llm_os_common::PackageManager::Dnf => {
-                    argv.push("dnf".to_string());
-                    argv.push("remove".to_string());
-                    argv.push("-y".to_string());
-                }
-                llm_os_common::PackageManager::Pacman => {
-                    argv.push("pacman".to_string());
-                    argv.push("-R".to_string());
-                    argv.push("--noconfirm".to_string());
-                }
-                llm_os_common::PackageManager::Zypper => {
-                    argv.push("zypper".to_string());
-                    argv.push("remove".to_string());
-                    argv.push("-y".to_string());
-                }
-                llm_os_common::PackageManager::Brew => {
-                    argv.push("brew".to_string());
-                    argv.push("uninstall".to_string());
-                }
-                llm_os_common::PackageManager::Other => {
-                    return ActionResult::RemovePackages(llm_os_common::RemovePackagesResult {
-                        ok: false,
-                        argv: vec![],
-                        error: Some(llm_os_common::ActionError {
-                            code: llm_os_common::ActionErrorCode::PolicyDenied,
-                            message: "remove_packages manager not supported".to_string(),
-                        }),
-                    });
-                }
-            }
-            argv.extend(pkgs.packages.iter().cloned());
-
+            let verdict = policy.remove_packages_verdict(&pkgs.packages);
+            let error = policy_error(&verdict, policy, "remove_packages", confirmed);
+            let backend = config.packages.get(&pkgs.manager);
+            let error = error.or_else(|| backend.is_none().then(|| unsupported_manager("remove_packages")));
             ActionResult::RemovePackages(llm_os_common::RemovePackagesResult {
-                ok: true,
-                argv,
-                error: None,
+                ok: error.is_none(),
+                argv: match (&error, backend) {
+                    (None, Some(backend)) => backend.argv(actions::packages::PackageOp::Remove, &pkgs.packages),
+                    _ => vec![],
+                },
+                packages: vec![],
+                error,
             })
         }
-        Action::UpdateSystem(upd) => {
-            match upd.manager {
-                llm_os_common::PackageManager::Apt => ActionResult::UpdateSystem(llm_os_common::UpdateSystemResult {
-                    ok: true,
-                    argv: vec![
-                        "apt-get".to_string(),
-                        "update".to_string(),
-                        "&&".to_string(),
-                        "apt-get".to_string(),
-                        "upgrade".to_string(),
-                        "-y".to_string(),
-                    ],
-                    error: None,
-                }),
-                _ => ActionResult::UpdateSystem(llm_os_common::UpdateSystemResult {
-                    ok: false,
-                    argv: vec![],
-                    error: Some(llm_os_common::ActionError {
-                        code: llm_os_common::ActionErrorCode::PolicyDenied,
-                        message: "update_system manager not supported".to_string(),
-                    }),
-                }),
-            }
-        }
+        Action::UpdateSystem(upd) => match update_steps(upd, confirmed, policy, config) {
+            Ok((_, steps)) => ActionResult::UpdateSystem(llm_os_common::UpdateSystemResult {
+                ok: true,
+                steps: actions::packages::planned(steps),
+                error: None,
+            }),
+            Err(error) => update_failed(error),
+        },
         Action::Observe(obs) => {
-            let base = match obs.tool {
-                llm_os_common::ObserveTool::Ps => "ps",
-                llm_os_common::ObserveTool::Top => "top",
-                llm_os_common::ObserveTool::Journalctl => "journalctl",
-                llm_os_common::ObserveTool::Perf => "perf",
-                llm_os_common::ObserveTool::Bpftrace => "bpftrace",
-                llm_os_common::ObserveTool::Other => {
-                    return ActionResult::Observe(llm_os_common::ObserveResult {
-                        ok: false,
-                        argv: vec![],
-                        exit_code: None,
-                        stdout: "".to_string(),
-                        stdout_truncated: false,
-                        stderr: "".to_string(),
-                        stderr_truncated: false,
-                        error: Some(llm_os_common::ActionError {
-                            code: llm_os_common::ActionErrorCode::PolicyDenied,
-                            message: "observe tool not supported".to_string(),
-                        }),
-                    });
-                }
+            let program = match observe_program(obs, policy, confirmed) {
+                Ok(program) => program,
+                Err(error) => return observe_failed(obs, policy, vec![], error),
+            };
+            let argv = program.argv(obs);
+            let timeout_sec = match program.timeout_sec(obs, policy) {
+                Ok(timeout_sec) => timeout_sec,
+                Err(error) => return observe_failed(obs, policy, argv, error),
             };
-
-            let mut argv = Vec::new();
-            argv.push(base.to_string());
-            argv.extend(obs.args.iter().cloned());
 
             ActionResult::Observe(llm_os_common::ObserveResult {
                 ok: true,
@@ -624,76 +1346,65 @@ async fn plan_action(action: &Action, confirmation_token: Option<&str>, confirm_
                 stdout_truncated: false,
                 stderr: "".to_string(),
                 stderr_truncated: false,
+                arg_classes: observe_arg_classes(obs, policy),
+                processes: None,
+                timeout_sec,
                 error: None,
             })
         }
+        Action::LogQuery(q) => {
+            if let Some(error) = policy_error(&policy.log_query_verdict(q), policy, "log_query", confirmed) {
+                return log_query_denied(q, error);
+            }
+            actions::journal::planned(q)
+        }
         Action::CgroupApply(cg) => {
-            let mut argv = Vec::new();
-            argv.push("systemd-run".to_string());
-            argv.push("--scope".to_string());
-            if let Some(w) = cg.cpu_weight {
-                argv.push("-p".to_string());
-                argv.push(format!("CPUWeight={w}"));
-            }
-            if let Some(m) = cg.mem_max_bytes {
-                argv.push("-p".to_string());
-                argv.push(format!("MemoryMax={m}"));
-            }
-            if let Some(pid) = cg.pid {
-                argv.push(format!("--pid={pid}"));
-                return ActionResult::CgroupApply(llm_os_common::CgroupApplyResult {
-                    ok: true,
-                    argv,
-                    error: None,
-                });
+            if let Some(error) = policy_error(&policy.cgroup_apply_verdict(cg), policy, "cgroup_apply", confirmed) {
+                return cgroup_denied(cg, error);
             }
-            if let Some(unit) = &cg.unit {
-                argv.push(format!("--unit={unit}"));
-                return ActionResult::CgroupApply(llm_os_common::CgroupApplyResult {
-                    ok: true,
-                    argv,
-                    error: None,
-                });
+            actions::cgroup::planned(cg)
+        }
+        Action::CgroupInspect(cg) => {
+            if let Some(error) = policy_error(&policy.cgroup_inspect_verdict(cg), policy, "cgroup_inspect", confirmed) {
+                return cgroup_inspect_denied(error);
             }
-            ActionResult::CgroupApply(llm_os_common::CgroupApplyResult {
-                ok: false,
-                argv: vec![],
-                error: Some(llm_os_common::ActionError {
-                    code: llm_os_common::ActionErrorCode::PolicyDenied,
-                    message: "cgroup_apply target is invalid".to_string(),
-                }),
-            })
+            actions::cgroup::planned_inspect(cg)
         }
-        Action::FirmwareOp(fw) => {
-            let argv = match fw.op {
-                llm_os_common::FirmwareOp::Inventory => vec!["dmidecode".to_string()],
-                llm_os_common::FirmwareOp::FwupdUpdate => vec!["fwupdmgr".to_string(), "update".to_string()],
-                llm_os_common::FirmwareOp::UefiVarRead => {
-                    let name = fw.uefi_var_name.as_deref().unwrap_or("");
-                    if name.trim().is_empty() {
-                        return ActionResult::FirmwareOp(llm_os_common::FirmwareOpResult {
-                            ok: false,
-                            argv: vec![],
-                            error: Some(llm_os_common::ActionError {
-                                code: llm_os_common::ActionErrorCode::PolicyDenied,
-                                message: "firmware_op target is invalid".to_string(),
-                            }),
-                        });
-                    }
-                    vec![
-                        "cat".to_string(),
-                        format!("/sys/firmware/efi/efivars/{name}"),
-                    ]
-                }
-            };
-
+        Action::FirmwareOp(_) => {
+            // the same verdict execute mode uses, so an op that cannot run is denied here too.
+            if let Some(error) = policy_error(&policy.action_verdict(action), policy, "firmware_op", confirmed) {
+                return firmware_failed(error);
+            }
             ActionResult::FirmwareOp(llm_os_common::FirmwareOpResult {
                 ok: true,
-                argv,
+                argv: vec![],
+                inventory: None,
+                uefi_var: None,
                 error: None,
             })
         }
-        Action::Ping => ActionResult::Pong(llm_os_common::PongResult { ok: true }),
+        Action::Approval(approval) => {
+            let verdict = policy.approval_verdict(approval.op);
+            let error = policy_error(&verdict, policy, "approval", confirmed);
+            ActionResult::Approval(llm_os_common::ApprovalResult {
+                ok: error.is_none(),
+                entries: vec![],
+                error,
+            })
+        }
+        Action::SessionGrant(grant) => {
+            let verdict = policy.session_grant_verdict(grant.op);
+            let error = policy_error(&verdict, policy, "session_grant", confirmed);
+            ActionResult::SessionGrant(llm_os_common::SessionGrantResult {
+                ok: error.is_none(),
+                grants: vec![],
+                error,
+            })
+        }
+        Action::Ping => ActionResult::Pong(llm_os_common::PongResult {
+            ok: true,
+            observe_tools: policy.observe_tool_listing(),
+        }),
     }
 }
 
@@ -701,6 +1412,32 @@ async fn plan_action(action: &Action, confirmation_token: Option<&str>, confirm_
 mod tests {
     use super::*;
 
+    fn confirmation_token_hint(expected_token: &str) -> &str {
+        expected_token
+    }
+
+    async fn roundtrip(socket_path: &Path, plan: &str) -> ActionPlanResult {
+        let mut stream = UnixStream::connect(socket_path).await.unwrap();
+        stream.write_all(plan.as_bytes()).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await.unwrap();
+        serde_json::from_slice(&out).unwrap()
+    }
+
+    // answers a confirmation challenge once by resending the plan with the issued nonce.
+    async fn send_confirmed(socket_path: &Path, plan: &str) -> ActionPlanResult {
+        let response = roundtrip(socket_path, plan).await;
+        let challenge = match response.challenge {
+            Some(challenge) => challenge,
+            None => return response,
+        };
+        let mut plan: serde_json::Value = serde_json::from_str(plan).unwrap();
+        plan["confirmation"]["nonce"] = challenge.nonce.into();
+        roundtrip(socket_path, &plan.to_string()).await
+    }
+
     #[tokio::test]
     async fn server_exec_echo_roundtrip() {
         let dir = tempfile::tempdir().unwrap();
@@ -710,7 +1447,7 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server = tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -741,6 +1478,10 @@ mod tests {
             ActionResult::Exec(exec) => {
                 assert!(exec.ok);
                 assert!(exec.stdout.contains("hi"));
+                assert_eq!(
+                    exec.resolved_path.as_deref(),
+                    Some(std::fs::canonicalize("/bin/echo").unwrap().to_str().unwrap())
+                );
             }
             _ => panic!("unexpected action result type"),
         }
@@ -760,6 +1501,10 @@ mod tests {
         let v: serde_json::Value = serde_json::from_str(first_line).unwrap();
         assert_eq!(v["request_id"], "req-echo-1");
         assert_eq!(v["session_id"], "sess-1");
+        assert!(v["result"]["results"][0]["resolved_path"]
+            .as_str()
+            .unwrap()
+            .ends_with("/echo"));
 
         server.abort();
     }
@@ -774,7 +1519,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -815,6 +1560,7 @@ mod tests {
         assert!(v["peer"]["pid"].is_number());
         assert!(v["peer"]["uid"].is_number());
         assert!(v["peer"]["gid"].is_number());
+        assert!(v["profile"].is_null());
         assert_eq!(v["peer"]["pid"].as_u64().unwrap(), std::process::id() as u64);
 
         server.abort();
@@ -831,7 +1577,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -880,7 +1626,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -910,7 +1656,17 @@ mod tests {
         match &response.results[0] {
             ActionResult::ServiceControl(r) => {
                 assert!(r.ok);
-                assert_eq!(r.argv, vec!["systemctl", "status", "ssh.service"]);
+                assert_eq!(
+                    r.argv,
+                    vec![
+                        "systemctl",
+                        "--no-ask-password",
+                        "show",
+                        "--property=LoadState,ActiveState,SubState,MainPID",
+                        "--",
+                        "ssh.service"
+                    ]
+                );
             }
             _ => panic!("unexpected action result type"),
         }
@@ -919,16 +1675,15 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn server_plan_only_install_packages_returns_structured_result() {
+    async fn server_executes_service_control_through_the_backend() {
         let dir = tempfile::tempdir().unwrap();
         let socket_path = dir.path().join("llm-osd.sock");
         let audit_path = dir.path().join("audit.jsonl");
 
-        let socket_path_str = socket_path.to_string_lossy().to_string();
-        let audit_path_str = audit_path.to_string_lossy().to_string();
-
-        let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let services = Arc::new(actions::service::FakeBackend::default());
+        let mut config = Config::new(&socket_path.to_string_lossy(), &audit_path.to_string_lossy(), "i-understand");
+        config.services = services.clone();
+        let server = tokio::spawn(async move { run(config).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -937,55 +1692,159 @@ mod tests {
             tokio::time::sleep(std::time::Duration::from_millis(10)).await;
         }
 
-        let plan = r#"{
-          "request_id":"req-plan-only-pkg-1",
-          "version":"0.1",
-          "mode":"plan_only",
-          "actions":[{"type":"install_packages","manager":"apt","packages":["curl","git"],"reason":"test","danger":null,"recovery":null}]
-        }"#;
+        let svc = |verb: &str, unit: &str| {
+            format!(
+                r#"{{"type":"service_control","action":"{verb}","unit":"{unit}","reason":"test","danger":null,"recovery":null}}"#
+            )
+        };
+        // the built-in policy confirms every service change; status alone runs unconfirmed.
+        let status = format!(
+            r#"{{"request_id":"req-svc-status","version":"0.1","mode":"execute","actions":[{}]}}"#,
+            svc("status", "nginx.service"),
+        );
+        assert!(roundtrip(&socket_path, &status).await.executed);
+        assert_eq!(*services.calls.lock().unwrap(), vec!["status nginx.service"]);
+        services.calls.lock().unwrap().clear();
+
+        // a plan with a change in it runs nothing, status included, until it is confirmed.
+        let unconfirmed = format!(
+            r#"{{"request_id":"req-svc-0","version":"0.1","mode":"execute","actions":[{},{}]}}"#,
+            svc("stop", "nginx.service"),
+            svc("status", "nginx.service"),
+        );
+        let response = roundtrip(&socket_path, &unconfirmed).await;
+        assert!(!response.executed);
+        assert!(response.challenge.is_some());
+        assert_eq!(
+            response.results[0].error().map(|e| e.code.clone()),
+            Some(llm_os_common::ActionErrorCode::ConfirmationRequired)
+        );
+        assert!(response.results[1].error().is_none(), "{response:?}");
+        assert!(services.calls.lock().unwrap().is_empty());
 
-        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
-        stream.write_all(plan.as_bytes()).await.unwrap();
-        stream.shutdown().await.unwrap();
+        let plan = format!(
+            r#"{{"request_id":"req-svc-1","version":"0.1","mode":"execute","actions":[{},{},{},{},{},{},{},{}],"confirmation":{{"token":"i-understand","nonce":null}}}}"#,
+            svc("enable", "nginx.service"),
+            svc("start", "nginx.service"),
+            svc("status", "nginx.service"),
+            svc("restart", "nginx.service"),
+            svc("stop", "nginx.service"),
+            svc("disable", "nginx.service"),
+            svc("status", "nginx.service"),
+            svc("start", "missing.service"),
+        );
+        let response = send_confirmed(&socket_path, &plan).await;
+        assert!(response.executed);
+        let results: Vec<_> = response
+            .results
+            .iter()
+            .map(|result| match result {
+                ActionResult::ServiceControl(r) => r.clone(),
+                _ => panic!("unexpected action result type"),
+            })
+            .collect();
+
+        assert!(results[..7].iter().all(|r| r.ok && r.error.is_none()));
+        assert_eq!(results[1].argv, vec!["fake", "start", "nginx.service"]);
+        let running = results[2].status.as_ref().unwrap();
+        assert_eq!(running.active_state, "active");
+        assert_eq!(running.sub_state, "running");
+        assert_eq!(running.main_pid, Some(4242));
+        assert!(results[1].status.is_none());
+        let stopped = results[6].status.as_ref().unwrap();
+        assert_eq!(stopped.active_state, "inactive");
+        assert_eq!(stopped.main_pid, None);
+
+        let failed = &results[7];
+        assert!(!failed.ok);
+        let error = failed.error.as_ref().unwrap();
+        assert_eq!(error.code, llm_os_common::ActionErrorCode::ServiceFailed);
+        assert_eq!(error.message, "Unit missing.service not found.");
+
+        // the unconfirmed first send ran nothing, so every action ran exactly once.
+        let calls = services.calls.lock().unwrap().clone();
+        assert_eq!(
+            calls,
+            [
+                "enable nginx.service",
+                "start nginx.service",
+                "status nginx.service",
+                "restart nginx.service",
+                "stop nginx.service",
+                "disable nginx.service",
+                "status nginx.service",
+                "start missing.service",
+            ]
+        );
 
-        let mut out = Vec::new();
-        stream.read_to_end(&mut out).await.unwrap();
-        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
-        assert_eq!(response.request_id, "req-plan-only-pkg-1");
-        assert!(response.error.is_none());
-        assert!(!response.executed);
-        assert_eq!(response.results.len(), 1);
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_installs_and_removes_packages_through_the_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let fake = Arc::new(actions::packages::FakeBackend::new(&["curl"], &["curl", "git"]));
+        let mut config = Config::new(&socket_path.to_string_lossy(), &audit_path.to_string_lossy(), "i-understand");
+        config.packages.set(llm_os_common::PackageManager::Apt, fake.clone());
+        config.policy = serde_json::from_str(r#"{"install_packages":{"default":"allow"},"remove_packages":{"default":"allow"}}"#).unwrap();
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let plan = r#"{"request_id":"req-pkg-1","version":"0.1","mode":"execute","actions":[
+          {"type":"install_packages","manager":"apt","packages":["curl","git","nope"],"reason":"test","danger":null,"recovery":null},
+          {"type":"remove_packages","manager":"apt","packages":["curl"],"reason":"test","danger":null,"recovery":null}
+        ]}"#;
+        let response = roundtrip(&socket_path, plan).await;
+        assert!(response.executed);
         match &response.results[0] {
             ActionResult::InstallPackages(r) => {
-                assert!(r.ok);
+                assert!(!r.ok);
+                assert_eq!(r.argv, vec!["fake", "install", "curl", "git", "nope"]);
+                let states: Vec<_> = r.packages.iter().map(|p| p.state).collect();
                 assert_eq!(
-                    r.argv,
+                    states,
                     vec![
-                        "apt-get",
-                        "install",
-                        "-y",
-                        "curl",
-                        "git"
+                        llm_os_common::PackageState::AlreadyPresent,
+                        llm_os_common::PackageState::Installed,
+                        llm_os_common::PackageState::NotFound,
                     ]
                 );
+                assert_eq!(r.error.as_ref().unwrap().code, llm_os_common::ActionErrorCode::PackageFailed);
+            }
+            _ => panic!("unexpected action result type"),
+        }
+        match &response.results[1] {
+            ActionResult::RemovePackages(r) => {
+                assert!(r.ok, "{r:?}");
+                assert_eq!(r.packages[0].state, llm_os_common::PackageState::Removed);
             }
             _ => panic!("unexpected action result type"),
         }
+        let installed: Vec<_> = fake.installed.lock().unwrap().iter().cloned().collect();
+        assert_eq!(installed, vec!["git"]);
 
         server.abort();
     }
 
     #[tokio::test]
-    async fn server_plan_only_remove_packages_returns_structured_result() {
+    async fn server_default_policy_holds_package_changes() {
         let dir = tempfile::tempdir().unwrap();
         let socket_path = dir.path().join("llm-osd.sock");
         let audit_path = dir.path().join("audit.jsonl");
 
-        let socket_path_str = socket_path.to_string_lossy().to_string();
-        let audit_path_str = audit_path.to_string_lossy().to_string();
-
-        let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let fake = Arc::new(actions::packages::FakeBackend::new(&["curl"], &["curl", "git"]));
+        let mut config = Config::new(&socket_path.to_string_lossy(), &audit_path.to_string_lossy(), "i-understand");
+        config.packages.set(llm_os_common::PackageManager::Apt, fake.clone());
+        let server = tokio::spawn(async move { run(config).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -994,37 +1853,253 @@ mod tests {
             tokio::time::sleep(std::time::Duration::from_millis(10)).await;
         }
 
-        let plan = r#"{
-          "request_id":"req-plan-only-rmpkg-1",
-          "version":"0.1",
-          "mode":"plan_only",
-          "actions":[{"type":"remove_packages","manager":"apt","packages":["curl","git"],"reason":"test","danger":null,"recovery":null}]
-        }"#;
+        let install = r#"{"request_id":"req-pkg-install","version":"0.1","mode":"execute","actions":[
+          {"type":"install_packages","manager":"apt","packages":["git"],"reason":"test","danger":null,"recovery":null}
+        ]}"#;
+        let response = roundtrip(&socket_path, install).await;
+        assert_eq!(
+            response.results[0].error().map(|e| e.code.clone()),
+            Some(llm_os_common::ActionErrorCode::ConfirmationRequired)
+        );
 
-        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
-        stream.write_all(plan.as_bytes()).await.unwrap();
-        stream.shutdown().await.unwrap();
+        // even a confirmed removal is parked for a second person rather than run.
+        let remove = r#"{"request_id":"req-pkg-remove","version":"0.1","mode":"execute","actions":[
+          {"type":"remove_packages","manager":"apt","packages":["curl"],"reason":"test","danger":null,"recovery":null}
+        ],"confirmation":{"token":"i-understand","nonce":null}}"#;
+        let response = send_confirmed(&socket_path, remove).await;
+        assert!(!response.executed);
+        assert!(response.results.is_empty());
+        assert_eq!(response.approval.unwrap().state, llm_os_common::ApprovalState::Pending);
 
-        let mut out = Vec::new();
-        stream.read_to_end(&mut out).await.unwrap();
-        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
-        assert_eq!(response.request_id, "req-plan-only-rmpkg-1");
-        assert!(response.error.is_none());
-        assert!(!response.executed);
-        assert_eq!(response.results.len(), 1);
-        match &response.results[0] {
-            ActionResult::RemovePackages(r) => {
-                assert!(r.ok);
-                assert_eq!(r.argv, vec!["apt-get", "remove", "-y", "curl", "git"]);
+        let installed: Vec<_> = fake.installed.lock().unwrap().iter().cloned().collect();
+        assert_eq!(installed, vec!["curl"]);
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_update_system_reports_each_step_and_stops_at_the_first_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let fake = Arc::new(actions::packages::FakeBackend::new(&["broken-lib"], &[]));
+        let mut config = Config::new(&socket_path.to_string_lossy(), &audit_path.to_string_lossy(), "i-understand");
+        config.packages.set(llm_os_common::PackageManager::Dnf, fake.clone());
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
             }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let update = |scope: &str| {
+            format!(
+                r#"{{"request_id":"req-upd-1","version":"0.1","mode":"execute","actions":[
+                  {{"type":"update_system","manager":"dnf","scope":{scope},"reason":"test","danger":null,"recovery":null}}
+                ],"confirmation":{{"token":"i-understand","nonce":null}}}}"#
+            )
+        };
+        let result = |response: &ActionPlanResult| match &response.results[0] {
+            ActionResult::UpdateSystem(r) => r.clone(),
             _ => panic!("unexpected action result type"),
+        };
+
+        // the built-in policy lets a refresh run unconfirmed and holds anything that upgrades.
+        let refreshed = roundtrip(&socket_path, &update(r#""refresh_only""#)).await;
+        assert!(refreshed.challenge.is_none());
+        let refreshed = result(&refreshed);
+        assert!(refreshed.ok, "{refreshed:?}");
+        assert_eq!(refreshed.steps.len(), 1);
+        let unconfirmed = result(&roundtrip(&socket_path, &update("null")).await);
+        assert!(unconfirmed.steps.is_empty());
+        assert_eq!(unconfirmed.error.unwrap().code, llm_os_common::ActionErrorCode::ConfirmationRequired);
+
+        let full = result(&send_confirmed(&socket_path, &update("null")).await);
+        assert!(!full.ok);
+        let states: Vec<_> = full.steps.iter().map(|step| step.state).collect();
+        assert_eq!(
+            states,
+            vec![llm_os_common::UpdateStepState::Succeeded, llm_os_common::UpdateStepState::Failed]
+        );
+        assert_eq!(full.error.unwrap().code, llm_os_common::ActionErrorCode::PackageFailed);
+
+        let security = result(&send_confirmed(&socket_path, &update(r#""security_only""#)).await);
+        assert!(security.steps.is_empty());
+        assert_eq!(
+            security.error.unwrap().message,
+            "update_system security_only is not supported for this manager"
+        );
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_cgroup_apply_writes_under_the_configured_root_and_honors_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+        let cgroup_root = dir.path().join("cgroup");
+        std::fs::create_dir(&cgroup_root).unwrap();
+
+        let mut config = Config::new(&socket_path.to_string_lossy(), &audit_path.to_string_lossy(), "i-understand");
+        config.cgroup_root = cgroup_root.clone();
+        config.policy.cgroup_apply.deny = vec!["system*".to_string()];
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
         }
 
+        let apply = |unit: &str| {
+            format!(
+                r#"{{"request_id":"req-cg-{unit}","version":"0.1","mode":"execute","actions":[
+                  {{"type":"cgroup_apply","pid":null,"unit":"{unit}","cpu_weight":50,"mem_max_bytes":null,"reason":"test","danger":null,"recovery":null}}
+                ],"confirmation":{{"token":"i-understand","nonce":null}}}}"#
+            )
+        };
+        let result = |response: &ActionPlanResult| match &response.results[0] {
+            ActionResult::CgroupApply(r) => r.clone(),
+            _ => panic!("unexpected action result type"),
+        };
+
+        // the built-in policy confirms every cgroup change.
+        let unconfirmed = result(&roundtrip(&socket_path, &apply("batch.scope")).await);
+        assert_eq!(unconfirmed.error.unwrap().code, llm_os_common::ActionErrorCode::ConfirmationRequired);
+        assert!(!cgroup_root.join("llm-osd/batch.scope").exists());
+
+        let applied = result(&send_confirmed(&socket_path, &apply("batch.scope")).await);
+        assert!(applied.ok, "{applied:?}");
+        assert!(applied.created);
+        assert_eq!(applied.files[0].effective.as_deref(), Some("50"));
+        assert_eq!(
+            std::fs::read_to_string(cgroup_root.join("llm-osd/batch.scope/cpu.weight")).unwrap(),
+            "50"
+        );
+
+        let denied = result(&roundtrip(&socket_path, &apply("system-jobs")).await);
+        assert!(!denied.ok);
+        assert_eq!(denied.error.unwrap().policy.unwrap().rule_id, "cgroup_apply.deny:system*");
+        assert!(!cgroup_root.join("llm-osd/system-jobs").exists());
+
         server.abort();
     }
 
     #[tokio::test]
-    async fn server_plan_only_update_system_returns_structured_result() {
+    async fn server_firmware_op_reads_the_configured_sysfs_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+        let sysfs_root = dir.path().join("sys");
+        std::fs::create_dir_all(sysfs_root.join("class/dmi/id")).unwrap();
+        std::fs::write(sysfs_root.join("class/dmi/id/bios_version"), "1.62\n").unwrap();
+
+        let mut config = Config::new(&socket_path.to_string_lossy(), &audit_path.to_string_lossy(), "i-understand");
+        config.sysfs_root = sysfs_root;
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let firmware = |op: &str, name: &str| {
+            format!(
+                r#"{{"request_id":"req-fw-{op}","version":"0.1","mode":"execute","actions":[
+                  {{"type":"firmware_op","op":"{op}","uefi_var_name":{name},"reason":"test","danger":null,"recovery":null}}
+                ]}}"#
+            )
+        };
+        let result = |response: &ActionPlanResult| match &response.results[0] {
+            ActionResult::FirmwareOp(r) => r.clone(),
+            _ => panic!("unexpected action result type"),
+        };
+
+        let inventory = result(&roundtrip(&socket_path, &firmware("inventory", "null")).await);
+        assert!(inventory.ok, "{inventory:?}");
+        assert_eq!(inventory.inventory.unwrap().bios_version.as_deref(), Some("1.62"));
+
+        let var = result(&roundtrip(&socket_path, &firmware("uefi_var_read", r#""BootOrder-0000""#)).await);
+        let error = var.error.unwrap();
+        assert_eq!(error.code, llm_os_common::ActionErrorCode::ConfirmationRequired);
+        assert_eq!(error.policy.unwrap().rule_id, "firmware_op.default");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_log_query_runs_the_configured_journalctl_and_parses_entries() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+        let args_path = dir.path().join("args");
+        let journalctl = dir.path().join("journalctl");
+        std::fs::write(
+            &journalctl,
+            format!(
+                "#!/bin/sh\nprintf '%s\\n' \"$@\" > {}\necho '{}'\n",
+                args_path.display(),
+                r#"{"__REALTIME_TIMESTAMP":"1760601600000000","MESSAGE":"Started ssh.service","PRIORITY":"6","_PID":"1","_SYSTEMD_UNIT":"init.scope"}"#
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&journalctl, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut config = Config::new(&socket_path.to_string_lossy(), &audit_path.to_string_lossy(), "i-understand");
+        config.journalctl = journalctl;
+        config.policy.log_query.deny = vec!["secrets*".to_string()];
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let query = |mode: &str, unit: &str| {
+            format!(
+                r#"{{"request_id":"req-log-{mode}-{unit}","version":"0.1","mode":"{mode}","actions":[
+                  {{"type":"log_query","unit":"{unit}","priority":{{"from":"emerg","to":"info"}},"since":"-1h","until":null,"grep":null,"max_entries":10,"reason":"test","danger":null,"recovery":null}}
+                ]}}"#
+            )
+        };
+        let result = |response: &ActionPlanResult| match &response.results[0] {
+            ActionResult::LogQuery(r) => r.clone(),
+            _ => panic!("unexpected action result type"),
+        };
+
+        let planned = result(&roundtrip(&socket_path, &query("plan_only", "ssh.service")).await);
+        assert!(planned.ok && planned.entries.is_empty());
+        assert!(planned.argv.contains(&"--unit=ssh.service".to_string()));
+        assert!(!args_path.exists());
+
+        let executed = result(&roundtrip(&socket_path, &query("execute", "ssh.service")).await);
+        assert!(executed.ok, "{executed:?}");
+        assert_eq!(executed.entries.len(), 1);
+        assert_eq!(executed.entries[0].message, "Started ssh.service");
+        assert_eq!(executed.entries[0].priority, Some(llm_os_common::LogPriority::Info));
+        let args = std::fs::read_to_string(&args_path).unwrap();
+        assert!(args.lines().any(|arg| arg == "--priority=emerg..info"));
+        assert!(args.lines().any(|arg| arg == "--since=-1h"));
+
+        let denied = result(&roundtrip(&socket_path, &query("execute", "secrets.service")).await);
+        assert_eq!(denied.error.unwrap().policy.unwrap().rule_id, "log_query.deny:secrets*");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_plan_only_install_packages_returns_structured_result() {
         let dir = tempfile::tempdir().unwrap();
         let socket_path = dir.path().join("llm-osd.sock");
         let audit_path = dir.path().join("audit.jsonl");
@@ -1032,8 +2107,9 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let mut config = Config::new(&socket_path_str, &audit_path_str, "i-understand");
+        config.policy = serde_json::from_str(r#"{"install_packages":{"default":"allow"}}"#).unwrap();
+        let server = tokio::spawn(async move { run(config).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1043,10 +2119,10 @@ mod tests {
         }
 
         let plan = r#"{
-          "request_id":"req-plan-only-upd-1",
+          "request_id":"req-plan-only-pkg-1",
           "version":"0.1",
           "mode":"plan_only",
-          "actions":[{"type":"update_system","manager":"apt","reason":"test","danger":null,"recovery":null}]
+          "actions":[{"type":"install_packages","manager":"apt","packages":["curl","git"],"reason":"test","danger":null,"recovery":null}]
         }"#;
 
         let mut stream = UnixStream::connect(&socket_path).await.unwrap();
@@ -1056,16 +2132,25 @@ mod tests {
         let mut out = Vec::new();
         stream.read_to_end(&mut out).await.unwrap();
         let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
-        assert_eq!(response.request_id, "req-plan-only-upd-1");
+        assert_eq!(response.request_id, "req-plan-only-pkg-1");
         assert!(response.error.is_none());
         assert!(!response.executed);
         assert_eq!(response.results.len(), 1);
         match &response.results[0] {
-            ActionResult::UpdateSystem(r) => {
+            ActionResult::InstallPackages(r) => {
                 assert!(r.ok);
                 assert_eq!(
                     r.argv,
-                    vec!["apt-get", "update", "&&", "apt-get", "upgrade", "-y"]
+                    vec![
+                        "apt-get",
+                        "install",
+                        "-y",
+                        "-o",
+                        "DPkg::Lock::Timeout=300",
+                        "--",
+                        "curl",
+                        "git"
+                    ]
                 );
             }
             _ => panic!("unexpected action result type"),
@@ -1075,7 +2160,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn server_plan_only_observe_returns_structured_result() {
+    async fn server_plan_only_remove_packages_returns_structured_result() {
         let dir = tempfile::tempdir().unwrap();
         let socket_path = dir.path().join("llm-osd.sock");
         let audit_path = dir.path().join("audit.jsonl");
@@ -1083,8 +2168,9 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let mut config = Config::new(&socket_path_str, &audit_path_str, "i-understand");
+        config.policy = serde_json::from_str(r#"{"remove_packages":{"default":"allow"}}"#).unwrap();
+        let server = tokio::spawn(async move { run(config).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1094,10 +2180,10 @@ mod tests {
         }
 
         let plan = r#"{
-          "request_id":"req-plan-only-obs-1",
+          "request_id":"req-plan-only-rmpkg-1",
           "version":"0.1",
           "mode":"plan_only",
-          "actions":[{"type":"observe","tool":"ps","args":["aux"],"reason":"test","danger":null,"recovery":null}]
+          "actions":[{"type":"remove_packages","manager":"apt","packages":["curl","git"],"reason":"test","danger":null,"recovery":null}]
         }"#;
 
         let mut stream = UnixStream::connect(&socket_path).await.unwrap();
@@ -1107,14 +2193,14 @@ mod tests {
         let mut out = Vec::new();
         stream.read_to_end(&mut out).await.unwrap();
         let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
-        assert_eq!(response.request_id, "req-plan-only-obs-1");
+        assert_eq!(response.request_id, "req-plan-only-rmpkg-1");
         assert!(response.error.is_none());
         assert!(!response.executed);
         assert_eq!(response.results.len(), 1);
         match &response.results[0] {
-            ActionResult::Observe(r) => {
+            ActionResult::RemovePackages(r) => {
                 assert!(r.ok);
-                assert_eq!(r.argv, vec!["ps", "aux"]);
+                assert_eq!(r.argv, vec!["apt-get", "remove", "-y", "-o", "DPkg::Lock::Timeout=300", "--", "curl", "git"]);
             }
             _ => panic!("unexpected action result type"),
         }
@@ -1123,7 +2209,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn server_execute_observe_returns_stdout() {
+    async fn server_plan_only_update_system_returns_structured_result() {
         let dir = tempfile::tempdir().unwrap();
         let socket_path = dir.path().join("llm-osd.sock");
         let audit_path = dir.path().join("audit.jsonl");
@@ -1131,8 +2217,9 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let mut config = Config::new(&socket_path_str, &audit_path_str, "i-understand");
+        config.policy = serde_json::from_str(r#"{"update_system":{"default":"allow"}}"#).unwrap();
+        let server = tokio::spawn(async move { run(config).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1142,10 +2229,10 @@ mod tests {
         }
 
         let plan = r#"{
-          "request_id":"req-exec-obs-1",
+          "request_id":"req-plan-only-upd-1",
           "version":"0.1",
-          "mode":"execute",
-          "actions":[{"type":"observe","tool":"ps","args":["aux"],"reason":"test","danger":null,"recovery":null}]
+          "mode":"plan_only",
+          "actions":[{"type":"update_system","manager":"apt","reason":"test","danger":null,"recovery":null}]
         }"#;
 
         let mut stream = UnixStream::connect(&socket_path).await.unwrap();
@@ -1155,15 +2242,22 @@ mod tests {
         let mut out = Vec::new();
         stream.read_to_end(&mut out).await.unwrap();
         let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
-        assert_eq!(response.request_id, "req-exec-obs-1");
+        assert_eq!(response.request_id, "req-plan-only-upd-1");
         assert!(response.error.is_none());
-        assert!(response.executed);
+        assert!(!response.executed);
         assert_eq!(response.results.len(), 1);
         match &response.results[0] {
-            ActionResult::Observe(r) => {
+            ActionResult::UpdateSystem(r) => {
                 assert!(r.ok);
-                assert_eq!(r.exit_code, Some(0));
-                assert!(!r.stdout.is_empty());
+                let argv: Vec<_> = r.steps.iter().map(|step| step.argv.join(" ")).collect();
+                assert_eq!(
+                    argv,
+                    vec![
+                        "apt-get update -o DPkg::Lock::Timeout=300",
+                        "apt-get upgrade -y -o DPkg::Lock::Timeout=300"
+                    ]
+                );
+                assert!(r.steps.iter().all(|step| step.state == llm_os_common::UpdateStepState::Planned));
             }
             _ => panic!("unexpected action result type"),
         }
@@ -1172,7 +2266,151 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn server_plan_only_cgroup_apply_returns_structured_result() {
+    async fn server_plan_only_observe_reports_the_argument_classification() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let config = Config::new(&socket_path.to_string_lossy(), &audit_path.to_string_lossy(), "i-understand");
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let observe = |args: &str| {
+            format!(
+                r#"{{"request_id":"req-obs-args","version":"0.1","mode":"plan_only","actions":[
+                  {{"type":"observe","tool":"journalctl","args":{args},"reason":"test","danger":null,"recovery":null}}
+                ]}}"#
+            )
+        };
+        let result = |response: &ActionPlanResult| match &response.results[0] {
+            ActionResult::Observe(r) => r.clone(),
+            _ => panic!("unexpected action result type"),
+        };
+
+        let planned = result(&roundtrip(&socket_path, &observe(r#"["-u","ssh.service","--since=-1h"]"#)).await);
+        assert!(planned.ok, "{planned:?}");
+        assert_eq!(planned.arg_classes.len(), 3);
+        assert!(planned.arg_classes.iter().all(|c| c.decision == llm_os_common::PolicyDecision::Allow));
+        assert_eq!(planned.arg_classes[1].rule_id, "observe.journalctl.allow:-u");
+
+        let vacuum = result(&roundtrip(&socket_path, &observe(r#"["-u","ssh.service","--vacuum-size=1"]"#)).await);
+        assert!(!vacuum.ok);
+        assert_eq!(vacuum.arg_classes[2].decision, llm_os_common::PolicyDecision::Deny);
+        let error = vacuum.error.unwrap();
+        assert_eq!(error.code, llm_os_common::ActionErrorCode::PolicyDenied);
+        assert_eq!(error.policy.unwrap().rule_id, "observe.journalctl.deny:--vacuum-size");
+
+        let follow = result(&roundtrip(&socket_path, &observe(r#"["-f"]"#)).await);
+        assert_eq!(follow.arg_classes[0].decision, llm_os_common::PolicyDecision::Confirm);
+        assert_eq!(planned.timeout_sec, Some(5));
+
+        let long = r#"{"request_id":"req-obs-timeout","version":"0.1","mode":"plan_only","actions":[
+          {"type":"observe","tool":"perf","args":["stat","-a"],"timeout_sec":30,"reason":"test","danger":null,"recovery":null},
+          {"type":"observe","tool":"perf","args":["stat","-a"],"timeout_sec":61,"reason":"test","danger":null,"recovery":null}
+        ]}"#;
+        let response = roundtrip(&socket_path, long).await;
+        match (&response.results[0], &response.results[1]) {
+            (ActionResult::Observe(within), ActionResult::Observe(above)) => {
+                assert_eq!(within.timeout_sec, Some(30));
+                let error = above.error.clone().unwrap();
+                assert_eq!(error.code, llm_os_common::ActionErrorCode::ObserveFailed);
+                assert_eq!(error.message, "timeout_sec 61 is above the 60s maximum for perf");
+            }
+            _ => panic!("unexpected action result types"),
+        }
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_execute_observe_runs_registered_tools_by_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let mut config = Config::new(&socket_path.to_string_lossy(), &audit_path.to_string_lossy(), "i-understand");
+        config.policy.observe_tools = policy::ObserveCatalog {
+            tools: vec![policy::RegisteredTool {
+                name: "echo-tool".to_string(),
+                path: "/bin/echo".into(),
+                args: vec!["-n".to_string()],
+                flags: vec![
+                    policy::RegisteredFlag {
+                        names: vec!["--verbose".to_string()],
+                        value: policy::FlagValue::None,
+                    },
+                    policy::RegisteredFlag {
+                        names: vec!["--greeting".to_string()],
+                        value: policy::FlagValue::Attached,
+                    },
+                ],
+                timeout_sec: 5,
+                max_timeout_sec: Some(10),
+                max_output_bytes: 9,
+                description: Some("echoes its arguments".to_string()),
+            }],
+            timeouts: Default::default(),
+        };
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let observe = |name: &str, args: &str| {
+            format!(
+                r#"{{"request_id":"req-obs-registered","version":"0.1","mode":"execute","actions":[
+                  {{"type":"observe","tool":"other","name":"{name}","args":{args},"reason":"test","danger":null,"recovery":null}}
+                ]}}"#
+            )
+        };
+        let result = |response: &ActionPlanResult| match &response.results[0] {
+            ActionResult::Observe(r) => r.clone(),
+            _ => panic!("unexpected action result type"),
+        };
+
+        let ran = result(&roundtrip(&socket_path, &observe("echo-tool", r#"["--verbose","--greeting=hi"]"#)).await);
+        assert!(ran.ok, "{:?}", ran.error);
+        assert_eq!(ran.argv, vec!["/bin/echo", "-n", "--verbose", "--greeting=hi"]);
+        assert!(ran.stdout.starts_with("--verbose\n[truncated]"), "{}", ran.stdout);
+        assert!(ran.stdout_truncated);
+        assert_eq!(ran.arg_classes[1].rule_id, "observe.echo-tool.allow:--greeting");
+        assert_eq!(ran.timeout_sec, Some(5));
+
+        let unknown = result(&roundtrip(&socket_path, &observe("echo-tool", r#"["-e"]"#)).await);
+        assert!(!unknown.ok);
+        assert_eq!(unknown.arg_classes[0].decision, llm_os_common::PolicyDecision::Deny);
+        assert_eq!(unknown.error.unwrap().policy.unwrap().rule_id, "observe.echo-tool.unknown");
+
+        let missing = result(&roundtrip(&socket_path, &observe("cat-tool", "[]")).await);
+        let error = missing.error.unwrap();
+        assert_eq!(error.code, llm_os_common::ActionErrorCode::PolicyDenied);
+        assert_eq!(error.policy.unwrap().rule_id, "observe.other.unregistered");
+
+        let ping = r#"{"request_id":"req-ping-tools","version":"0.1","mode":"execute","actions":[{"type":"ping"}]}"#;
+        match &roundtrip(&socket_path, ping).await.results[0] {
+            ActionResult::Pong(p) => {
+                assert_eq!(p.observe_tools.len(), 1);
+                assert_eq!(p.observe_tools[0].name, "echo-tool");
+                assert_eq!(p.observe_tools[0].allowed_flags, vec!["--verbose", "--greeting=<value>"]);
+            }
+            _ => panic!("unexpected action result type"),
+        }
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_plan_only_observe_returns_structured_result() {
         let dir = tempfile::tempdir().unwrap();
         let socket_path = dir.path().join("llm-osd.sock");
         let audit_path = dir.path().join("audit.jsonl");
@@ -1181,7 +2419,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1191,10 +2429,10 @@ mod tests {
         }
 
         let plan = r#"{
-          "request_id":"req-plan-only-cg-1",
+          "request_id":"req-plan-only-obs-1",
           "version":"0.1",
           "mode":"plan_only",
-          "actions":[{"type":"cgroup_apply","pid":1234,"unit":null,"cpu_weight":100,"mem_max_bytes":1048576,"reason":"test","danger":null,"recovery":null}]
+          "actions":[{"type":"observe","tool":"ps","args":[],"reason":"test","danger":null,"recovery":null}]
         }"#;
 
         let mut stream = UnixStream::connect(&socket_path).await.unwrap();
@@ -1204,25 +2442,15 @@ mod tests {
         let mut out = Vec::new();
         stream.read_to_end(&mut out).await.unwrap();
         let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
-        assert_eq!(response.request_id, "req-plan-only-cg-1");
+        assert_eq!(response.request_id, "req-plan-only-obs-1");
         assert!(response.error.is_none());
         assert!(!response.executed);
         assert_eq!(response.results.len(), 1);
         match &response.results[0] {
-            ActionResult::CgroupApply(r) => {
+            ActionResult::Observe(r) => {
                 assert!(r.ok);
-                assert_eq!(
-                    r.argv,
-                    vec![
-                        "systemd-run",
-                        "--scope",
-                        "-p",
-                        "CPUWeight=100",
-                        "-p",
-                        "MemoryMax=1048576",
-                        "--pid=1234"
-                    ]
-                );
+                assert!(r.argv.is_empty());
+                assert!(r.processes.is_none());
             }
             _ => panic!("unexpected action result type"),
         }
@@ -1231,7 +2459,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn server_plan_only_firmware_op_returns_structured_result() {
+    async fn server_execute_observe_ps_returns_a_process_snapshot() {
         let dir = tempfile::tempdir().unwrap();
         let socket_path = dir.path().join("llm-osd.sock");
         let audit_path = dir.path().join("audit.jsonl");
@@ -1240,7 +2468,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1250,10 +2478,10 @@ mod tests {
         }
 
         let plan = r#"{
-          "request_id":"req-plan-only-fw-1",
+          "request_id":"req-exec-obs-1",
           "version":"0.1",
-          "mode":"plan_only",
-          "actions":[{"type":"firmware_op","op":"inventory","uefi_var_name":null,"reason":"test","danger":null,"recovery":null}]
+          "mode":"execute",
+          "actions":[{"type":"observe","tool":"ps","args":[],"processes":{"fields":["pid","name"]},"reason":"test","danger":null,"recovery":null}]
         }"#;
 
         let mut stream = UnixStream::connect(&socket_path).await.unwrap();
@@ -1263,14 +2491,19 @@ mod tests {
         let mut out = Vec::new();
         stream.read_to_end(&mut out).await.unwrap();
         let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
-        assert_eq!(response.request_id, "req-plan-only-fw-1");
+        assert_eq!(response.request_id, "req-exec-obs-1");
         assert!(response.error.is_none());
-        assert!(!response.executed);
+        assert!(response.executed);
         assert_eq!(response.results.len(), 1);
         match &response.results[0] {
-            ActionResult::FirmwareOp(r) => {
-                assert!(r.ok);
-                assert_eq!(r.argv, vec!["dmidecode"]);
+            ActionResult::Observe(r) => {
+                assert!(r.ok, "{:?}", r.error);
+                assert!(r.argv.is_empty());
+                assert!(r.stdout.is_empty());
+                let snapshot = r.processes.as_ref().unwrap();
+                let own = snapshot.processes.iter().find(|p| p.pid == std::process::id()).unwrap();
+                assert!(own.name.is_some());
+                assert!(own.ppid.is_none() && own.cmdline.is_none());
             }
             _ => panic!("unexpected action result type"),
         }
@@ -1279,7 +2512,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn server_ping_roundtrip() {
+    async fn server_plan_only_cgroup_apply_returns_structured_result() {
         let dir = tempfile::tempdir().unwrap();
         let socket_path = dir.path().join("llm-osd.sock");
         let audit_path = dir.path().join("audit.jsonl");
@@ -1287,7 +2520,9 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server = tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let mut config = Config::new(&socket_path_str, &audit_path_str, "i-understand");
+        config.policy.cgroup_apply.default = policy::Decision::Allow;
+        let server = tokio::spawn(async move { run(config).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1297,10 +2532,10 @@ mod tests {
         }
 
         let plan = r#"{
-          "request_id":"req-ping-1",
+          "request_id":"req-plan-only-cg-1",
           "version":"0.1",
-          "mode":"execute",
-          "actions":[{"type":"ping"}]
+          "mode":"plan_only",
+          "actions":[{"type":"cgroup_apply","pid":1234,"unit":null,"cpu_weight":100,"mem_max_bytes":1048576,"reason":"test","danger":null,"recovery":null}]
         }"#;
 
         let mut stream = UnixStream::connect(&socket_path).await.unwrap();
@@ -1310,12 +2545,19 @@ mod tests {
         let mut out = Vec::new();
         stream.read_to_end(&mut out).await.unwrap();
         let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
-        assert_eq!(response.request_id, "req-ping-1");
+        assert_eq!(response.request_id, "req-plan-only-cg-1");
         assert!(response.error.is_none());
+        assert!(!response.executed);
         assert_eq!(response.results.len(), 1);
-
         match &response.results[0] {
-            ActionResult::Pong(p) => assert!(p.ok),
+            ActionResult::CgroupApply(r) => {
+                assert!(r.ok);
+                assert_eq!(r.cgroup, "/llm-osd/pid-1234");
+                assert_eq!(r.pid, Some(1234));
+                let files: Vec<(&str, &str)> = r.files.iter().map(|f| (f.file.as_str(), f.requested.as_str())).collect();
+                assert_eq!(files, vec![("cpu.weight", "100"), ("memory.max", "1048576")]);
+                assert!(r.files.iter().all(|f| f.previous.is_none() && f.effective.is_none()));
+            }
             _ => panic!("unexpected action result type"),
         }
 
@@ -1323,7 +2565,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn server_returns_parse_failed_for_incomplete_json_without_close() {
+    async fn server_plan_only_firmware_op_returns_structured_result() {
         let dir = tempfile::tempdir().unwrap();
         let socket_path = dir.path().join("llm-osd.sock");
         let audit_path = dir.path().join("audit.jsonl");
@@ -1332,7 +2574,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1341,9 +2583,116 @@ mod tests {
             tokio::time::sleep(std::time::Duration::from_millis(10)).await;
         }
 
+        let plan = r#"{
+          "request_id":"req-plan-only-fw-1",
+          "version":"0.1",
+          "mode":"plan_only",
+          "actions":[
+            {"type":"firmware_op","op":"inventory","uefi_var_name":null,"reason":"test","danger":null,"recovery":null},
+            {"type":"firmware_op","op":"fwupd_update","uefi_var_name":null,"reason":"test","danger":null,"recovery":null}
+          ]
+        }"#;
+
         let mut stream = UnixStream::connect(&socket_path).await.unwrap();
-        stream
-            .write_all(b"{\"request_id\":\"req-timeout-1\"")
+        stream.write_all(plan.as_bytes()).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await.unwrap();
+        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+        assert_eq!(response.request_id, "req-plan-only-fw-1");
+        assert!(response.error.is_none());
+        assert!(!response.executed);
+        assert_eq!(response.results.len(), 2);
+        match &response.results[0] {
+            ActionResult::FirmwareOp(r) => {
+                assert!(r.ok);
+                assert!(r.argv.is_empty());
+                assert!(r.inventory.is_none());
+            }
+            _ => panic!("unexpected action result type"),
+        }
+        // fwupd_update cannot run, so planning it gives the same answer executing it would.
+        match &response.results[1] {
+            ActionResult::FirmwareOp(r) => {
+                assert!(!r.ok);
+                assert!(r.argv.is_empty());
+                let error = r.error.as_ref().unwrap();
+                assert_eq!(error.code, llm_os_common::ActionErrorCode::PolicyDenied);
+                assert_eq!(error.policy.as_ref().unwrap().rule_id, "unsupported");
+            }
+            _ => panic!("unexpected action result type"),
+        }
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_ping_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let plan = r#"{
+          "request_id":"req-ping-1",
+          "version":"0.1",
+          "mode":"execute",
+          "actions":[{"type":"ping"}]
+        }"#;
+
+        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
+        stream.write_all(plan.as_bytes()).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await.unwrap();
+        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+        assert_eq!(response.request_id, "req-ping-1");
+        assert!(response.error.is_none());
+        assert_eq!(response.results.len(), 1);
+
+        match &response.results[0] {
+            ActionResult::Pong(p) => assert!(p.ok),
+            _ => panic!("unexpected action result type"),
+        }
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_returns_parse_failed_for_incomplete_json_without_close() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+
+        let server =
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
+        stream
+            .write_all(b"{\"request_id\":\"req-timeout-1\"")
             .await
             .unwrap();
 
@@ -1371,7 +2720,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1416,7 +2765,7 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server = tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1459,16 +2808,10 @@ mod tests {
               "actions":[{{"type":"exec","argv":["/usr/bin/true"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}}],
               "confirmation":{{"token":"{}"}}
             }}"#,
-            policy::confirmation_token_hint("i-understand")
+            confirmation_token_hint("i-understand")
         );
 
-        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
-        stream.write_all(plan_with.as_bytes()).await.unwrap();
-        stream.shutdown().await.unwrap();
-
-        let mut out = Vec::new();
-        stream.read_to_end(&mut out).await.unwrap();
-        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+        let response = send_confirmed(&socket_path, &plan_with).await;
 
         match &response.results[0] {
             ActionResult::Exec(exec) => assert!(exec.ok),
@@ -1487,7 +2830,7 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server = tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1533,7 +2876,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1579,7 +2922,7 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server = tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1612,7 +2955,7 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server = tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1654,7 +2997,7 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server = tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1702,16 +3045,10 @@ mod tests {
             }}"#,
             file_path.file_name().unwrap().to_string_lossy(),
             dir.path().to_string_lossy(),
-            policy::confirmation_token_hint("i-understand")
+            confirmation_token_hint("i-understand")
         );
 
-        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
-        stream.write_all(plan_with.as_bytes()).await.unwrap();
-        stream.shutdown().await.unwrap();
-
-        let mut out = Vec::new();
-        stream.read_to_end(&mut out).await.unwrap();
-        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+        let response = send_confirmed(&socket_path, &plan_with).await;
         match &response.results[0] {
             ActionResult::Exec(exec) => assert!(exec.ok),
             _ => panic!("unexpected action result type"),
@@ -1742,7 +3079,7 @@ mod tests {
         let socket_path_str = socket_path.to_string_lossy().to_string();
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
-        let server = tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "custom-token").await });
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "custom-token")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1785,13 +3122,7 @@ mod tests {
           "confirmation":{"token":"custom-token"}
         }"#;
 
-        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
-        stream.write_all(plan_good.as_bytes()).await.unwrap();
-        stream.shutdown().await.unwrap();
-
-        let mut out = Vec::new();
-        stream.read_to_end(&mut out).await.unwrap();
-        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+        let response = send_confirmed(&socket_path, plan_good).await;
         match &response.results[0] {
             ActionResult::Exec(exec) => assert!(exec.ok),
             _ => panic!("unexpected action result type"),
@@ -1811,7 +3142,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1866,7 +3197,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1899,7 +3230,7 @@ mod tests {
           "request_id":"req-observe-ps-1",
           "version":"0.1",
           "mode":"execute",
-          "actions":[{"type":"observe","tool":"ps","args":["aux"],"reason":"test","danger":null,"recovery":null}]
+          "actions":[{"type":"observe","tool":"ps","args":[],"reason":"test","danger":null,"recovery":null}]
         }"#;
         let mut stream = UnixStream::connect(&socket_path).await.unwrap();
         stream.write_all(plan_observe.as_bytes()).await.unwrap();
@@ -1933,6 +3264,7 @@ mod tests {
             .unwrap();
         let observe_v: serde_json::Value = serde_json::from_str(observe_line).unwrap();
         assert_eq!(observe_v["result"]["results"][0]["stdout"], "[redacted]");
+        assert_eq!(observe_v["result"]["results"][0]["processes"], "[redacted]");
 
         server.abort();
     }
@@ -1947,7 +3279,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -1989,13 +3321,7 @@ mod tests {
           "confirmation":{"token":"i-understand"}
         }"#;
 
-        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
-        stream.write_all(plan_with.as_bytes()).await.unwrap();
-        stream.shutdown().await.unwrap();
-
-        let mut out = Vec::new();
-        stream.read_to_end(&mut out).await.unwrap();
-        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+        let response = send_confirmed(&socket_path, plan_with).await;
         match &response.results[0] {
             ActionResult::ReadFile(r) => {
                 assert!(r.ok);
@@ -2022,7 +3348,7 @@ mod tests {
         let audit_path_str = audit_path.to_string_lossy().to_string();
 
         let server =
-            tokio::spawn(async move { run(&socket_path_str, &audit_path_str, "i-understand").await });
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
 
         for _ in 0..50u32 {
             if socket_path.exists() {
@@ -2070,22 +3396,854 @@ mod tests {
             out_path.to_string_lossy()
         );
 
+        let response = send_confirmed(&socket_path, &plan_with).await;
+        match &response.results[0] {
+            ActionResult::WriteFile(w) => assert!(w.ok),
+            _ => panic!("unexpected action result type"),
+        }
+
+        assert!(tokio::fs::try_exists(&out_path).await.unwrap());
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_file_policy_uses_the_real_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+        std::os::unix::fs::symlink("/etc/passwd", dir.path().join("passwd-link")).unwrap();
+        std::os::unix::fs::symlink("/etc", dir.path().join("etc-link")).unwrap();
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        // the symlink read is sent on its own, since a plan that still needs confirmation runs nothing.
+        let read = |request_id: &str, path: &str| {
+            format!(
+                r#"{{"request_id":"{request_id}","version":"0.1","mode":"execute","actions":[
+                  {{"type":"read_file","path":"{}/{path}","max_bytes":256,"reason":"test","danger":null,"recovery":null}}
+                ]}}"#,
+                dir.path().to_string_lossy()
+            )
+        };
+        let response = roundtrip(&socket_path, &read("req-realpath-1", "passwd-link")).await;
+        match &response.results[0] {
+            ActionResult::ReadFile(r) => {
+                assert!(!r.ok);
+                assert!(r.content_base64.is_none());
+                let error = r.error.as_ref().unwrap();
+                assert_eq!(error.code, llm_os_common::ActionErrorCode::ReadFailed);
+                assert!(error.message.contains("symlink"));
+            }
+            _ => panic!("unexpected action result type"),
+        }
+        let response = roundtrip(&socket_path, &read("req-realpath-2", "etc-link/passwd")).await;
+        match &response.results[0] {
+            ActionResult::ReadFile(r) => {
+                assert!(!r.ok);
+                assert_eq!(r.resolved_path.as_deref(), Some("/etc/passwd"));
+                assert_eq!(
+                    r.error.as_ref().unwrap().code,
+                    llm_os_common::ActionErrorCode::ConfirmationRequired
+                );
+            }
+            _ => panic!("unexpected action result type"),
+        }
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_rejects_peers_without_a_matching_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+        let policy_path = dir.path().join("policy.toml");
+        let other_uid = unsafe { libc::getuid() }.wrapping_add(1);
+        tokio::fs::write(
+            &policy_path,
+            format!("[[profiles]]\nname = \"someone-else\"\nuids = [{other_uid}]\n"),
+        )
+        .await
+        .unwrap();
+
+        let mut config = Config::new(
+            &socket_path.to_string_lossy(),
+            &audit_path.to_string_lossy(),
+            "i-understand",
+        );
+        config.policy = policy::Policy::load(&policy_path.to_string_lossy()).unwrap();
+
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let plan = r#"{
+          "request_id":"req-profile-1",
+          "version":"0.1",
+          "mode":"plan_only",
+          "actions":[{"type":"observe","tool":"ps","args":[],"reason":"test","danger":null,"recovery":null}]
+        }"#;
+
+        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
+        stream.write_all(plan.as_bytes()).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await.unwrap();
+        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+        assert_eq!(response.request_id, "req-profile-1");
+        assert!(response.results.is_empty());
+        let error = response.error.unwrap();
+        assert_eq!(error.code, ErrorCode::NoMatchingProfile);
+        assert!(error.message.contains("no policy profile matches"));
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_consults_loaded_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+        let policy_path = dir.path().join("policy.toml");
+        tokio::fs::write(
+            &policy_path,
+            r#"
+            [exec]
+            default = "confirm"
+            deny = ["/bin/echo"]
+
+            [service_control]
+            default = "allow"
+            deny = ["ssh.service"]
+            "#,
+        )
+        .await
+        .unwrap();
+
+        let mut config = Config::new(
+            &socket_path.to_string_lossy(),
+            &audit_path.to_string_lossy(),
+            "i-understand",
+        );
+        config.policy = policy::Policy::load(&policy_path.to_string_lossy()).unwrap();
+
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let plan = r#"{
+          "request_id":"req-policy-1",
+          "version":"0.1",
+          "mode":"plan_only",
+          "actions":[
+            {"type":"exec","argv":["/bin/echo","hi"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null},
+            {"type":"service_control","action":"status","unit":"ssh.service","reason":"test","danger":null,"recovery":null}
+          ]
+        }"#;
+
         let mut stream = UnixStream::connect(&socket_path).await.unwrap();
-        stream.write_all(plan_with.as_bytes()).await.unwrap();
+        stream.write_all(plan.as_bytes()).await.unwrap();
         stream.shutdown().await.unwrap();
 
         let mut out = Vec::new();
         stream.read_to_end(&mut out).await.unwrap();
         let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+        assert_eq!(response.results.len(), 2);
         match &response.results[0] {
-            ActionResult::WriteFile(w) => assert!(w.ok),
+            ActionResult::Exec(exec) => {
+                assert!(!exec.ok);
+                assert_eq!(
+                    exec.error.as_ref().unwrap().code,
+                    llm_os_common::ActionErrorCode::PolicyDenied
+                );
+            }
+            _ => panic!("unexpected action result type"),
+        }
+        match &response.results[1] {
+            ActionResult::ServiceControl(r) => {
+                assert!(!r.ok);
+                assert_eq!(
+                    r.error.as_ref().unwrap().code,
+                    llm_os_common::ActionErrorCode::PolicyDenied
+                );
+            }
             _ => panic!("unexpected action result type"),
         }
-
-        assert!(tokio::fs::try_exists(&out_path).await.unwrap());
 
         server.abort();
     }
-}
 
+    #[tokio::test]
+    async fn server_exec_applies_argument_rules_before_spawning() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+        let scratch = dir.path().join("scratch");
+        std::fs::create_dir(&scratch).unwrap();
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let plan = format!(
+            r#"{{
+              "request_id":"req-rm-args-1",
+              "version":"0.1",
+              "mode":"execute",
+              "actions":[
+                {{"type":"exec","argv":["rm","-rf","/llm-osd-test-missing"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}},
+                {{"type":"exec","argv":["rm","-rf","{}"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}}
+              ],
+              "confirmation":{{"token":"i-understand"}}
+            }}"#,
+            scratch.to_string_lossy()
+        );
 
+        let response = send_confirmed(&socket_path, &plan).await;
+        match &response.results[0] {
+            ActionResult::Exec(exec) => {
+                assert!(!exec.ok);
+                assert_eq!(
+                    exec.error.as_ref().unwrap().code,
+                    llm_os_common::ActionErrorCode::PolicyDenied
+                );
+                assert!(exec.exit_code.is_none());
+                let detail = exec.error.as_ref().unwrap().policy.as_ref().unwrap();
+                assert_eq!(detail.rule_id, "exec.rules:rm-recursive-outside-tmp");
+                assert_eq!(detail.source, "builtin");
+                assert_eq!(detail.decision, llm_os_common::PolicyDecision::Deny);
+            }
+            _ => panic!("unexpected action result type"),
+        }
+        match &response.results[1] {
+            ActionResult::Exec(exec) => assert!(exec.ok),
+            _ => panic!("unexpected action result type"),
+        }
+        assert!(!scratch.exists());
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_exec_inspects_shell_wrappers() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let plan = r#"{
+          "request_id":"req-shell-1",
+          "version":"0.1",
+          "mode":"execute",
+          "actions":[
+            {"type":"exec","argv":["/bin/sh","-c","echo ok; dd if=/dev/zero of=/dev/null count=1"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null},
+            {"type":"exec","argv":["env","FOO=1","timeout","5","dd","count=1"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}
+          ],
+          "confirmation":{"token":"i-understand"}
+        }"#;
+
+        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
+        stream.write_all(plan.as_bytes()).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await.unwrap();
+        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+        assert_eq!(response.results.len(), 2);
+        for result in &response.results {
+            match result {
+                ActionResult::Exec(exec) => {
+                    assert!(!exec.ok);
+                    assert_eq!(
+                        exec.error.as_ref().unwrap().code,
+                        llm_os_common::ActionErrorCode::PolicyDenied
+                    );
+                    assert!(exec.stdout.is_empty());
+                }
+                _ => panic!("unexpected action result type"),
+            }
+        }
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_exec_refuses_injected_shell_functions() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+        let policy_path = dir.path().join("policy.toml");
+        let marker = dir.path().join("injected");
+        tokio::fs::write(
+            &policy_path,
+            r#"
+            [exec]
+            default = "deny"
+            unparsed = "deny"
+            allow = ["bash", "echo"]
+            "#,
+        )
+        .await
+        .unwrap();
+
+        let mut config = Config::new(
+            &socket_path.to_string_lossy(),
+            &audit_path.to_string_lossy(),
+            "i-understand",
+        );
+        config.policy = policy::Policy::load(&policy_path.to_string_lossy()).unwrap();
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let plan = format!(
+            r#"{{
+              "request_id":"req-bash-func-1",
+              "version":"0.1",
+              "mode":"execute",
+              "actions":[
+                {{"type":"exec","argv":["bash","-c","echo hi"],"cwd":null,"env":{{"BASH_FUNC_echo%%":"() {{ /usr/bin/touch {}; }}"}},"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}}
+              ]
+            }}"#,
+            marker.display()
+        );
+        let response = roundtrip(&socket_path, &plan).await;
+        match &response.results[0] {
+            ActionResult::Exec(exec) => {
+                assert!(!exec.ok);
+                assert_eq!(
+                    exec.error.as_ref().unwrap().code,
+                    llm_os_common::ActionErrorCode::PolicyDenied
+                );
+                assert!(exec.stdout.is_empty());
+            }
+            _ => panic!("unexpected action result type"),
+        }
+        assert!(!marker.exists());
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_exec_denies_path_variants_of_denied_program() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+
+        let server =
+            tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let dd = std::fs::canonicalize("/bin/dd").unwrap();
+        let dd_dir = dd.parent().unwrap().to_string_lossy().to_string();
+        for argv0 in ["/usr/bin/dd", "/bin/../bin/dd", "./dd"] {
+            let plan = format!(
+                r#"{{
+                  "request_id":"req-dd-variant-1",
+                  "version":"0.1",
+                  "mode":"execute",
+                  "actions":[{{"type":"exec","argv":["{}","--version"],"cwd":"{}","env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}}],
+                  "confirmation":{{"token":"i-understand"}}
+                }}"#,
+                argv0, dd_dir
+            );
+
+            let mut stream = UnixStream::connect(&socket_path).await.unwrap();
+            stream.write_all(plan.as_bytes()).await.unwrap();
+            stream.shutdown().await.unwrap();
+
+            let mut out = Vec::new();
+            stream.read_to_end(&mut out).await.unwrap();
+            let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+            match &response.results[0] {
+                ActionResult::Exec(exec) => {
+                    assert!(!exec.ok, "{argv0}");
+                    assert_eq!(
+                        exec.error.as_ref().unwrap().code,
+                        llm_os_common::ActionErrorCode::PolicyDenied
+                    );
+                    assert_eq!(exec.resolved_path.as_deref(), Some(dd.to_str().unwrap()));
+                }
+                _ => panic!("unexpected action result type"),
+            }
+        }
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_explain_reports_traces_without_executing() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+        let scratch = dir.path().join("scratch");
+        std::fs::create_dir(&scratch).unwrap();
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let plan = format!(
+            r#"{{
+              "request_id":"req-explain-1",
+              "version":"0.1",
+              "mode":"explain",
+              "actions":[
+                {{"type":"exec","argv":["sh","-c","rm -rf {}; dd if=/dev/zero of=/dev/null"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}},
+                {{"type":"ping"}}
+              ]
+            }}"#,
+            scratch.to_string_lossy()
+        );
+
+        let mut stream = UnixStream::connect(&socket_path).await.unwrap();
+        stream.write_all(plan.as_bytes()).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut out = Vec::new();
+        stream.read_to_end(&mut out).await.unwrap();
+        let response: ActionPlanResult = serde_json::from_slice(&out).unwrap();
+        assert!(!response.executed);
+        assert!(response.results.is_empty());
+
+        let explanations = response.explanations.unwrap();
+        assert_eq!(explanations.len(), 2);
+        assert_eq!(explanations[0].action, "exec");
+        assert_eq!(explanations[0].decision, llm_os_common::PolicyDecision::Deny);
+        let rules: Vec<&str> = explanations[0].trace.iter().map(|step| step.rule_id.as_str()).collect();
+        assert!(rules.contains(&"exec.deny:/bin/dd"), "{rules:?}");
+        assert!(explanations[0].trace.iter().any(|step| step.hint.starts_with("via sh: ")));
+        assert_eq!(explanations[1].action, "ping");
+        assert!(explanations[1].trace.is_empty());
+        assert!(scratch.exists());
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_confirmation_nonces_are_single_use_and_plan_bound() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let plan = |argv0: &str, nonce: Option<&str>| {
+            let nonce = nonce.map(|n| format!(r#""{n}""#)).unwrap_or_else(|| "null".to_string());
+            format!(
+                r#"{{
+                  "request_id":"req-nonce-1",
+                  "version":"0.1",
+                  "mode":"execute",
+                  "actions":[{{"type":"exec","argv":["{argv0}"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}}],
+                  "confirmation":{{"token":"i-understand","nonce":{nonce}}}
+                }}"#
+            )
+        };
+        let requires_confirmation = |response: &ActionPlanResult| match &response.results[0] {
+            ActionResult::Exec(exec) => {
+                exec.error.as_ref().map(|e| e.code.clone())
+                    == Some(llm_os_common::ActionErrorCode::ConfirmationRequired)
+            }
+            _ => panic!("unexpected action result type"),
+        };
+
+        // the static token alone no longer confirms anything.
+        let first = roundtrip(&socket_path, &plan("/usr/bin/true", None)).await;
+        assert!(requires_confirmation(&first));
+        let challenge = first.challenge.unwrap();
+        assert_eq!(challenge.plan_hash.len(), 64);
+
+        // a nonce issued for one plan does not confirm a different plan, and is spent by the attempt.
+        let other = roundtrip(&socket_path, &plan("/usr/bin/false", Some(&challenge.nonce))).await;
+        assert!(requires_confirmation(&other));
+        let spent = roundtrip(&socket_path, &plan("/usr/bin/true", Some(&challenge.nonce))).await;
+        assert!(requires_confirmation(&spent));
+
+        let fresh = spent.challenge.unwrap();
+        assert_ne!(fresh.nonce, challenge.nonce);
+        let confirmed = roundtrip(&socket_path, &plan("/usr/bin/true", Some(&fresh.nonce))).await;
+        match &confirmed.results[0] {
+            ActionResult::Exec(exec) => assert!(exec.ok),
+            _ => panic!("unexpected action result type"),
+        }
+        assert!(confirmed.challenge.is_none());
+
+        let replayed = roundtrip(&socket_path, &plan("/usr/bin/true", Some(&fresh.nonce))).await;
+        assert!(requires_confirmation(&replayed));
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_locks_out_repeated_confirmation_failures_and_audits_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let plan = |token: &str, nonce: Option<&str>| {
+            let nonce = nonce.map(|n| format!(r#""{n}""#)).unwrap_or_else(|| "null".to_string());
+            format!(
+                r#"{{
+                  "request_id":"req-lockout-1",
+                  "version":"0.1",
+                  "mode":"execute",
+                  "actions":[{{"type":"exec","argv":["/usr/bin/true"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}}],
+                  "confirmation":{{"token":"{token}","nonce":{nonce}}}
+                }}"#
+            )
+        };
+        let message = |response: &ActionPlanResult| response.results[0].error().unwrap().message.clone();
+
+        let mut response = roundtrip(&socket_path, &plan("guess", None)).await;
+        for _ in 0..4 {
+            let nonce = response.challenge.expect("challenge").nonce;
+            response = roundtrip(&socket_path, &plan("guess", Some(&nonce))).await;
+        }
+        assert!(message(&response).starts_with("confirmation locked out until "));
+        assert!(response.challenge.is_none());
+
+        // the right token does not help while locked out, and no new challenge is issued.
+        let locked = roundtrip(&socket_path, &plan("i-understand", None)).await;
+        assert!(message(&locked).starts_with("confirmation locked out until "));
+        assert!(locked.challenge.is_none());
+
+        let mut outcomes = Vec::new();
+        for _ in 0..50u32 {
+            let audit_text = tokio::fs::read_to_string(&audit_path).await.unwrap_or_default();
+            outcomes = audit_text
+                .lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["confirmation"].clone())
+                .collect();
+            if outcomes.len() == 6 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        // records are appended after each response is sent, so their order is not guaranteed.
+        outcomes.sort_by_key(|outcome| outcome.to_string());
+        let bad_token = serde_json::Value::from("bad_token");
+        assert_eq!(
+            outcomes,
+            vec![
+                bad_token.clone(),
+                bad_token.clone(),
+                bad_token.clone(),
+                bad_token,
+                serde_json::Value::Null,
+                serde_json::Value::Null,
+            ]
+        );
+
+        server.abort();
+    }
+
+    // sends a plan from a thread whose uid alone is switched, so the daemon sees a second peer uid.
+    async fn roundtrip_as(uid: u32, socket_path: &Path, plan: &str) -> ActionPlanResult {
+        let socket_path = socket_path.to_path_buf();
+        let plan = plan.to_string();
+        let thread = std::thread::spawn(move || {
+            use std::io::{Read, Write};
+            let rc = unsafe { libc::syscall(libc::SYS_setresuid, uid, uid, 0u32) };
+            assert_eq!(rc, 0, "setresuid: {}", std::io::Error::last_os_error());
+            let mut stream = std::os::unix::net::UnixStream::connect(&socket_path).unwrap();
+            stream.write_all(plan.as_bytes()).unwrap();
+            stream.shutdown(std::net::Shutdown::Write).unwrap();
+            let mut out = Vec::new();
+            stream.read_to_end(&mut out).unwrap();
+            out
+        });
+        while !thread.is_finished() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        serde_json::from_slice(&thread.join().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn server_parks_plans_until_a_second_uid_approves() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+        let etc = dir.path().join("etc");
+        std::fs::create_dir(&etc).unwrap();
+        let etc = std::fs::canonicalize(&etc).unwrap();
+        let target = etc.join("motd");
+        let unlisted = dir.path().join("unlisted");
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+        let mut config = Config::new(&socket_path_str, &audit_path_str, "i-understand");
+        config.policy = serde_json::from_str(&format!(
+            r#"{{"write_file":{{"default":"confirm","parent_dir":"confirm","allow":[],"approve":["{}"]}}}}"#,
+            etc.display()
+        ))
+        .unwrap();
+        let server = tokio::spawn(async move { run(config).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o777)).unwrap();
+
+        let write = |path: &Path| {
+            format!(
+                r#"{{"type":"write_file","path":"{}","content":"hello","mode":"0644","reason":"test","danger":null,"recovery":null}}"#,
+                path.display()
+            )
+        };
+        let plan = format!(
+            r#"{{"request_id":"req-approve-1","version":"0.1","mode":"execute","actions":[{}]}}"#,
+            write(&target)
+        );
+        let approval = |op: &str| {
+            format!(
+                r#"{{"request_id":"req-approvals-{op}","version":"0.1","mode":"execute","actions":[
+                  {{"type":"approval","op":"{op}","request_id":"req-approve-1","note":null}}
+                ]}}"#
+            )
+        };
+        let approval_result = |response: &ActionPlanResult| match &response.results[0] {
+            ActionResult::Approval(result) => result.clone(),
+            _ => panic!("unexpected action result type"),
+        };
+
+        // an action that needs no approval cannot ride along in a parked plan.
+        let mixed = format!(
+            r#"{{"request_id":"req-approve-0","version":"0.1","mode":"execute","actions":[{},{}]}}"#,
+            write(&target),
+            write(&unlisted)
+        );
+        let rejected = roundtrip(&socket_path, &mixed).await;
+        assert!(!rejected.executed);
+        assert!(rejected.approval.is_none());
+        let error = rejected.error.unwrap();
+        assert_eq!(error.code, ErrorCode::MixedApprovalPlan);
+        assert_eq!(error.message, "actions 1 do not need approval; send them in a plan of their own");
+
+        let parked = roundtrip(&socket_path, &plan).await;
+        assert!(!parked.executed);
+        assert!(parked.results.is_empty());
+        let status = parked.approval.clone().unwrap();
+        assert_eq!(status.state, llm_os_common::ApprovalState::Pending);
+        let explanation = &parked.explanations.as_ref().unwrap()[0];
+        assert_eq!(explanation.decision, llm_os_common::PolicyDecision::Approve);
+        assert!(!target.exists());
+        assert_eq!(roundtrip(&socket_path, &plan).await.approval, Some(status));
+
+        let own = approval_result(&roundtrip(&socket_path, &approval("approve")).await);
+        assert!(!own.ok);
+        assert!(own.error.unwrap().message.contains("cannot also decide"));
+
+        if unsafe { libc::geteuid() } != 0 {
+            server.abort();
+            return;
+        }
+        let approved = approval_result(&roundtrip_as(65534, &socket_path, &approval("approve")).await);
+        assert!(approved.ok, "{approved:?}");
+        let entry = &approved.entries[0];
+        assert_eq!(entry.state, llm_os_common::ApprovalState::Approved);
+        assert_eq!(entry.decided_by, Some(65534));
+        let executed = entry.result.as_ref().unwrap();
+        assert!(executed.executed);
+        assert!(executed.results[0].error().is_none(), "{executed:?}");
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "hello");
+        assert!(!unlisted.exists());
+
+        let shown = approval_result(&roundtrip(&socket_path, &approval("show")).await);
+        assert!(shown.entries[0].result.is_some());
+        let shown_plan = serde_json::to_value(shown.entries[0].plan.as_ref().unwrap()).unwrap();
+        assert_eq!(shown_plan["actions"][0]["content"], "hello");
+        let reviewed = approval_result(&roundtrip_as(65534, &socket_path, &approval("show")).await);
+        let reviewed_plan = serde_json::to_value(reviewed.entries[0].plan.as_ref().unwrap()).unwrap();
+        assert_eq!(reviewed_plan["actions"][0]["content"], "[redacted]");
+        let again = approval_result(&roundtrip_as(65534, &socket_path, &approval("reject")).await);
+        assert_eq!(again.error.unwrap().message, "req-approve-1 is already approved");
+
+        // the parked plan is audited when parked and again when it runs, under the requester's uid.
+        let mut parked_plans = Vec::new();
+        for _ in 0..50u32 {
+            let audit_text = tokio::fs::read_to_string(&audit_path).await.unwrap_or_default();
+            parked_plans = audit_text
+                .lines()
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .filter(|record| record["request_id"] == "req-approve-1")
+                .collect();
+            if parked_plans.len() == 3 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let ran = parked_plans
+            .iter()
+            .find(|record| record["result"]["executed"] == true)
+            .expect("audit record for the approved run");
+        assert_eq!(ran["peer"]["uid"], 0);
+        assert_eq!(ran["result"]["approval"]["decided_by"], 65534);
+        assert_eq!(ran["plan"]["actions"][0]["content"], "[redacted]");
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn server_session_grants_stand_in_for_confirmation_within_their_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("llm-osd.sock");
+        let audit_path = dir.path().join("audit.jsonl");
+
+        let socket_path_str = socket_path.to_string_lossy().to_string();
+        let audit_path_str = audit_path.to_string_lossy().to_string();
+        let server = tokio::spawn(async move { run(Config::new(&socket_path_str, &audit_path_str, "i-understand")).await });
+
+        for _ in 0..50u32 {
+            if socket_path.exists() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        let exec = |session: &str| {
+            format!(
+                r#"{{"request_id":"req-exec","session_id":"{session}","version":"0.1","mode":"execute","actions":[
+                  {{"type":"ping"}},
+                  {{"type":"exec","argv":["/usr/bin/true","status"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"test","danger":null,"recovery":null}}
+                ]}}"#
+            )
+        };
+        let session_grant = |op: &str, fields: &str| {
+            format!(
+                r#"{{"request_id":"req-session-{op}","session_id":"sess-1","version":"0.1","mode":"execute","actions":[
+                  {{"type":"session_grant","op":"{op}",{fields}}}
+                ]}}"#
+            )
+        };
+        let grants = |response: &ActionPlanResult| match &response.results[0] {
+            ActionResult::SessionGrant(result) => result.clone(),
+            _ => panic!("unexpected action result type"),
+        };
+        let exec_code = |response: &ActionPlanResult| response.results[1].error().map(|e| e.code.clone());
+
+        let before = roundtrip(&socket_path, &exec("sess-1")).await;
+        assert_eq!(exec_code(&before), Some(llm_os_common::ActionErrorCode::ConfirmationRequired));
+
+        let grant = r#"{"request_id":"req-session-grant","session_id":"sess-1","version":"0.1","mode":"execute","actions":[
+          {"type":"session_grant","op":"grant","scope":{"type":"exec","program":"/usr/bin/true","args":["status*"]},"ttl_sec":600,"grant_id":null,"note":"triage"}
+        ],"confirmation":{"token":"i-understand","nonce":null}}"#;
+        let unconfirmed = grants(&roundtrip(&socket_path, &grant.replace("i-understand", "wrong")).await);
+        assert_eq!(unconfirmed.error.unwrap().code, llm_os_common::ActionErrorCode::ConfirmationRequired);
+        let granted = grants(&send_confirmed(&socket_path, grant).await);
+        assert!(granted.ok, "{granted:?}");
+        let grant_id = granted.grants[0].grant_id.clone();
+
+        let covered = roundtrip(&socket_path, &exec("sess-1")).await;
+        assert_eq!(exec_code(&covered), None, "{covered:?}");
+        assert_eq!(
+            covered.grants_applied,
+            Some(vec![llm_os_common::GrantUse {
+                index: 1,
+                grant_id: grant_id.clone()
+            }])
+        );
+        let other = roundtrip(&socket_path, &exec("sess-2")).await;
+        assert_eq!(exec_code(&other), Some(llm_os_common::ActionErrorCode::ConfirmationRequired));
+        assert!(other.grants_applied.is_none());
+        // the grant vouches for the argv only, so a loader variable on top of it still needs confirmation.
+        let preload = exec("sess-1").replace(r#""env":null"#, r#""env":{"LD_PRELOAD":"/tmp/evil.so"}"#);
+        let preloaded = roundtrip(&socket_path, &preload).await;
+        assert_eq!(exec_code(&preloaded), Some(llm_os_common::ActionErrorCode::ConfirmationRequired));
+        assert!(preloaded.grants_applied.is_none());
+
+        let listed = grants(&roundtrip(&socket_path, &session_grant("list", r#""scope":null,"ttl_sec":null,"grant_id":null,"note":null"#)).await);
+        assert_eq!(listed.grants.len(), 1);
+        let revoke = session_grant("revoke", &format!(r#""scope":null,"ttl_sec":null,"grant_id":"{grant_id}","note":null"#));
+        assert!(grants(&roundtrip(&socket_path, &revoke).await).ok);
+        let revoked = roundtrip(&socket_path, &exec("sess-1")).await;
+        assert_eq!(exec_code(&revoked), Some(llm_os_common::ActionErrorCode::ConfirmationRequired));
+
+        server.abort();
+    }
+}
@@ -1,7 +1,13 @@
 // ABOUTME: provides llmsh helpers for parsing and validating action plans before sending them.
 // ABOUTME: keeps client behavior deterministic by enforcing local validation and mode checks.
 
-use llm_os_common::{parse_action_plan, validate_action_plan, ActionPlan, ErrorCode, Mode, RequestError};
+use llm_os_common::{
+    parse_action_plan, validate_action_plan, Action, ActionErrorCode, ActionPlan, ActionPlanResult,
+    ActionResult, ApprovalAction, ApprovalOp, ApprovalState, ApprovalStatus, Confirmation, ErrorCode,
+    GrantScope, Mode, PolicyDecision, RequestError, SessionGrantAction,
+};
+
+pub mod policy_cases;
 
 pub fn apply_overrides(
     mut plan: ActionPlan,
@@ -86,6 +92,253 @@ pub fn parse_and_validate_for_send_with_overrides(
     Ok(plan)
 }
 
+// explain requests reuse whatever mode the plan names; the daemon never executes them.
+pub fn parse_and_validate_for_explain(
+    input: &str,
+    request_id: Option<&str>,
+    session_id: Option<&str>,
+) -> anyhow::Result<ActionPlan> {
+    let plan = parse_action_plan(input)?;
+    let mut plan = apply_overrides(plan, request_id, session_id)?;
+    plan.mode = Mode::Explain;
+    validate_action_plan(&plan).map_err(|e| anyhow::anyhow!(e.message))?;
+    Ok(plan)
+}
+
+pub fn decision_name(decision: &PolicyDecision) -> &'static str {
+    match decision {
+        PolicyDecision::Allow => "allow",
+        PolicyDecision::Confirm => "confirm",
+        PolicyDecision::Approve => "approve",
+        PolicyDecision::Deny => "deny",
+    }
+}
+
+// one line per action followed by one indented line per consulted rule.
+pub fn render_explanations(response: &ActionPlanResult) -> anyhow::Result<String> {
+    if let Some(error) = &response.error {
+        return Err(anyhow::anyhow!("{:?}: {}", error.code, error.message));
+    }
+    let explanations = response
+        .explanations
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("daemon response has no explanations"))?;
+
+    let mut out = String::new();
+    for (index, explanation) in explanations.iter().enumerate() {
+        out.push_str(&format!(
+            "[{index}] {}: {}\n",
+            explanation.action,
+            decision_name(&explanation.decision)
+        ));
+        for step in &explanation.trace {
+            out.push_str(&format!(
+                "    {:<7} {} ({}): {}\n",
+                decision_name(&step.decision),
+                step.rule_id,
+                step.source,
+                step.hint
+            ));
+        }
+    }
+    Ok(out)
+}
+
+// one action the daemon refused to run without confirmation, with the text a human needs to decide.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PendingConfirmation {
+    pub index: usize,
+    pub summary: String,
+    pub reason: String,
+    pub danger: Option<String>,
+    pub recovery: Option<String>,
+    pub hint: Option<String>,
+}
+
+pub fn pending_confirmations(plan: &ActionPlan, response: &ActionPlanResult) -> Vec<PendingConfirmation> {
+    plan.actions
+        .iter()
+        .zip(&response.results)
+        .enumerate()
+        .filter_map(|(index, (action, result))| {
+            let error = result.error()?;
+            if error.code != ActionErrorCode::ConfirmationRequired {
+                return None;
+            }
+            let (summary, reason, danger, recovery) = describe_action(action);
+            Some(PendingConfirmation {
+                index,
+                summary,
+                reason: reason.to_string(),
+                danger: danger.cloned(),
+                recovery: recovery.cloned(),
+                hint: error.policy.as_ref().map(|p| format!("{}: {}", p.rule_id, p.hint)),
+            })
+        })
+        .collect()
+}
+
+fn describe_action(action: &Action) -> (String, &str, Option<&String>, Option<&String>) {
+    match action {
+        Action::Exec(a) => (format!("exec {}", a.argv.join(" ")), &a.reason, a.danger.as_ref(), a.recovery.as_ref()),
+        Action::ReadFile(a) => (format!("read_file {}", a.path), &a.reason, a.danger.as_ref(), a.recovery.as_ref()),
+        Action::WriteFile(a) => (format!("write_file {}", a.path), &a.reason, a.danger.as_ref(), a.recovery.as_ref()),
+        Action::ServiceControl(a) => (
+            format!("service_control {} {}", wire_name(&a.action), a.unit),
+            &a.reason,
+            a.danger.as_ref(),
+            a.recovery.as_ref(),
+        ),
+        Action::InstallPackages(a) => (
+            format!("install_packages {}", a.packages.join(" ")),
+            &a.reason,
+            a.danger.as_ref(),
+            a.recovery.as_ref(),
+        ),
+        Action::RemovePackages(a) => (
+            format!("remove_packages {}", a.packages.join(" ")),
+            &a.reason,
+            a.danger.as_ref(),
+            a.recovery.as_ref(),
+        ),
+        Action::UpdateSystem(a) => (
+            format!("update_system {}", wire_name(&a.scope.unwrap_or_default())),
+            &a.reason,
+            a.danger.as_ref(),
+            a.recovery.as_ref(),
+        ),
+        Action::Observe(a) => (
+            format!("observe {} {}", a.name.clone().unwrap_or_else(|| wire_name(&a.tool)), a.args.join(" ")),
+            &a.reason,
+            a.danger.as_ref(),
+            a.recovery.as_ref(),
+        ),
+        Action::LogQuery(a) => (
+            format!("log_query {}", a.unit.as_deref().unwrap_or("all units")),
+            &a.reason,
+            a.danger.as_ref(),
+            a.recovery.as_ref(),
+        ),
+        Action::CgroupApply(a) => ("cgroup_apply".to_string(), &a.reason, a.danger.as_ref(), a.recovery.as_ref()),
+        Action::CgroupInspect(a) => ("cgroup_inspect".to_string(), &a.reason, a.danger.as_ref(), a.recovery.as_ref()),
+        Action::FirmwareOp(a) => (
+            format!("firmware_op {}", wire_name(&a.op)),
+            &a.reason,
+            a.danger.as_ref(),
+            a.recovery.as_ref(),
+        ),
+        Action::Approval(a) => (
+            format!("approval {} {}", wire_name(&a.op), a.request_id.as_deref().unwrap_or("")),
+            a.note.as_deref().unwrap_or(""),
+            None,
+            None,
+        ),
+        Action::SessionGrant(a) => (
+            format!("session_grant {}{}", wire_name(&a.op), describe_grant(a)),
+            a.note.as_deref().unwrap_or(""),
+            None,
+            None,
+        ),
+        Action::Ping => ("ping".to_string(), "", None, None),
+    }
+}
+
+fn describe_grant(grant: &SessionGrantAction) -> String {
+    match (&grant.scope, &grant.grant_id) {
+        (Some(GrantScope::Exec(exec)), _) => match &exec.args {
+            Some(args) => format!(" exec {} {}", exec.program, args.join(" ")),
+            None => format!(" exec {}", exec.program),
+        },
+        (Some(GrantScope::ReadFile(path)), _) => format!(" read_file {}", path.root),
+        (Some(GrantScope::WriteFile(path)), _) => format!(" write_file {}", path.root),
+        (None, Some(grant_id)) => format!(" {grant_id}"),
+        (None, None) => String::new(),
+    }
+}
+
+// the snake_case name an enum has on the wire.
+fn wire_name<T: serde::Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => "unknown".to_string(),
+    }
+}
+
+pub fn render_confirmation_prompt(pending: &[PendingConfirmation]) -> String {
+    let mut out = String::from("the daemon requires confirmation for:\n");
+    for p in pending {
+        out.push_str(&format!("  [{}] {}\n", p.index, p.summary));
+        out.push_str(&format!("      reason:   {}\n", p.reason));
+        out.push_str(&format!("      danger:   {}\n", p.danger.as_deref().unwrap_or("(none given)")));
+        out.push_str(&format!("      recovery: {}\n", p.recovery.as_deref().unwrap_or("(none given)")));
+        if let Some(hint) = &p.hint {
+            out.push_str(&format!("      policy:   {hint}\n"));
+        }
+    }
+    out
+}
+
+pub fn attach_confirmation(mut plan: ActionPlan, token: &str, nonce: &str) -> ActionPlan {
+    plan.confirmation = Some(Confirmation {
+        token: token.to_string(),
+        nonce: Some(nonce.to_string()),
+    });
+    plan
+}
+
+// a single-action plan that operates the daemon's approval queue.
+pub fn approval_plan(op: ApprovalOp, request_id: Option<&str>, note: Option<&str>) -> anyhow::Result<ActionPlan> {
+    let plan = ActionPlan {
+        request_id: format!("req-approvals-{}", wire_name(&op)),
+        session_id: None,
+        version: "0.1".to_string(),
+        mode: Mode::Execute,
+        actions: vec![Action::Approval(ApprovalAction {
+            op,
+            request_id: request_id.map(|id| id.to_string()),
+            note: note.map(|n| n.to_string()),
+        })],
+        confirmation: None,
+    };
+    validate_action_plan(&plan).map_err(|e| anyhow::anyhow!(e.message))?;
+    Ok(plan)
+}
+
+// a single-action plan that grants, lists or revokes confirmation grants in one session.
+pub fn session_grant_plan(session_id: &str, grant: SessionGrantAction) -> anyhow::Result<ActionPlan> {
+    let plan = ActionPlan {
+        request_id: format!("req-session-{}", wire_name(&grant.op)),
+        session_id: Some(session_id.to_string()),
+        version: "0.1".to_string(),
+        mode: Mode::Execute,
+        actions: vec![Action::SessionGrant(grant)],
+        confirmation: None,
+    };
+    validate_action_plan(&plan).map_err(|e| anyhow::anyhow!(e.message))?;
+    Ok(plan)
+}
+
+// the parked plan from a `show` response once waiting is over: decided, and executed if approved.
+pub fn settled_approval(response: &ActionPlanResult) -> anyhow::Result<Option<&ApprovalStatus>> {
+    if let Some(error) = &response.error {
+        return Err(anyhow::anyhow!("{}: {}", wire_name(&error.code), error.message));
+    }
+    let status = match response.results.first() {
+        Some(ActionResult::Approval(result)) => match (&result.error, result.entries.first()) {
+            (Some(error), _) => return Err(anyhow::anyhow!("{}: {}", wire_name(&error.code), error.message)),
+            (None, Some(status)) => status,
+            (None, None) => return Err(anyhow::anyhow!("daemon returned no approval entry")),
+        },
+        _ => return Err(anyhow::anyhow!("daemon response has no approval result")),
+    };
+    let settled = match status.state {
+        ApprovalState::Pending => false,
+        ApprovalState::Approved => status.result.is_some(),
+        ApprovalState::Rejected | ApprovalState::Expired => true,
+    };
+    Ok(settled.then_some(status))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -172,6 +425,132 @@ mod tests {
         let plan = parse_and_validate_for_send_with_overrides(input, Some("req-1"), None).unwrap();
         assert_eq!(plan.request_id, "req-1");
     }
-}
 
+    #[test]
+    fn explain_forces_explain_mode() {
+        let input = r#"{
+          "request_id":"req-1",
+          "version":"0.1",
+          "mode":"execute",
+          "actions":[{"type":"ping"}]
+        }"#;
+
+        let plan = parse_and_validate_for_explain(input, Some("req-2"), None).unwrap();
+        assert_eq!(plan.mode, Mode::Explain);
+        assert_eq!(plan.request_id, "req-2");
+    }
+
+    #[test]
+    fn renders_one_block_per_action() {
+        let response: ActionPlanResult = serde_json::from_str(
+            r#"{
+              "request_id":"req-1",
+              "executed":false,
+              "results":[],
+              "explanations":[
+                {"action":"exec","decision":"deny","trace":[
+                  {"rule_id":"exec.deny:dd","source":"builtin","decision":"deny","hint":"dd matches the deny list"}
+                ]},
+                {"action":"ping","decision":"allow","trace":[]}
+              ],
+              "error":null
+            }"#,
+        )
+        .unwrap();
+
+        let out = render_explanations(&response).unwrap();
+        assert_eq!(
+            out,
+            "[0] exec: deny\n    deny    exec.deny:dd (builtin): dd matches the deny list\n[1] ping: allow\n"
+        );
+    }
+
+    #[test]
+    fn collects_only_actions_awaiting_confirmation() {
+        let plan = parse_and_validate(
+            r#"{
+              "request_id":"req-1",
+              "version":"0.1",
+              "mode":"execute",
+              "actions":[
+                {"type":"ping"},
+                {"type":"exec","argv":["rm","-r","/tmp/x"],"cwd":null,"env":null,"timeout_sec":5,"as_root":false,"reason":"clean up","danger":"deletes files","recovery":"restore from backup"}
+              ],
+              "confirmation":{"token":"t","nonce":null}
+            }"#,
+        )
+        .unwrap();
+        let response: ActionPlanResult = serde_json::from_str(
+            r#"{
+              "request_id":"req-1",
+              "executed":true,
+              "results":[
+                {"type":"pong","ok":true},
+                {"type":"exec","ok":false,"resolved_path":"/usr/bin/rm","exit_code":null,"stdout":"","stdout_truncated":false,"stderr":"","stderr_truncated":false,
+                 "error":{"code":"confirmation_required","message":"confirmation required","policy":{"rule_id":"exec.confirm:rm","source":"builtin","decision":"confirm","hint":"rm matches"}}}
+              ],
+              "explanations":null,
+              "challenge":{"nonce":"abc","plan_hash":"h","expires_at_ms":1},
+              "error":null
+            }"#,
+        )
+        .unwrap();
+
+        let pending = pending_confirmations(&plan, &response);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].index, 1);
+        assert_eq!(pending[0].summary, "exec rm -r /tmp/x");
+
+        let prompt = render_confirmation_prompt(&pending);
+        assert!(prompt.contains("      danger:   deletes files\n"));
+        assert!(prompt.contains("      policy:   exec.confirm:rm: rm matches\n"));
 
+        let confirmed = attach_confirmation(plan, "t", "abc");
+        assert_eq!(confirmed.confirmation.unwrap().nonce.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn approval_plans_validate_and_waiting_ends_once_decided() {
+        let plan = approval_plan(ApprovalOp::Approve, Some("req-9"), Some("looks fine")).unwrap();
+        assert_eq!(plan.request_id, "req-approvals-approve");
+        assert!(approval_plan(ApprovalOp::Show, None, None).is_err());
+
+        let response = |state: &str, result: &str| -> ActionPlanResult {
+            serde_json::from_str(&format!(
+                r#"{{"request_id":"req-approvals-show","executed":true,"results":[{{"type":"approval","ok":true,"entries":[
+                  {{"request_id":"req-9","state":"{state}","requested_by":1000,"requested_at_ms":0,"expires_at_ms":1,
+                    "decided_by":null,"decided_at_ms":null,"note":null,"plan":null,"result":{result}}}
+                ],"error":null}}],"explanations":null,"challenge":null,"approval":null,"error":null}}"#
+            ))
+            .unwrap()
+        };
+        assert!(settled_approval(&response("pending", "null")).unwrap().is_none());
+        assert!(settled_approval(&response("approved", "null")).unwrap().is_none());
+        let done = r#"{"request_id":"req-9","executed":true,"results":[],"explanations":null,"challenge":null,"approval":null,"error":null}"#;
+        assert!(settled_approval(&response("approved", done)).unwrap().is_some());
+        assert!(settled_approval(&response("rejected", "null")).unwrap().is_some());
+    }
+
+    #[test]
+    fn session_grant_plans_carry_the_session_and_describe_the_scope() {
+        let grant = SessionGrantAction {
+            op: llm_os_common::SessionGrantOp::Grant,
+            scope: Some(GrantScope::Exec(llm_os_common::ExecGrant {
+                program: "/usr/bin/systemctl".to_string(),
+                args: Some(vec!["status".to_string(), "**".to_string()]),
+            })),
+            ttl_sec: Some(600),
+            grant_id: None,
+            note: Some("triage".to_string()),
+        };
+        let plan = session_grant_plan("sess-1", grant.clone()).unwrap();
+        assert_eq!(plan.request_id, "req-session-grant");
+        assert_eq!(plan.session_id.as_deref(), Some("sess-1"));
+        let (summary, reason, _, _) = describe_action(&plan.actions[0]);
+        assert_eq!(summary, "session_grant grant exec /usr/bin/systemctl status **");
+        assert_eq!(reason, "triage");
+
+        let unbounded = SessionGrantAction { ttl_sec: None, ..grant };
+        assert!(session_grant_plan("sess-1", unbounded).is_err());
+    }
+}
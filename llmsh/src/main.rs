@@ -5,10 +5,16 @@ use clap::{Parser, Subcommand};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::UnixStream;
 
+use llm_os_common::{ActionPlan, ActionPlanResult};
+use llmsh::policy_cases::{render_outcomes, run_cases};
 use llmsh::{
-    apply_overrides, parse_and_validate_for_send, parse_and_validate_for_send_with_overrides,
-    validate_verdict,
+    apply_overrides, approval_plan, attach_confirmation, parse_and_validate_for_explain,
+    parse_and_validate_for_send, parse_and_validate_for_send_with_overrides, pending_confirmations,
+    render_confirmation_prompt, render_explanations, session_grant_plan, settled_approval, validate_verdict,
 };
+use llm_os_common::{ApprovalOp, ApprovalState, ExecGrant, GrantScope, PathGrant, SessionGrantAction, SessionGrantOp};
+
+mod tty;
 
 #[derive(Debug, Parser)]
 #[command(name = "llmsh")]
@@ -52,6 +58,137 @@ enum Command {
         #[arg(long)]
         json: Option<String>,
     },
+    Policy {
+        #[command(subcommand)]
+        command: PolicyCommand,
+    },
+    Approvals {
+        #[command(subcommand)]
+        command: ApprovalsCommand,
+    },
+    Session {
+        #[command(subcommand)]
+        command: SessionCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum SessionCommand {
+    Grants {
+        #[arg(long, default_value = "/tmp/llm-osd.sock")]
+        socket_path: String,
+
+        #[arg(long)]
+        session_id: String,
+    },
+    // pre-confirms matching actions in one session; the grant itself is confirmed on the terminal.
+    Grant {
+        #[arg(long, default_value = "/tmp/llm-osd.sock")]
+        socket_path: String,
+
+        #[arg(long)]
+        session_id: String,
+
+        #[arg(long, conflicts_with_all = ["read_root", "write_root"])]
+        exec: Option<String>,
+
+        // one glob per argument, repeated in order; a final `**` matches any remaining arguments.
+        #[arg(long = "arg", requires = "exec")]
+        args: Vec<String>,
+
+        #[arg(long, conflicts_with = "write_root")]
+        read_root: Option<String>,
+
+        #[arg(long)]
+        write_root: Option<String>,
+
+        #[arg(long, default_value_t = 900)]
+        ttl_sec: u64,
+
+        #[arg(long)]
+        note: Option<String>,
+    },
+    Revoke {
+        #[arg(long, default_value = "/tmp/llm-osd.sock")]
+        socket_path: String,
+
+        #[arg(long)]
+        session_id: String,
+
+        grant_id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ApprovalsCommand {
+    List {
+        #[arg(long, default_value = "/tmp/llm-osd.sock")]
+        socket_path: String,
+    },
+    Show {
+        #[arg(long, default_value = "/tmp/llm-osd.sock")]
+        socket_path: String,
+
+        request_id: String,
+    },
+    Approve {
+        #[arg(long, default_value = "/tmp/llm-osd.sock")]
+        socket_path: String,
+
+        request_id: String,
+
+        #[arg(long)]
+        note: Option<String>,
+    },
+    Reject {
+        #[arg(long, default_value = "/tmp/llm-osd.sock")]
+        socket_path: String,
+
+        request_id: String,
+
+        #[arg(long)]
+        note: Option<String>,
+    },
+    // polls `show` until the parked plan is rejected, expired, or approved and executed.
+    Wait {
+        #[arg(long, default_value = "/tmp/llm-osd.sock")]
+        socket_path: String,
+
+        request_id: String,
+
+        #[arg(long, default_value_t = 600)]
+        timeout_sec: u64,
+
+        #[arg(long, default_value_t = 2)]
+        interval_sec: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum PolicyCommand {
+    Explain {
+        #[arg(long, default_value = "/tmp/llm-osd.sock")]
+        socket_path: String,
+
+        #[arg(long)]
+        request_id: Option<String>,
+
+        #[arg(long)]
+        session_id: Option<String>,
+
+        #[arg(long)]
+        file: Option<String>,
+
+        #[arg(long)]
+        json: Option<String>,
+    },
+    Test {
+        #[arg(long)]
+        policy: Option<String>,
+
+        #[arg(long)]
+        cases: String,
+    },
 }
 
 #[tokio::main]
@@ -74,7 +211,17 @@ async fn main() -> anyhow::Result<()> {
             )?;
             let canonical = serde_json::to_string(&plan)?;
             let response = send(&socket_path, &canonical).await?;
+            let response = match confirm_on_tty(&plan, &response)? {
+                Some(confirmed) => send(&socket_path, &serde_json::to_string(&confirmed)?).await?,
+                None => response,
+            };
             print!("{response}");
+            if let Ok(ActionPlanResult { approval: Some(status), .. }) = serde_json::from_str(&response) {
+                eprintln!(
+                    "parked for approval by another user; run `llmsh approvals wait {}` to follow it",
+                    status.request_id
+                );
+            }
         }
         Command::Ping {
             socket_path,
@@ -101,11 +248,187 @@ async fn main() -> anyhow::Result<()> {
             let verdict = validate_verdict(&input);
             print!("{}", serde_json::to_string_pretty(&verdict)?);
         }
+        Command::Policy {
+            command:
+                PolicyCommand::Explain {
+                    socket_path,
+                    request_id,
+                    session_id,
+                    file,
+                    json,
+                },
+        } => {
+            let input = read_input(file.as_deref(), json.as_deref()).await?;
+            let plan = parse_and_validate_for_explain(
+                &input,
+                request_id.as_deref(),
+                session_id.as_deref(),
+            )?;
+            let canonical = serde_json::to_string(&plan)?;
+            let response = send(&socket_path, &canonical).await?;
+            let response: llm_os_common::ActionPlanResult = serde_json::from_str(&response)?;
+            print!("{}", render_explanations(&response)?);
+        }
+        Command::Policy {
+            command: PolicyCommand::Test { policy, cases },
+        } => {
+            let policy = match &policy {
+                Some(path) => llm_os_policy::Policy::load(path)?,
+                None => llm_os_policy::Policy::default(),
+            };
+            let input = tokio::fs::read_to_string(&cases).await?;
+            let outcomes = run_cases(&policy, &input)?;
+            print!("{}", render_outcomes(&outcomes));
+            let failed = outcomes.iter().filter(|o| !o.passed()).count();
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{failed} of {} policy cases failed", outcomes.len()));
+            }
+        }
+        Command::Approvals { command } => run_approvals(command).await?,
+        Command::Session { command } => run_session(command).await?,
     }
 
     Ok(())
 }
 
+async fn run_approvals(command: ApprovalsCommand) -> anyhow::Result<()> {
+    let (socket_path, plan) = match command {
+        ApprovalsCommand::List { socket_path } => (socket_path, approval_plan(ApprovalOp::List, None, None)?),
+        ApprovalsCommand::Show { socket_path, request_id } => {
+            (socket_path, approval_plan(ApprovalOp::Show, Some(&request_id), None)?)
+        }
+        ApprovalsCommand::Approve {
+            socket_path,
+            request_id,
+            note,
+        } => (socket_path, approval_plan(ApprovalOp::Approve, Some(&request_id), note.as_deref())?),
+        ApprovalsCommand::Reject {
+            socket_path,
+            request_id,
+            note,
+        } => (socket_path, approval_plan(ApprovalOp::Reject, Some(&request_id), note.as_deref())?),
+        ApprovalsCommand::Wait {
+            socket_path,
+            request_id,
+            timeout_sec,
+            interval_sec,
+        } => return wait_for_approval(&socket_path, &request_id, timeout_sec, interval_sec).await,
+    };
+    let response = send(&socket_path, &serde_json::to_string(&plan)?).await?;
+    print!("{response}");
+    Ok(())
+}
+
+async fn run_session(command: SessionCommand) -> anyhow::Result<()> {
+    let grant = |op, scope, ttl_sec, grant_id, note| SessionGrantAction {
+        op,
+        scope,
+        ttl_sec,
+        grant_id,
+        note,
+    };
+    let (socket_path, plan) = match command {
+        SessionCommand::Grants { socket_path, session_id } => {
+            let list = grant(SessionGrantOp::List, None, None, None, None);
+            (socket_path, session_grant_plan(&session_id, list)?)
+        }
+        SessionCommand::Grant {
+            socket_path,
+            session_id,
+            exec,
+            args,
+            read_root,
+            write_root,
+            ttl_sec,
+            note,
+        } => {
+            let scope = match (exec, read_root, write_root) {
+                (Some(program), None, None) => GrantScope::Exec(ExecGrant {
+                    program,
+                    args: (!args.is_empty()).then_some(args),
+                }),
+                (None, Some(root), None) => GrantScope::ReadFile(PathGrant { root }),
+                (None, None, Some(root)) => GrantScope::WriteFile(PathGrant { root }),
+                _ => return Err(anyhow::anyhow!("pass exactly one of --exec, --read-root or --write-root")),
+            };
+            let action = grant(SessionGrantOp::Grant, Some(scope), Some(ttl_sec), None, note);
+            (socket_path, session_grant_plan(&session_id, action)?)
+        }
+        SessionCommand::Revoke {
+            socket_path,
+            session_id,
+            grant_id,
+        } => {
+            let revoke = grant(SessionGrantOp::Revoke, None, None, Some(grant_id), None);
+            (socket_path, session_grant_plan(&session_id, revoke)?)
+        }
+    };
+    let response = send(&socket_path, &serde_json::to_string(&plan)?).await?;
+    let response = match confirm_on_tty(&plan, &response)? {
+        Some(confirmed) => send(&socket_path, &serde_json::to_string(&confirmed)?).await?,
+        None => response,
+    };
+    print!("{response}");
+    Ok(())
+}
+
+// prints the final `show` response; rejected and expired plans exit non-zero.
+async fn wait_for_approval(socket_path: &str, request_id: &str, timeout_sec: u64, interval_sec: u64) -> anyhow::Result<()> {
+    let plan = serde_json::to_string(&approval_plan(ApprovalOp::Show, Some(request_id), None)?)?;
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(timeout_sec);
+    loop {
+        let response = send(socket_path, &plan).await?;
+        let result: ActionPlanResult = serde_json::from_str(&response)?;
+        if let Some(status) = settled_approval(&result)? {
+            print!("{response}");
+            return match status.state {
+                ApprovalState::Approved => Ok(()),
+                _ => Err(anyhow::anyhow!("{request_id} was not approved")),
+            };
+        }
+        if std::time::Instant::now() >= deadline {
+            return Err(anyhow::anyhow!("{request_id} is still waiting after {timeout_sec}s"));
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(interval_sec.max(1))).await;
+    }
+}
+
+// when the daemon asks for confirmation, ask the human on the controlling terminal and return the
+// plan to resend; stdin is never read here, so whatever piped the plan in cannot approve it.
+fn confirm_on_tty(plan: &ActionPlan, response: &str) -> anyhow::Result<Option<ActionPlan>> {
+    let result: ActionPlanResult = match serde_json::from_str(response) {
+        Ok(result) => result,
+        Err(_) => return Ok(None),
+    };
+    let challenge = match &result.challenge {
+        Some(challenge) => challenge,
+        None => return Ok(None),
+    };
+    let pending = pending_confirmations(plan, &result);
+    if pending.is_empty() {
+        return Ok(None);
+    }
+    let mut tty = match tty::Tty::open() {
+        Ok(tty) => tty,
+        Err(_) => {
+            eprintln!("confirmation required but no controlling terminal is available");
+            return Ok(None);
+        }
+    };
+
+    tty.say(&render_confirmation_prompt(&pending))?;
+    if !tty.confirm("approve and resend this plan?")? {
+        tty.say("not confirmed\n")?;
+        return Ok(None);
+    }
+    // the plan was written by whoever produced it, so a token inside it proves nothing about the operator.
+    if plan.confirmation.is_some() {
+        tty.say("ignoring the confirmation token carried in the plan\n")?;
+    }
+    let token = tty.ask_secret("confirmation token: ")?;
+    Ok(Some(attach_confirmation(plan.clone(), &token, &challenge.nonce)))
+}
+
 async fn read_input(file: Option<&str>, json: Option<&str>) -> anyhow::Result<String> {
     if let Some(json) = json {
         return Ok(json.to_string());
@@ -0,0 +1,176 @@
+// ABOUTME: evaluates policy test cases offline against the same engine the daemon uses.
+// ABOUTME: each jsonl case pairs a plan and a peer identity with the decisions expected per action.
+
+use llm_os_common::{validate_action_plan, ActionPlan, PolicyDecision};
+use llm_os_policy::{action_name, Peer, Policy};
+use serde::Deserialize;
+
+use crate::decision_name;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Case {
+    name: String,
+    #[serde(default)]
+    peer: Option<Peer>,
+    plan: ActionPlan,
+    expect: Expectation,
+}
+
+// either one decision per action, or the request-level rejection for peers no profile admits.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Expectation {
+    Decisions(Vec<PolicyDecision>),
+    Rejected(Rejection),
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Rejection {
+    NoMatchingProfile,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CaseOutcome {
+    pub line: usize,
+    pub name: String,
+    pub diffs: Vec<String>,
+}
+
+impl CaseOutcome {
+    pub fn passed(&self) -> bool {
+        self.diffs.is_empty()
+    }
+}
+
+// blank lines and lines starting with `#` are skipped so case files can be annotated.
+pub fn run_cases(policy: &Policy, input: &str) -> anyhow::Result<Vec<CaseOutcome>> {
+    let mut outcomes = Vec::new();
+    for (index, line) in input.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let line = index + 1;
+        let case: Case = serde_json::from_str(trimmed)
+            .map_err(|err| anyhow::anyhow!("case on line {line}: {err}"))?;
+        validate_action_plan(&case.plan)
+            .map_err(|err| anyhow::anyhow!("case on line {line}: validation failed: {}", err.message))?;
+        outcomes.push(CaseOutcome {
+            line,
+            name: case.name.clone(),
+            diffs: evaluate(policy, &case),
+        });
+    }
+    Ok(outcomes)
+}
+
+fn evaluate(policy: &Policy, case: &Case) -> Vec<String> {
+    let expected = match &case.expect {
+        Expectation::Rejected(Rejection::NoMatchingProfile) => {
+            return match policy.for_peer(case.peer.as_ref()) {
+                Err(_) => vec![],
+                Ok(selected) => {
+                    let profile = selected.active_profile.as_deref().unwrap_or("the base policy");
+                    vec![format!("expected no_matching_profile, got {profile}")]
+                }
+            };
+        }
+        Expectation::Decisions(expected) => expected,
+    };
+    let policy = match policy.for_peer(case.peer.as_ref()) {
+        Ok(selected) => selected,
+        Err(err) => return vec![format!("expected decisions, got no_matching_profile: {err}")],
+    };
+
+    if expected.len() != case.plan.actions.len() {
+        return vec![format!(
+            "expected {} decisions for {} actions",
+            expected.len(),
+            case.plan.actions.len()
+        )];
+    }
+
+    let mut diffs = Vec::new();
+    for (index, (action, want)) in case.plan.actions.iter().zip(expected).enumerate() {
+        let verdict = policy.action_verdict(action);
+        let got: PolicyDecision = verdict.decision().into();
+        if &got != want {
+            let rule = verdict
+                .decisive()
+                .map(|step| step.rule_id.as_str())
+                .unwrap_or("no rule");
+            diffs.push(format!(
+                "[{index}] {}: expected {}, got {} ({rule})",
+                action_name(action),
+                decision_name(want),
+                decision_name(&got)
+            ));
+        }
+    }
+    diffs
+}
+
+// one line per case, with the differing actions indented under failures.
+pub fn render_outcomes(outcomes: &[CaseOutcome]) -> String {
+    let mut out = String::new();
+    for outcome in outcomes {
+        let status = if outcome.passed() { "ok  " } else { "FAIL" };
+        out.push_str(&format!("{status} {} (line {})\n", outcome.name, outcome.line));
+        for diff in &outcome.diffs {
+            out.push_str(&format!("    {diff}\n"));
+        }
+    }
+    let failed = outcomes.iter().filter(|o| !o.passed()).count();
+    out.push_str(&format!("{} cases, {failed} failed\n", outcomes.len()));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> Policy {
+        serde_json::from_str(
+            r#"{"profiles":[{"name":"ops","gids":[2000],"service_control":{"default":"allow"}}]}"#,
+        )
+        .unwrap()
+    }
+
+    fn case(name: &str, peer: &str, actions: &str, expect: &str) -> String {
+        format!(
+            r#"{{"name":"{name}","peer":{peer},"plan":{{"request_id":"req-1","version":"0.1","mode":"execute","actions":[{actions}]}},"expect":{expect}}}"#
+        )
+    }
+
+    #[test]
+    fn matching_cases_pass_and_mismatches_report_the_rule() {
+        let svc = r#"{"type":"service_control","action":"restart","unit":"nginx.service","reason":"test","danger":null,"recovery":null}"#;
+        let input = [
+            "# annotated case file".to_string(),
+            case("ops restart", r#"{"uid":1000,"gid":2000}"#, svc, r#"["allow"]"#),
+            String::new(),
+            case("ops ping", r#"{"uid":1000,"gid":1000,"groups":[2000]}"#, r#"{"type":"ping"}"#, r#"["deny"]"#),
+            case("stranger", r#"{"uid":70000,"gid":70000}"#, svc, r#""no_matching_profile""#),
+        ]
+        .join("\n");
+
+        let outcomes = run_cases(&policy(), &input).unwrap();
+        assert_eq!(outcomes.len(), 3);
+        assert!(outcomes[0].passed());
+        assert_eq!(outcomes[1].line, 4);
+        assert_eq!(outcomes[1].diffs, vec!["[0] ping: expected deny, got allow (no rule)".to_string()]);
+        assert!(outcomes[2].passed());
+
+        let rendered = render_outcomes(&outcomes);
+        assert!(rendered.contains("FAIL ops ping (line 4)\n    [0] ping: expected deny, got allow (no rule)\n"));
+        assert!(rendered.ends_with("3 cases, 1 failed\n"));
+    }
+
+    #[test]
+    fn malformed_cases_are_errors() {
+        let err = run_cases(&policy(), "{\"name\":\"x\"}").unwrap_err();
+        assert!(err.to_string().contains("case on line 1"));
+    }
+}
@@ -0,0 +1,54 @@
+// ABOUTME: asks the human at the controlling terminal, bypassing stdin and stdout entirely.
+// ABOUTME: a model piping plans into llmsh cannot answer these prompts.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::io::AsRawFd;
+
+pub struct Tty {
+    file: File,
+}
+
+impl Tty {
+    // fails when the process has no controlling terminal (cron, ci, a detached model runner).
+    pub fn open() -> std::io::Result<Tty> {
+        let file = std::fs::OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        Ok(Tty { file })
+    }
+
+    pub fn confirm(&mut self, prompt: &str) -> std::io::Result<bool> {
+        let answer = self.ask(&format!("{prompt} [y/N] "))?;
+        Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+    }
+
+    pub fn ask(&mut self, prompt: &str) -> std::io::Result<String> {
+        self.file.write_all(prompt.as_bytes())?;
+        self.file.flush()?;
+        let mut line = String::new();
+        BufReader::new(&self.file).read_line(&mut line)?;
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+
+    // reads a line with terminal echo turned off, restoring the previous mode afterwards.
+    pub fn ask_secret(&mut self, prompt: &str) -> std::io::Result<String> {
+        let fd = self.file.as_raw_fd();
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd, &mut saved) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut silent = saved;
+        silent.c_lflag &= !libc::ECHO;
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &silent) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let answer = self.ask(prompt);
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &saved) };
+        self.file.write_all(b"\n")?;
+        answer
+    }
+
+    pub fn say(&mut self, text: &str) -> std::io::Result<()> {
+        self.file.write_all(text.as_bytes())?;
+        self.file.flush()
+    }
+}